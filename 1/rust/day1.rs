@@ -1,40 +1,61 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+#[path = "../../solver/rust/solver.rs"]
+mod solver;
+mod fuel;
 
-const CONSIDER_FUEL_WEIGHT: bool = true;
+use std::env;
+use fuel::{get_fuel, get_fuel_for_fuel, json_report, Module};
+use solver::{read_input, Solver};
 
-fn get_fuel(mass: u32) -> u32 {
-    let fuel = mass as i32 / 3 - 2;
-    return if fuel > 0 { fuel as u32 } else { 0 };
-}
+struct Day01;
 
-fn get_fuel_for_fuel(fuel: u32) -> u32 {
-    let mut total_fuel = 0;
-    let mut extra_fuel = fuel;
-    loop {
-        extra_fuel = get_fuel(extra_fuel);
-        if extra_fuel > 0 {
-            total_fuel += extra_fuel;
-        } else {
-            return total_fuel;
-        }
+impl Solver for Day01 {
+    type Input = Vec<u32>; // masses
+    type Output = u32;
+
+    fn parse(text: &str) -> Vec<u32> {
+        return text.lines().map(|line| line.trim()).filter(|line| line.len() > 0)
+            .map(|line| line.parse::<u32>().unwrap_or_else(|_| panic!("Invalid mass '{}'", line)))
+            .collect();
+    }
+
+    fn part1(masses: &Vec<u32>) -> u32 {
+        return masses.iter().map(|&mass| get_fuel(mass)).sum();
+    }
+
+    fn part2(masses: &Vec<u32>) -> u32 {
+        return masses.iter().map(|&mass| get_fuel(mass) + get_fuel_for_fuel(get_fuel(mass))).sum();
     }
 }
 
+// day1 [input file, - for stdin] [--json]
 fn main() {
-    let file = File::open("../input").unwrap();
-    let reader = BufReader::new(file);
-
-    let mut total_fuel = 0;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let mass = line.parse::<u32>().unwrap();
-        let fuel = get_fuel(mass);
-        total_fuel += fuel;
-        if CONSIDER_FUEL_WEIGHT {
-            total_fuel += get_fuel_for_fuel(fuel);
+    let masses = Day01::parse(&read_input());
+    if env::args().any(|arg| arg == "--json") {
+        let modules: Vec<Module> = masses.iter().map(|&mass| Module::new(mass)).collect();
+        print!("{}", json_report(&modules));
+    } else {
+        println!("Fuel for the modules: {}", Day01::part1(&masses));
+        println!("Total fuel: {}", Day01::part2(&masses));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solver::solve;
+
+    #[test]
+    fn part1() {
+        for &(mass, fuel) in [(12, 2), (14, 2), (1969, 654), (100756, 33583)].iter() {
+            assert_eq!(Day01::part1(&Day01::parse(&mass.to_string())), fuel);
         }
     }
 
-    println!("Total fuel: {}", total_fuel);
+    #[test]
+    fn part2() {
+        for &(mass, fuel) in [(12, 2), (14, 2), (1969, 966), (100756, 50346)].iter() {
+            assert_eq!(Day01::part2(&Day01::parse(&mass.to_string())), fuel);
+        }
+        assert_eq!(solve::<Day01>("12\n14\n\n"), (4, 4));
+    }
 }
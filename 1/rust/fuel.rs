@@ -0,0 +1,82 @@
+pub fn get_fuel(mass: u32) -> u32 {
+    let fuel = mass as i32 / 3 - 2;
+    return if fuel > 0 { fuel as u32 } else { 0 };
+}
+
+// The fuel needs fuel too, and that fuel needs fuel...
+pub fn get_fuel_for_fuel(fuel: u32) -> u32 {
+    let mut total_fuel = 0;
+    let mut extra_fuel = fuel;
+    loop {
+        extra_fuel = get_fuel(extra_fuel);
+        if extra_fuel > 0 {
+            total_fuel += extra_fuel;
+        } else {
+            return total_fuel;
+        }
+    }
+}
+
+pub struct Module {
+    pub mass: u32,
+    pub fuel: u32,
+    pub recursive_fuel: u32, // including the fuel for the fuel
+}
+
+impl Module {
+    pub fn new(mass: u32) -> Module {
+        let fuel = get_fuel(mass);
+        return Module {
+            mass: mass,
+            fuel: fuel,
+            recursive_fuel: fuel + get_fuel_for_fuel(fuel),
+        };
+    }
+}
+
+// One module per line, so it's still readable
+pub fn json_report(modules: &[Module]) -> String {
+    let entries: Vec<String> = modules.iter()
+        .map(|m| format!("    {{\"mass\": {}, \"fuel\": {}, \"recursive_fuel\": {}}}", m.mass, m.fuel, m.recursive_fuel))
+        .collect();
+    let fuel: u32 = modules.iter().map(|m| m.fuel).sum();
+    let recursive_fuel: u32 = modules.iter().map(|m| m.recursive_fuel).sum();
+    return format!("{{\n  \"modules\": [\n{}\n  ],\n  \"total_fuel\": {},\n  \"total_recursive_fuel\": {}\n}}\n",
+        entries.join(",\n"), fuel, recursive_fuel);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuel() {
+        assert_eq!(get_fuel(12), 2);
+        assert_eq!(get_fuel(14), 2);
+        assert_eq!(get_fuel(1969), 654);
+        assert_eq!(get_fuel(100756), 33583);
+        assert_eq!(get_fuel(1), 0);
+    }
+
+    #[test]
+    fn fuel_for_fuel() {
+        assert_eq!(get_fuel_for_fuel(get_fuel(14)), 0);
+        assert_eq!(get_fuel(1969) + get_fuel_for_fuel(get_fuel(1969)), 966);
+        assert_eq!(get_fuel(100756) + get_fuel_for_fuel(get_fuel(100756)), 50346);
+    }
+
+    #[test]
+    fn report() {
+        let report = json_report(&[Module::new(14), Module::new(1969)]);
+        assert_eq!(report, "{
+  \"modules\": [
+    {\"mass\": 14, \"fuel\": 2, \"recursive_fuel\": 2},
+    {\"mass\": 1969, \"fuel\": 654, \"recursive_fuel\": 966}
+  ],
+  \"total_fuel\": 656,
+  \"total_recursive_fuel\": 968
+}
+");
+        assert!(json_report(&[]).contains("\"modules\": [\n\n  ]"));
+    }
+}
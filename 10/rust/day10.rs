@@ -1,18 +1,16 @@
-use std::io::{BufRead, BufReader};
-use std::fs::File;
+use std::fs;
 use std::cmp;
-use std::cmp::Ordering::Equal;
-use std::f64::consts::{PI, FRAC_PI_2};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 
 type BoolGrid = Vec<Vec<bool>>;
 
-fn load_asteroid_map(filename: &str) -> BoolGrid {
-    let file = BufReader::new(File::open(filename).expect("open failed"));
+fn parse_asteroid_map(text: &str) -> BoolGrid {
     let mut map: BoolGrid = Vec::new();
-    for line in file.lines() {
+    for line in text.lines().map(|l| l.trim()).filter(|l| l.len() > 0) {
         map.push(Vec::new());
         let last_idx = map.len() - 1;
-        for c in line.expect("lines failed").chars() {
+        for c in line.chars() {
             map[last_idx].push(match c {
                 '.' => false,
                 '#' => true,
@@ -26,71 +24,26 @@ fn load_asteroid_map(filename: &str) -> BoolGrid {
     return map;
 }
 
-fn int_normalize(num: i64, denom: i64) -> (i64, i64) {
-    assert!(num != 0 || denom != 0);
-    let min = cmp::min(num.abs(), denom.abs());
-    let max = cmp::max(num.abs(), denom.abs());
-    if num == 0 || denom == 0 {
-        return (num / max, denom / max);
-    }
-    let mut new_num = num;
-    let mut new_denom = denom;
-    for factor in 2..min+1 { // max is dumb and inefficient, but not trivially sufficient
-        while new_num % factor == 0 && new_denom % factor == 0 {
-            new_num /= factor;
-            new_denom /= factor;
-        }
-    }
-    return (new_num, new_denom);
+fn load_asteroid_map(filename: &str) -> BoolGrid {
+    return parse_asteroid_map(&fs::read_to_string(filename).expect("open failed"));
 }
 
-fn get_visibility_map(asteroid_map: &BoolGrid, view_x: usize, view_y: usize) -> BoolGrid {
-    let mut vis_map = BoolGrid::new();
-    // Init map all visible
-    for y in 0..asteroid_map.len() {
-        let mut row: Vec<bool> = Vec::new();
-        row.resize(asteroid_map[y].len(), true);
-        vis_map.push(row);
-    }
-
-    // Find all obstacles
-    for y in 0..asteroid_map.len() {
-        for x in 0..asteroid_map[y].len() {
-            if asteroid_map[y][x] && vis_map[y][x] && (x != view_x || y != view_y) { // If obstacle and still visible
-                //println!("Start walk from {}, {}", x, y);
-                // Walk along the line of sight and mark as not visible
-                let rel_x = x as i64 - view_x as i64;
-                let rel_y = y as i64 - view_y as i64;
-                let (dir_x, dir_y) = int_normalize(rel_x, rel_y);
-                assert!(dir_x != 0 || dir_y != 0);
-                let mut cur_x = x;
-                let mut cur_y = y;
-                loop {
-                    let next_x = cur_x as i64 + dir_x;
-                    let next_y = cur_y as i64 + dir_y;
-                    if next_x < 0 || next_x as usize >= asteroid_map[y].len() || next_y < 0 || next_y as usize >= asteroid_map.len() {
-                        break;
-                    }
-                    cur_x = next_x as usize;
-                    cur_y = next_y as usize;
-                    //println!("Set invis {}, {}", cur_x, cur_y);
-                    vis_map[cur_y][cur_x] = false;
-                }
-            }
-        }
-    }
+fn gcd(a: i64, b: i64) -> i64 {
+    return if b == 0 { a.abs() } else { gcd(b, a % b) };
+}
 
-    return vis_map;
+// The smallest step in the same direction, e.g. (-3, 9) -> (-1, 3)
+fn int_normalize(num: i64, denom: i64) -> (i64, i64) {
+    assert!(num != 0 || denom != 0);
+    let divisor = gcd(num, denom);
+    return (num / divisor, denom / divisor);
 }
 
-fn get_visible_asteroids(asteroid_map: &BoolGrid, vis_map: &BoolGrid) -> Vec<(usize, usize)> {
-    // I'm sure this can be done in some nice functional way or something
+fn get_asteroids(map: &BoolGrid) -> Vec<(usize, usize)> {
     let mut asteroids: Vec<(usize, usize)> = Vec::new();
-    assert!(asteroid_map.len() == vis_map.len());
-    for y in 0..asteroid_map.len() {
-        assert!(asteroid_map[y].len() == vis_map[y].len());
-        for x in 0..asteroid_map[y].len() {
-            if asteroid_map[y][x] && vis_map[y][x] {
+    for y in 0..map.len() {
+        for x in 0..map[y].len() {
+            if map[y][x] {
                 asteroids.push((x, y));
             }
         }
@@ -98,6 +51,19 @@ fn get_visible_asteroids(asteroid_map: &BoolGrid, vis_map: &BoolGrid) -> Vec<(us
     return asteroids;
 }
 
+// The other asteroids grouped by their direction from the station. Only the closest one
+// in every direction is visible, so the number of directions is the number of visible asteroids.
+fn group_by_direction(asteroids: &[(usize, usize)], view_x: usize, view_y: usize) -> HashMap<(i64, i64), Vec<(usize, usize)>> {
+    let mut groups: HashMap<(i64, i64), Vec<(usize, usize)>> = HashMap::new();
+    for &asteroid in asteroids {
+        if asteroid != (view_x, view_y) {
+            let (dx, dy) = relative(asteroid, view_x, view_y);
+            groups.entry(int_normalize(dx, dy)).or_insert(Vec::new()).push(asteroid);
+        }
+    }
+    return groups;
+}
+
 fn print_map(map: &BoolGrid, true_str: &str, false_str: &str) {
     for y in 0..map.len() {
         for x in 0..map[y].len() {
@@ -129,21 +95,68 @@ fn print_asteroids(asteroids: &Vec<(usize, usize)>) {
     print_map(&map, "#", ".");
 }
 
-fn norm_angle(angle: f64) -> f64 {
-    let mut a = angle;
-    while a > 2.0 * PI {
-        a -= 2.0 * PI;
+// 0 for straight up and everything right of it, 1 for straight down and everything left
+fn half(dx: i64, dy: i64) -> u8 {
+    return if dx > 0 || (dx == 0 && dy < 0) { 0 } else { 1 };
+}
+
+// Orders directions (not (0, 0)) clockwise starting straight up, y points down like in
+// the map. Exact, unlike comparing angles from atan2: in the same half b comes later if
+// it is clockwise from a, which is what the sign of the cross product says.
+fn compare_directions(a: (i64, i64), b: (i64, i64)) -> Ordering {
+    assert!(a != (0, 0) && b != (0, 0), "(0, 0) has no direction");
+    let (half_a, half_b) = (half(a.0, a.1), half(b.0, b.1));
+    if half_a != half_b {
+        return half_a.cmp(&half_b);
     }
-    while a < 0.0 {
-        a += 2.0 * PI;
+    let cross = a.0 * b.1 - a.1 * b.0;
+    return 0.cmp(&cross);
+}
+
+fn relative(asteroid: (usize, usize), view_x: usize, view_y: usize) -> (i64, i64) {
+    return (asteroid.0 as i64 - view_x as i64, asteroid.1 as i64 - view_y as i64);
+}
+
+// (visible asteroids, x, y). Every asteroid is tried as the station, which is O(n) with
+// the hash map, so O(n²) in total.
+fn best_station(map: &BoolGrid) -> (usize, usize, usize) {
+    let asteroids = get_asteroids(map);
+    let mut max_vis = 0;
+    let mut max_vis_x = 0;
+    let mut max_vis_y = 0;
+    for &(x, y) in asteroids.iter() {
+        let visible_count = group_by_direction(&asteroids, x, y).len();
+        if visible_count > max_vis {
+            max_vis = visible_count;
+            max_vis_x = x;
+            max_vis_y = y;
+        }
     }
-    return a;
+    return (max_vis, max_vis_x, max_vis_y);
 }
 
-fn get_pos_angle(x: usize, y: usize, view_x: usize, view_y: usize) -> f64 {
-    let rel_x = (x as i64 - view_x as i64) as f64;
-    let rel_y = (y as i64 - view_y as i64) as f64;
-    return norm_angle(rel_y.atan2(rel_x) + FRAC_PI_2);
+// Every asteroid but the station, in the order the laser vaporizes them: the directions
+// clockwise (sorting them is the log n), the closest asteroid in each direction per turn
+fn vaporization_order(map: &BoolGrid, view_x: usize, view_y: usize) -> Vec<(usize, usize)> {
+    let mut groups: Vec<((i64, i64), Vec<(usize, usize)>)> = group_by_direction(&get_asteroids(map), view_x, view_y).into_iter().collect();
+    groups.sort_by(|a, b| compare_directions(a.0, b.0));
+    for &mut (_, ref mut group) in groups.iter_mut() {
+        // farthest first, so the next one to vaporize can be popped
+        group.sort_by_key(|&asteroid| {
+            let (dx, dy) = relative(asteroid, view_x, view_y);
+            return cmp::Reverse(dx.abs() + dy.abs());
+        });
+    }
+    let total = groups.iter().map(|&(_, ref group)| group.len()).sum();
+    let mut order = Vec::with_capacity(total);
+    while order.len() < total {
+        for &mut (_, ref mut group) in groups.iter_mut() {
+            if let Some(asteroid) = group.pop() {
+                order.push(asteroid);
+            }
+        }
+    }
+    return order;
 }
 
 fn main() {
@@ -151,36 +164,71 @@ fn main() {
     println!("Asteroid map:");
     print_map(&map, "#", ".");
 
-    let mut max_vis = 0;
-    let mut max_vis_x = 0;
-    let mut max_vis_y = 0;
-    let (_x, _y) = int_normalize(-3, 9);
-    for y in 0..map.len() {
-        for x in 0..map[y].len() {
-            if map[y][x] { // Asteroid
-                let vis_map = get_visibility_map(&map, x, y);
-                //print_map(&vis_map, " ", "X");
-                let visible_count = get_visible_asteroids(&map, &vis_map).len() - 1; // -1 for OTHER asteroids
-                if visible_count > max_vis {
-                    max_vis = visible_count; 
-                    max_vis_x = x;
-                    max_vis_y = y;
-                }
+    let (max_vis, max_vis_x, max_vis_y) = best_station(&map);
+    println!("Max {} asteroids visible from {}, {}", max_vis, max_vis_x, max_vis_y);
+
+    let order = vaporization_order(&map, max_vis_x, max_vis_y);
+    println!("Vaporized in the first turn:");
+    print_asteroids(&order[..max_vis].to_vec());
+    println!("in order: {:?}", order);
+    println!("1st: {:?}", order[0]);
+    for &n in [200, 201].iter() {
+        match order.get(n - 1) {
+            Some(asteroid) => println!("{}th vaporized asteroid: {:?}", n, asteroid),
+            None => println!("There are only {} asteroids to vaporize", order.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions() {
+        // clockwise from straight up, 8 directions around the center and some close to vertical
+        let order = [(0, -1), (1, -1000), (1, -1), (1, 0), (1, 1), (1, 1000), (0, 1), (-1, 1000), (-1, 1), (-1, 0), (-1, -1), (-1, -1000)];
+        for i in 0..order.len() {
+            for j in 0..order.len() {
+                assert_eq!(compare_directions(order[i], order[j]), i.cmp(&j), "{:?} vs {:?}", order[i], order[j]);
             }
         }
+        assert_eq!(compare_directions((2, -4), (1, -2)), Ordering::Equal);
     }
-    println!("Max {} asteroids visible from {}, {}", max_vis, max_vis_x, max_vis_y);
-    
-    let vis_map = get_visibility_map(&map, max_vis_x, max_vis_y);
-    println!("Vis map:");
-    print_map(&vis_map, " ", "X");
-    
-    let mut visible = get_visible_asteroids(&map, &vis_map);
-    println!("Vaporized asteroids:");
-    print_asteroids(&visible);
-    visible.sort_by(|a, b| get_pos_angle(a.0, a.1, max_vis_x, max_vis_y).partial_cmp(&get_pos_angle(b.0, b.1, max_vis_x, max_vis_y)).unwrap_or(Equal));
-    println!("in order: {:?}", visible);
-    println!("1st: {:?}", visible[0]);
-    println!("200th vaporized asteroid: {:?}", visible[199]);
-    println!("201th vaporized asteroid: {:?}", visible[200]);
-}
\ No newline at end of file
+
+    #[test]
+    fn stations() {
+        let map = parse_asteroid_map(".#..#\n.....\n#####\n....#\n...##\n");
+        assert_eq!(best_station(&map), (8, 3, 4));
+        let map = parse_asteroid_map("
+......#.#.
+#..#.#....
+..#######.
+.#.#.###..
+.#..#.....
+..#....#.#
+#..#....#.
+.##.#..###
+##...#..#.
+.#....####
+");
+        assert_eq!(best_station(&map), (33, 5, 8));
+    }
+
+    #[test]
+    fn vaporization() {
+        let map = parse_asteroid_map("
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##
+");
+        assert_eq!(group_by_direction(&get_asteroids(&map), 8, 3).len(), 30);
+        let order = vaporization_order(&map, 8, 3);
+        assert_eq!(order.len(), 36);
+        assert_eq!(order[..9], [(8, 1), (9, 0), (9, 1), (10, 0), (9, 2), (11, 1), (12, 1), (11, 2), (15, 1)]);
+        // the asteroids that were hidden during the first turns
+        assert_eq!(order[30..], [(8, 0), (10, 1), (14, 0), (16, 1), (13, 3), (14, 3)]);
+    }
+}
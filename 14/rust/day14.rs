@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+
+struct Reaction {
+    amount: u64, // of the output
+    inputs: Vec<(String, u64)>,
+}
+
+// chemical -> the reaction that produces it
+type Reactions = HashMap<String, Reaction>;
+
+fn parse_amount(text: &str) -> (String, u64) {
+    let mut parts = text.trim().split_whitespace();
+    let amount = parts.next().and_then(|a| a.parse::<u64>().ok())
+        .unwrap_or_else(|| panic!("Invalid amount in '{}'", text));
+    let chemical = parts.next().unwrap_or_else(|| panic!("Missing chemical in '{}'", text));
+    return (chemical.to_string(), amount);
+}
+
+fn parse_reactions(text: &str) -> Reactions {
+    let mut reactions = Reactions::new();
+    for line in text.lines().filter(|l| l.trim().len() > 0) {
+        let mut sides = line.split("=>");
+        let inputs = sides.next().unwrap().split(',').map(parse_amount).collect();
+        let (output, amount) = parse_amount(sides.next().unwrap_or_else(|| panic!("Missing '=>' in '{}'", line)));
+        if reactions.insert(output.clone(), Reaction { amount: amount, inputs: inputs }).is_some() {
+            panic!("{} is produced by more than one reaction", output);
+        }
+    }
+    return reactions;
+}
+
+// Returns the ore needed. Reactions make more than needed in whole batches, the rest is
+// kept in leftovers and used first the next time the chemical is needed.
+fn produce(reactions: &Reactions, chemical: &str, amount: u64, leftovers: &mut HashMap<String, u64>) -> u64 {
+    if chemical == "ORE" {
+        return amount;
+    }
+    let leftover = leftovers.entry(chemical.to_string()).or_insert(0);
+    let used = amount.min(*leftover);
+    *leftover -= used;
+    let needed = amount - used;
+    if needed == 0 {
+        return 0;
+    }
+    let reaction = reactions.get(chemical).unwrap_or_else(|| panic!("No reaction produces {}", chemical));
+    let batches = (needed + reaction.amount - 1) / reaction.amount;
+    *leftover += batches * reaction.amount - needed;
+    let mut ore = 0;
+    for &(ref input, input_amount) in &reaction.inputs {
+        ore += produce(reactions, input, batches * input_amount, leftovers);
+    }
+    return ore;
+}
+
+fn ore_for_fuel(reactions: &Reactions, fuel: u64) -> u64 {
+    return produce(reactions, "FUEL", fuel, &mut HashMap::new());
+}
+
+// Binary search, the ore needed only grows with the fuel
+fn max_fuel(reactions: &Reactions, ore: u64) -> u64 {
+    let mut low = 0; // always possible
+    let mut high = 1; // impossible once found
+    while ore_for_fuel(reactions, high) <= ore {
+        low = high;
+        high *= 2;
+    }
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if ore_for_fuel(reactions, mid) <= ore {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    return low;
+}
+
+fn main() {
+    let text = fs::read_to_string("../input").expect("Could not read input");
+    let reactions = parse_reactions(&text);
+    println!("Ore for 1 fuel: {}", ore_for_fuel(&reactions, 1));
+    println!("Fuel for a trillion ore: {}", max_fuel(&reactions, 1000000000000));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_1: &'static str = "
+10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL
+";
+
+    const EXAMPLE_2: &'static str = "
+9 ORE => 2 A
+8 ORE => 3 B
+7 ORE => 5 C
+3 A, 4 B => 1 AB
+5 B, 7 C => 1 BC
+4 C, 1 A => 1 CA
+2 AB, 3 BC, 4 CA => 1 FUEL
+";
+
+    const EXAMPLE_3: &'static str = "
+157 ORE => 5 NZVS
+165 ORE => 6 DCFZ
+44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
+12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
+179 ORE => 7 PSHF
+177 ORE => 5 HKGWZ
+7 DCFZ, 7 PSHF => 2 XJWVT
+165 ORE => 2 GPVTF
+3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT
+";
+
+    const EXAMPLE_4: &'static str = "
+2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG
+17 NVRVD, 3 JNWZP => 8 VPVL
+53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL
+22 VJHF, 37 MNCFX => 5 FWMGM
+139 ORE => 4 NVRVD
+144 ORE => 7 JNWZP
+5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC
+5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV
+145 ORE => 6 MNCFX
+1 NVRVD => 8 CXFTF
+1 VJHF, 6 MNCFX => 4 RFSQX
+176 ORE => 6 VJHF
+";
+
+    const EXAMPLE_5: &'static str = "
+171 ORE => 8 CNZTR
+7 ZLQW, 3 BMBT, 9 XCVML, 26 XMNCP, 1 WPTQ, 2 MZWV, 1 RJRHP => 4 PLWSL
+114 ORE => 4 BHXH
+14 VRPVC => 6 BMBT
+6 BHXH, 18 KTJDG, 12 WPTQ, 7 PLWSL, 31 FHTLT, 37 ZDVW => 1 FUEL
+6 WPTQ, 2 BMBT, 8 ZLQW, 18 KTJDG, 1 XMNCP, 6 MZWV, 1 RJRHP => 6 FHTLT
+15 XDBXC, 2 LTCX, 1 VRPVC => 6 ZLQW
+13 WPTQ, 10 LTCX, 3 RJRHP, 14 XMNCP, 2 MZWV, 1 ZLQW => 1 ZDVW
+5 BMBT => 4 WPTQ
+189 ORE => 9 KTJDG
+1 MZWV, 17 XDBXC, 3 XCVML => 2 XMNCP
+12 VRPVC, 27 CNZTR => 2 XDBXC
+15 KTJDG, 12 BHXH => 5 XCVML
+3 BHXH, 2 VRPVC => 7 MZWV
+121 ORE => 7 VRPVC
+7 XCVML => 6 RJRHP
+5 BHXH, 4 VRPVC => 5 LTCX
+";
+
+    #[test]
+    fn ore_for_one_fuel() {
+        assert_eq!(ore_for_fuel(&parse_reactions(EXAMPLE_1), 1), 31);
+        assert_eq!(ore_for_fuel(&parse_reactions(EXAMPLE_2), 1), 165);
+        assert_eq!(ore_for_fuel(&parse_reactions(EXAMPLE_3), 1), 13312);
+        assert_eq!(ore_for_fuel(&parse_reactions(EXAMPLE_4), 1), 180697);
+        assert_eq!(ore_for_fuel(&parse_reactions(EXAMPLE_5), 1), 2210736);
+    }
+
+    #[test]
+    fn fuel_for_a_trillion_ore() {
+        assert_eq!(max_fuel(&parse_reactions(EXAMPLE_3), 1000000000000), 82892753);
+        assert_eq!(max_fuel(&parse_reactions(EXAMPLE_4), 1000000000000), 5586022);
+        assert_eq!(max_fuel(&parse_reactions(EXAMPLE_5), 1000000000000), 460664);
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs;
+
+fn parse_signal(text: &str) -> Vec<i64> {
+    return text.trim().chars()
+        .map(|c| c.to_digit(10).unwrap_or_else(|| panic!("Invalid digit '{}'", c)) as i64)
+        .collect();
+}
+
+fn digits_to_string(digits: &[i64]) -> String {
+    return digits.iter().map(|d| d.to_string()).collect();
+}
+
+// Output element i is the signal times the pattern 0, 1, 0, -1 with every value repeated
+// i + 1 times (and the first value skipped), so it is a sum of every other block of i + 1
+// elements, added and subtracted in turn. With prefix sums every block is one subtraction,
+// which makes a phase O(n log n) instead of O(n^2).
+fn phase(signal: &[i64]) -> Vec<i64> {
+    let n = signal.len();
+    let mut prefix = vec![0; n + 1];
+    for i in 0..n {
+        prefix[i + 1] = prefix[i] + signal[i];
+    }
+    let block_sum = |start: usize, length: usize| prefix[(start + length).min(n)] - prefix[start.min(n)];
+    let mut output = Vec::with_capacity(n);
+    for i in 0..n {
+        let length = i + 1;
+        let mut sum = 0;
+        let mut start = i;
+        while start < n {
+            sum += block_sum(start, length);
+            sum -= block_sum(start + 2 * length, length);
+            start += 4 * length;
+        }
+        output.push(sum.abs() % 10);
+    }
+    return output;
+}
+
+fn fft(signal: &[i64], phases: usize) -> Vec<i64> {
+    let mut signal = signal.to_vec();
+    for _ in 0..phases {
+        signal = phase(&signal);
+    }
+    return signal;
+}
+
+// In the second half of the signal the pattern is 0 before the element and 1 from there
+// on, so every element is the sum of itself and everything after it. The message offset
+// is always in the second half, so only the part from there to the end is computed.
+fn real_message(signal: &[i64], phases: usize) -> String {
+    let offset = signal[..7].iter().fold(0, |acc, &d| acc * 10 + d) as usize;
+    let length = signal.len() * 10000;
+    assert!(offset >= length / 2, "Message offset {} is not in the second half of the signal", offset);
+    let mut tail: Vec<i64> = (offset..length).map(|i| signal[i % signal.len()]).collect();
+    for _ in 0..phases {
+        for i in (0..tail.len() - 1).rev() {
+            tail[i] = (tail[i] + tail[i + 1]) % 10;
+        }
+    }
+    return digits_to_string(&tail[..8]);
+}
+
+fn main() {
+    let text = fs::read_to_string("../input").expect("Could not read input");
+    let signal = parse_signal(&text);
+    println!("First eight digits after 100 phases: {}", digits_to_string(&fft(&signal, 100)[..8]));
+    println!("Message: {}", real_message(&signal, 100));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases() {
+        assert_eq!(digits_to_string(&fft(&parse_signal("12345678"), 4)), "01029498");
+        let examples = [
+            ("80871224585914546619083218645595", "24176176"),
+            ("19617804207202209144916044189917", "73745418"),
+            ("69317163492948606335995924319873", "52432133"),
+        ];
+        for &(signal, expected) in examples.iter() {
+            assert_eq!(digits_to_string(&fft(&parse_signal(signal), 100)[..8]), expected);
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let examples = [
+            ("03036732577212944063491565474664", "84462026"),
+            ("02935109699940807407585447034323", "78725270"),
+            ("03081770884921959731165446850517", "53553731"),
+        ];
+        for &(signal, expected) in examples.iter() {
+            assert_eq!(real_message(&parse_signal(signal), 100), expected);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+#[path = "../../intcode/rust/intcode.rs"]
+mod intcode;
+
+use std::collections::VecDeque;
+use intcode::{from_iter, read_patched_program, AsciiStringSink, FromIter, Patch, Vm, VmBuilder};
+
+const MAX_ROUTINE_LENGTH: usize = 20; // characters, without the newline
+
+type Grid = Vec<Vec<char>>;
+
+fn parse_view(view: &str) -> Grid {
+    return view.lines().filter(|l| l.len() > 0).map(|l| l.chars().collect()).collect();
+}
+
+fn is_scaffold(grid: &Grid, x: i64, y: i64) -> bool {
+    if y < 0 || y as usize >= grid.len() || x < 0 || x as usize >= grid[y as usize].len() {
+        return false;
+    }
+    return grid[y as usize][x as usize] != '.';
+}
+
+fn alignment_parameter_sum(grid: &Grid) -> usize {
+    let mut sum = 0;
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            let (xi, yi) = (x as i64, y as i64);
+            if is_scaffold(grid, xi, yi) && is_scaffold(grid, xi - 1, yi) && is_scaffold(grid, xi + 1, yi)
+                    && is_scaffold(grid, xi, yi - 1) && is_scaffold(grid, xi, yi + 1) {
+                sum += x * y;
+            }
+        }
+    }
+    return sum;
+}
+
+// The whole walk as moves like "R,8", going straight over intersections
+fn find_path(grid: &Grid) -> Vec<String> {
+    let mut position = None;
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            let direction = match grid[y][x] {
+                '^' => (0, -1),
+                'v' => (0, 1),
+                '<' => (-1, 0),
+                '>' => (1, 0),
+                _ => continue,
+            };
+            position = Some((x as i64, y as i64, direction));
+        }
+    }
+    let (mut x, mut y, (mut dx, mut dy)) = position.expect("Robot not found");
+    let mut path = Vec::new();
+    loop {
+        // y points down, so turning left is (dx, dy) -> (dy, -dx)
+        let (turn, new_dx, new_dy) = if is_scaffold(grid, x + dy, y - dx) {
+            ("L", dy, -dx)
+        } else if is_scaffold(grid, x - dy, y + dx) {
+            ("R", -dy, dx)
+        } else {
+            return path;
+        };
+        dx = new_dx;
+        dy = new_dy;
+        let mut steps = 0;
+        while is_scaffold(grid, x + dx, y + dy) {
+            x += dx;
+            y += dy;
+            steps += 1;
+        }
+        path.push(format!("{},{}", turn, steps));
+    }
+}
+
+fn routine_length(moves: &[String]) -> usize {
+    return moves.iter().map(|m| m.len()).sum::<usize>() + moves.len().saturating_sub(1);
+}
+
+// Depth first: the rest of the path has to start with one of the functions, or with a
+// new one (while there are less than three) of any length that still fits
+fn compress_from(path: &[String], functions: &mut Vec<Vec<String>>, main: &mut Vec<usize>) -> bool {
+    if path.len() == 0 {
+        return true;
+    }
+    if 2 * (main.len() + 1) - 1 > MAX_ROUTINE_LENGTH {
+        return false;
+    }
+    for i in 0..functions.len() {
+        if path.starts_with(&functions[i]) {
+            let length = functions[i].len();
+            main.push(i);
+            if compress_from(&path[length..], functions, main) {
+                return true;
+            }
+            main.pop();
+        }
+    }
+    if functions.len() < 3 {
+        for length in 1..path.len() + 1 {
+            if routine_length(&path[..length]) > MAX_ROUTINE_LENGTH {
+                break;
+            }
+            functions.push(path[..length].to_vec());
+            main.push(functions.len() - 1);
+            if compress_from(&path[length..], functions, main) {
+                return true;
+            }
+            main.pop();
+            functions.pop();
+        }
+    }
+    return false;
+}
+
+// The input for the robot: main routine, A, B, C, one per line
+fn compress(path: &[String]) -> Option<Vec<String>> {
+    let mut functions = Vec::new();
+    let mut main = Vec::new();
+    if !compress_from(path, &mut functions, &mut main) {
+        return None;
+    }
+    let names: Vec<String> = main.iter().map(|&i| ((b'A' + i as u8) as char).to_string()).collect();
+    let mut routines = vec![names.join(",")];
+    for i in 0..3 {
+        // unused functions still need a line
+        routines.push(functions.get(i).map(|f| f.join(",")).unwrap_or("L,0".to_string()));
+    }
+    return Some(routines);
+}
+
+fn main() {
+    let program = read_patched_program("../input");
+
+    let mut camera: Vm<VecDeque<i64>, AsciiStringSink> = Vm::new(program.clone());
+    camera.run();
+    print!("{}", camera.output_sink.text);
+    let grid = parse_view(&camera.output_sink.text);
+    println!("Sum of alignment parameters: {}", alignment_parameter_sum(&grid));
+
+    let path = find_path(&grid);
+    println!("Path: {}", path.join(","));
+    let routines = compress(&path).expect("Path can't be split into movement functions");
+    let input = format!("{}\nn\n", routines.join("\n"));
+    print!("{}", input);
+    let wake_up = Patch::load("../wake_up.patch").unwrap_or_else(|err| panic!("{}", err));
+    let mut robot: Vm<FromIter<_>, AsciiStringSink> =
+        VmBuilder::with_io(program, from_iter(input.bytes().map(|b| b as i64)), AsciiStringSink::new())
+        .apply_patch(&wake_up).build();
+    robot.run();
+    println!("Dust collected: {:?}", robot.output_sink.non_ascii_values().last().expect("No dust reported"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alignment_parameters() {
+        let view = "..#..........\n..#..........\n#######...###\n#.#...#...#.#\n#############\n..#...#...#..\n..#####...^..\n";
+        assert_eq!(alignment_parameter_sum(&parse_view(view)), 76);
+    }
+
+    #[test]
+    fn path_and_compression() {
+        let view = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......
+";
+        let path = find_path(&parse_view(view));
+        assert_eq!(path.join(","), "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2");
+        let routines = compress(&path).unwrap();
+        let expanded: Vec<&str> = routines[0].split(',').map(|name| match name {
+            "A" => routines[1].as_str(),
+            "B" => routines[2].as_str(),
+            _ => routines[3].as_str(),
+        }).collect();
+        assert_eq!(expanded.join(","), path.join(","));
+        assert!(routines.iter().all(|r| r.len() <= MAX_ROUTINE_LENGTH));
+    }
+}
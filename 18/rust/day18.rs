@@ -0,0 +1,205 @@
+#[path = "../../grid/rust/grid.rs"]
+mod grid;
+
+use std::collections::VecDeque;
+use std::fs;
+use grid::{dijkstra, Grid, Pos};
+
+const MAX_ROBOTS: usize = 4;
+
+// From one point of interest (a key or an entrance) to a key
+#[derive(Clone, Copy)]
+struct Edge {
+    key: usize,
+    distance: usize,
+    doors: u32, // keys needed to open the doors on the way
+    keys: u32, // keys on the way, which would be picked up
+}
+
+fn key_bit(c: u8) -> u32 {
+    return 1 << (c.to_ascii_lowercase() - b'a');
+}
+
+// BFS over the grid, doors and keys don't stop it, they are just noted
+// (that's why it's not grid::bfs, which only knows the distance)
+fn edges_from(grid: &Grid, start: Pos) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    let mut seen = vec![false; grid.cells.len()];
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 0, 0, 0));
+    seen[start.1 * grid.width + start.0] = true;
+    while let Some((pos, distance, doors, keys)) = queue.pop_front() {
+        let c = grid.at(pos);
+        let (mut doors, mut keys) = (doors, keys);
+        if c.is_ascii_lowercase() && pos != start {
+            edges.push(Edge { key: (c - b'a') as usize, distance: distance, doors: doors, keys: keys });
+            keys |= key_bit(c);
+        } else if c.is_ascii_uppercase() {
+            doors |= key_bit(c);
+        }
+        for next in grid.neighbours(pos) {
+            let index = next.1 * grid.width + next.0;
+            if grid.at(next) != b'#' && !seen[index] {
+                seen[index] = true;
+                queue.push_back((next, distance + 1, doors, keys));
+            }
+        }
+    }
+    return edges;
+}
+
+// Dijkstra over (robot positions, collected keys). Robots only move from key to key.
+fn shortest_collection(grid: &Grid) -> usize {
+    // points of interest: keys 0-25, entrances from 26 on
+    let mut positions: Vec<Pos> = vec![(0, 0); 26];
+    let mut all_keys = 0;
+    let mut entrances = Vec::new();
+    for pos in grid.positions() {
+        let c = grid.at(pos);
+        if c.is_ascii_lowercase() {
+            positions[(c - b'a') as usize] = pos;
+            all_keys |= key_bit(c);
+        } else if c == b'@' {
+            entrances.push(positions.len());
+            positions.push(pos);
+        }
+    }
+    assert!(entrances.len() > 0 && entrances.len() <= MAX_ROBOTS, "Expected 1 to {} entrances, got {}", MAX_ROBOTS, entrances.len());
+    let count = entrances.len(); // the other slots are unused
+    let mut robots = [0; MAX_ROBOTS];
+    for (i, &entrance) in entrances.iter().enumerate() {
+        robots[i] = entrance;
+    }
+    let edges: Vec<Vec<Edge>> = positions.iter().enumerate().map(|(i, &position)| {
+        if i < 26 && all_keys & (1 << i) == 0 { Vec::new() } else { edges_from(grid, position) }
+    }).collect();
+
+    let moves = |&(robots, keys): &([usize; MAX_ROBOTS], u32)| {
+        let mut result = Vec::new();
+        for i in 0..count {
+            for edge in &edges[robots[i]] {
+                let bit = 1 << edge.key;
+                // passing a key that isn't collected yet is the same as collecting it first
+                if keys & bit != 0 || edge.doors & !keys != 0 || edge.keys & !keys != 0 {
+                    continue;
+                }
+                let mut next_robots = robots;
+                next_robots[i] = edge.key;
+                result.push(((next_robots, keys | bit), edge.distance));
+            }
+        }
+        return result;
+    };
+    return dijkstra((robots, 0), moves, |&(_, keys)| keys == all_keys).expect("Not all keys can be collected");
+}
+
+// Splits the entrance into four separate ones, like on the updated map
+fn split_entrance(grid: &Grid) -> Grid {
+    let mut grid = grid.clone();
+    let entrances = grid.find(b'@');
+    if entrances.len() != 1 {
+        return grid; // already split
+    }
+    let (x, y) = entrances[0];
+    let replacement = [b"@#@", b"###", b"@#@"];
+    for dy in 0..3 {
+        for dx in 0..3 {
+            grid.set((x + dx - 1, y + dy - 1), replacement[dy][dx]);
+        }
+    }
+    return grid;
+}
+
+fn main() {
+    let text = fs::read_to_string("../input").expect("Could not read input");
+    let grid = Grid::parse(&text);
+    println!("Shortest path: {}", shortest_collection(&grid));
+    println!("Shortest path with four robots: {}", shortest_collection(&split_entrance(&grid)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_robot() {
+        let examples = [
+            ("#########\n#b.A.@.a#\n#########\n", 8),
+            ("########################
+#f.D.E.e.C.b.A.@.a.B.c.#
+######################.#
+#d.....................#
+########################
+", 86),
+            ("########################
+#...............b.C.D.f#
+#.######################
+#.....@.a.B.c.d.A.e.F.g#
+########################
+", 132),
+            ("#################
+#i.G..c...e..H.p#
+########.########
+#j.A..b...f..D.o#
+########@########
+#k.E..a...g..B.n#
+########.########
+#l.F..d...h..C.m#
+#################
+", 136),
+            ("########################
+#@..............ac.GI.b#
+###d#e#f################
+###A#B#C################
+###g#h#i################
+########################
+", 81),
+        ];
+        for &(maze, steps) in examples.iter() {
+            assert_eq!(shortest_collection(&Grid::parse(maze)), steps);
+        }
+    }
+
+    #[test]
+    fn four_robots() {
+        let examples = [
+            ("#######
+#a.#Cd#
+##...##
+##.@.##
+##...##
+#cB#Ab#
+#######
+", 8),
+            ("###############
+#d.ABC.#.....a#
+######@#@######
+###############
+######@#@######
+#b.....#.....c#
+###############
+", 24),
+            ("#############
+#DcBa.#.GhKl#
+#.###@#@#I###
+#e#d#####j#k#
+###C#@#@###J#
+#fEbA.#.FgHi#
+#############
+", 32),
+            ("#############
+#g#f.D#..h#l#
+#F###e#E###.#
+#dCba@#@BcIJ#
+#############
+#nK.L@#@G...#
+#M###N#H###.#
+#o#m..#i#jk.#
+#############
+", 72),
+        ];
+        for &(maze, steps) in examples.iter() {
+            assert_eq!(shortest_collection(&split_entrance(&Grid::parse(maze))), steps);
+        }
+    }
+}
@@ -1,58 +1,149 @@
-use std::fs;
-
-fn execute_add(instruction_pointer: usize, memory: &mut Vec<u64>) {
-    assert!(memory[instruction_pointer] == 1);
-    let param1 = memory[instruction_pointer + 1] as usize;
-    let param2 = memory[instruction_pointer + 2] as usize;
-    let dest = memory[instruction_pointer + 3] as usize;
-    memory[dest] = memory[param1] + memory[param2];
-}
-
-fn execute_mul(instruction_pointer: usize, memory: &mut Vec<u64>) {
-    assert!(memory[instruction_pointer] == 2);
-    let param1 = memory[instruction_pointer + 1] as usize;
-    let param2 = memory[instruction_pointer + 2] as usize;
-    let dest = memory[instruction_pointer + 3] as usize;
-    memory[dest] = memory[param1] * memory[param2];
-}
-
-fn str_to_u64(s: &str) -> u64 {
-    return s.trim().parse::<u64>().unwrap();
-}
-
-fn run_program(init_memory: &Vec<u64>, noun: u64, verb: u64) -> u64 {
-    let mut memory = init_memory.clone();
-    let mut instruction_pointer: usize = 0;
-    memory[1] = noun;
-    memory[2] = verb;
-    loop {
-        match memory[instruction_pointer] {
-            1 => {
-                execute_add(instruction_pointer, &mut memory);
-                instruction_pointer += 4;
-            },
-            2 => {
-                execute_mul(instruction_pointer, &mut memory);
-                instruction_pointer += 4;
-            },
-            99 => break,
-            _ => panic!("Unknown opcode: {}", memory[instruction_pointer])
+#[path = "../../intcode/rust/intcode.rs"]
+mod intcode;
+
+use std::collections::VecDeque;
+use std::env;
+use intcode::{read_patched_program, Cell, Limits, RunResult, Vm};
+
+const TARGET: Cell = 19690720;
+const MAX_STEPS: u64 = 100000; // for the heatmap, a noun/verb can turn the program into anything
+const TARGET_COLOR: (u8, u8, u8) = (255, 255, 255);
+const FAULT_COLOR: (u8, u8, u8) = (0, 0, 0);
+
+fn run_program(program: &Vec<Cell>, noun: Cell, verb: Cell) -> Cell {
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::builder(program.clone()).noun(noun).verb(verb).build();
+    vm.run();
+    return vm.read_mem(0);
+}
+
+fn solve_brute_force(program: &Vec<Cell>, target: Cell) -> Option<(Cell, Cell)> {
+    for noun in 0..100 {
+        for verb in 0..100 {
+            if run_program(program, noun, verb) == target {
+                return Some((noun, verb));
+            }
         }
     }
-    return memory[0];
+    return None;
 }
 
-fn main() {
-    let program_string = fs::read_to_string("../input").unwrap();
-    let memory = program_string.split(",").map(str_to_u64).collect::<Vec<u64>>();
-    run_program(&memory, 12, 2);
-    println!("Computation result: {}", run_program(&memory, 12, 2));
+// Most programs compute a * noun + b * verb + c, so three runs are enough to find the
+// coefficients. Whatever that gives is checked with another run, because it's only a guess.
+fn solve_linear(program: &Vec<Cell>, target: Cell) -> Option<(Cell, Cell)> {
+    let c = run_program(program, 0, 0);
+    let a = run_program(program, 1, 0) - c;
+    let b = run_program(program, 0, 1) - c;
     for noun in 0..100 {
+        let rest = target - c - a * noun;
+        let verb = if b == 0 {
+            if rest == 0 { 0 } else { continue }
+        } else if rest % b == 0 {
+            rest / b
+        } else {
+            continue;
+        };
+        if verb >= 0 && verb < 100 {
+            return if run_program(program, noun, verb) == target { Some((noun, verb)) } else { None };
+        }
+    }
+    return None;
+}
+
+// None if the program faults or doesn't halt
+fn try_run_program(program: &Vec<Cell>, noun: Cell, verb: Cell) -> Option<Cell> {
+    let limits = Limits { max_steps: Some(MAX_STEPS), ..Limits::default() };
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::builder(program.clone()).noun(noun).verb(verb).limits(limits).build();
+    return match vm.try_run() {
+        RunResult::Halted => Some(vm.read_mem(0)),
+        _ => None,
+    };
+}
+
+// Blue for the smallest output, red for the largest
+fn color(value: Cell, min: Cell, max: Cell) -> (u8, u8, u8) {
+    let t = if max > min { (value - min) as f64 / (max - min) as f64 } else { 0.0 };
+    let green = 1.0 - (2.0 * t - 1.0).abs();
+    return ((t * 255.0) as u8, (green * 160.0) as u8, ((1.0 - t) * 255.0) as u8);
+}
+
+// Nouns go down, verbs to the right. Every character is two nouns, the upper half block
+// is colored with the foreground color and the lower one with the background.
+fn heatmap(program: &Vec<Cell>, target: Cell) -> String {
+    let outputs: Vec<Vec<Option<Cell>>> = (0..100).map(|noun| (0..100).map(|verb| try_run_program(program, noun, verb)).collect()).collect();
+    let values = outputs.iter().flat_map(|row| row.iter().filter_map(|&v| v));
+    let (min, max) = values.fold((Cell::max_value(), Cell::min_value()), |(min, max), v| (min.min(v), max.max(v)));
+    let cell_color = |noun: usize, verb: usize| match outputs[noun][verb] {
+        Some(value) if value == target => TARGET_COLOR,
+        Some(value) => color(value, min, max),
+        None => FAULT_COLOR,
+    };
+    let mut result = String::new();
+    for noun in (0..100).step_by(2) {
         for verb in 0..100 {
-            if run_program(&memory, noun, verb) == 19690720 {
-                println!("Noun = {}, verb = {}", noun, verb);
-                return;
-            }
+            let (upper, lower) = (cell_color(noun, verb), cell_color(noun + 1, verb));
+            result.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}", upper.0, upper.1, upper.2, lower.0, lower.1, lower.2));
         }
+        result.push_str("\x1b[0m\n");
+    }
+    result.push_str(&format!("Nouns go down, verbs to the right. Blue is {}, red is {}, white is {}, black didn't halt.\n", min, max, target));
+    return result;
+}
+
+fn main() {
+    let program = read_patched_program("../input");
+    if env::args().any(|arg| arg == "--heatmap") {
+        print!("{}", heatmap(&program, TARGET));
+        return;
+    }
+    println!("Computation result: {}", run_program(&program, 12, 2));
+    let solution = if env::args().any(|arg| arg == "--linear") {
+        solve_linear(&program, TARGET).or_else(|| {
+            println!("Output is not linear in noun and verb, trying all of them");
+            return solve_brute_force(&program, TARGET);
+        })
+    } else {
+        solve_brute_force(&program, TARGET)
+    };
+    match solution {
+        Some((noun, verb)) => println!("Noun = {}, verb = {}", noun, verb),
+        None => println!("No noun and verb produce {}", TARGET),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::parse_program;
+
+    #[test]
+    fn linear() {
+        // mem[0] = noun * 1000 + verb + 7
+        let program = parse_program("1,0,0,3,1002,1,1000,0,1,0,2,0,1001,0,7,0,99");
+        assert_eq!(run_program(&program, 12, 45), 12052);
+        assert_eq!(solve_linear(&program, 12052), Some((12, 45)));
+        assert_eq!(solve_linear(&program, 100000), None);
+    }
+
+    #[test]
+    fn not_linear() {
+        // mem[0] = noun * verb
+        let program = parse_program("1,0,0,3,2,1,2,0,99");
+        assert_eq!(solve_linear(&program, 42), None);
+        assert_eq!(solve_brute_force(&program, 42), Some((1, 42)));
+    }
+
+    #[test]
+    fn heatmap_colors() {
+        let program = parse_program("1,0,0,3,2,1,2,0,99");
+        let map = heatmap(&program, 42);
+        assert_eq!(map.lines().count(), 51);
+        assert_eq!(map.matches("\u{2580}").count(), 100 * 50);
+        // 2 * 21 is drawn in an upper half, 3 * 14 in a lower one
+        assert!(map.contains("\x1b[38;2;255;255;255m"));
+        assert!(map.contains("\x1b[48;2;255;255;255m"));
+        assert_eq!(color(0, 0, 100), (0, 0, 255));
+        assert_eq!(color(100, 0, 100), (255, 0, 0));
+        // writes 100 over the 99, which is no opcode
+        assert_eq!(try_run_program(&parse_program("1,0,0,4,99"), 4, 0), None);
     }
 }
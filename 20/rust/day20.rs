@@ -0,0 +1,129 @@
+#[path = "../../grid/rust/grid.rs"]
+mod grid;
+
+use std::collections::HashMap;
+use std::fs;
+use grid::{shortest_path, Grid, Pos};
+
+struct Maze {
+    grid: Grid,
+    start: Pos,
+    end: Pos,
+    portals: HashMap<Pos, (Pos, bool)>, // tile in front of the portal -> (tile on the other side, is inner portal)
+}
+
+// Labels are two letters next to a tile, read left to right or top to bottom
+fn parse_maze(text: &str) -> Maze {
+    let grid = Grid::parse(text);
+    let mut labels: HashMap<String, Vec<Pos>> = HashMap::new();
+    for pos in grid.positions() {
+        if grid.at(pos) != b'.' {
+            continue;
+        }
+        for next in grid.neighbours(pos) {
+            let first = grid.at(next);
+            if !first.is_ascii_uppercase() {
+                continue;
+            }
+            let (beyond_x, beyond_y) = (2 * next.0 as i64 - pos.0 as i64, 2 * next.1 as i64 - pos.1 as i64);
+            let second = grid.get(beyond_x, beyond_y).filter(|c| c.is_ascii_uppercase())
+                .expect(&format!("Incomplete label next to {:?}", pos));
+            let letters = if beyond_x > next.0 as i64 || beyond_y > next.1 as i64 { [first, second] } else { [second, first] };
+            labels.entry(String::from_utf8_lossy(&letters).to_string()).or_insert(Vec::new()).push(pos);
+        }
+    }
+
+    let is_inner = |pos: Pos| pos.0 > 2 && pos.1 > 2 && pos.0 < grid.width - 3 && pos.1 < grid.height - 3;
+    let mut portals = HashMap::new();
+    for (label, tiles) in labels.iter() {
+        match (label.as_str(), tiles.len()) {
+            ("AA", 1) | ("ZZ", 1) => (),
+            (_, 2) => {
+                portals.insert(tiles[0], (tiles[1], is_inner(tiles[0])));
+                portals.insert(tiles[1], (tiles[0], is_inner(tiles[1])));
+            },
+            _ => panic!("Portal {} has {} ends", label, tiles.len()),
+        }
+    }
+    let entrance = |label: &str| labels.get(label).expect(&format!("No {} in the maze", label))[0];
+    return Maze {
+        start: entrance("AA"),
+        end: entrance("ZZ"),
+        portals: portals,
+        grid: grid,
+    };
+}
+
+impl Maze {
+    fn open_neighbours(&self, pos: Pos) -> Vec<Pos> {
+        return self.grid.neighbours(pos).into_iter().filter(|&next| self.grid.at(next) == b'.').collect();
+    }
+
+    fn shortest_path(&self) -> Option<usize> {
+        let neighbours = |&pos: &Pos| {
+            let mut result = self.open_neighbours(pos);
+            if let Some(&(other, _)) = self.portals.get(&pos) {
+                result.push(other);
+            }
+            return result;
+        };
+        return shortest_path(self.start, neighbours, |&pos| pos == self.end);
+    }
+
+    // Inner portals go one level down, outer ones up (and are walls on the outermost level)
+    fn shortest_recursive_path(&self) -> Option<usize> {
+        // deeper than there are portals doesn't help, you'd just have to come back up
+        let max_level = self.portals.len();
+        let neighbours = |&(pos, level): &(Pos, usize)| {
+            let mut result: Vec<(Pos, usize)> = self.open_neighbours(pos).into_iter().map(|next| (next, level)).collect();
+            match self.portals.get(&pos) {
+                Some(&(other, true)) if level < max_level => result.push((other, level + 1)),
+                Some(&(other, false)) if level > 0 => result.push((other, level - 1)),
+                _ => (),
+            }
+            return result;
+        };
+        return shortest_path((self.start, 0), neighbours, |&node| node == (self.end, 0));
+    }
+}
+
+fn main() {
+    let text = fs::read_to_string("../input").expect("Could not read input");
+    let maze = parse_maze(&text);
+    println!("Shortest path: {}", maze.shortest_path().expect("There is no path"));
+    println!("Shortest recursive path: {}", maze.shortest_recursive_path().expect("There is no recursive path"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "         A           
+         A           
+  #######.#########  
+  #######.........#  
+  #######.#######.#  
+  #######.#######.#  
+  #######.#######.#  
+  #####  B    ###.#  
+BC...##  C    ###.#  
+  ##.##       ###.#  
+  ##...DE  F  ###.#  
+  #####    G  ###.#  
+  #########.#####.#  
+DE..#######...###.#  
+  #.#########.###.#  
+FG..#########.....#  
+  ###########.#####  
+             Z       
+             Z       
+";
+
+    #[test]
+    fn example() {
+        let maze = parse_maze(EXAMPLE);
+        assert_eq!(maze.portals.len(), 6);
+        assert_eq!(maze.shortest_path(), Some(23));
+        assert_eq!(maze.shortest_recursive_path(), Some(26));
+    }
+}
@@ -0,0 +1,198 @@
+#[path = "../../intcode/rust/intcode.rs"]
+mod intcode;
+
+use std::env;
+use std::fs;
+use intcode::{from_iter, read_patched_program, AsciiStringSink, Cell, FromIter, Vm};
+
+const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Mode {
+    Walk, // sees A-D
+    Run, // sees A-I
+}
+
+impl Mode {
+    fn command(self) -> &'static str {
+        return match self { Mode::Walk => "WALK", Mode::Run => "RUN" };
+    }
+
+    fn range(self) -> usize {
+        return match self { Mode::Walk => 4, Mode::Run => 9 };
+    }
+}
+
+// Runs a springscript for one position, sensors[0] is A (true is ground)
+fn jumps(script: &[String], sensors: &[bool]) -> bool {
+    let (mut t, mut j) = (false, false);
+    for line in script {
+        let parts: Vec<&str> = line.split(' ').collect();
+        assert!(parts.len() == 3, "Invalid instruction '{}'", line);
+        let x = match parts[1] {
+            "T" => t,
+            "J" => j,
+            sensor => sensors[(sensor.as_bytes()[0] - b'A') as usize],
+        };
+        let y = if parts[2] == "T" { &mut t } else { &mut j };
+        *y = match parts[0] {
+            "AND" => x && *y,
+            "OR" => x || *y,
+            "NOT" => !x,
+            op => panic!("Unknown instruction '{}'", op),
+        };
+    }
+    return j;
+}
+
+// Walks the droid over a hull like "#####.#..########" (where it starts on the first
+// tile), the hull continues after the end
+fn survives(script: &[String], mode: Mode, hull: &[u8]) -> bool {
+    let mut x = 0;
+    while x < hull.len() {
+        let sensors: Vec<bool> = (1..mode.range() + 1).map(|i| hull.get(x + i).map_or(true, |&c| c == b'#')).collect();
+        x += if jumps(script, &sensors) { 4 } else { 1 };
+        if hull.get(x) == Some(&b'.') {
+            return false;
+        }
+    }
+    return true;
+}
+
+// Scripts of the form: jump if there's a hole in a subset of A-C, the landing tile D is ground,
+// and (when running) the droid can go on from there via a subset of E-I. Shortest first.
+fn candidates(mode: Mode) -> Vec<Vec<String>> {
+    let beyond: &[char] = if mode == Mode::Run { &['E', 'F', 'G', 'H', 'I'] } else { &[] };
+    let mut scripts = Vec::new();
+    for holes in 0..(1 << 3) {
+        for continuations in 0..(1 << beyond.len()) {
+            let mut script = Vec::new();
+            for sensor in (0..3).filter(|i| holes & (1 << i) != 0).map(|i| ['A', 'B', 'C'][i]) {
+                if script.len() == 0 {
+                    script.push(format!("NOT {} J", sensor));
+                } else {
+                    script.push(format!("NOT {} T", sensor));
+                    script.push("OR T J".to_string());
+                }
+            }
+            script.push("AND D J".to_string());
+            let continuations: Vec<char> = (0..beyond.len()).filter(|i| continuations & (1 << i) != 0).map(|i| beyond[i]).collect();
+            if continuations.len() > 0 {
+                // T = continuations[0] || ...
+                script.push(format!("NOT {} T", continuations[0]));
+                script.push("NOT T T".to_string());
+                for sensor in &continuations[1..] {
+                    script.push(format!("OR {} T", sensor));
+                }
+                script.push("AND T J".to_string());
+            }
+            if script.len() <= MAX_INSTRUCTIONS {
+                scripts.push(script);
+            }
+        }
+    }
+    scripts.sort_by_key(|script| script.len());
+    return scripts;
+}
+
+// The hull row of the first frame in the output after the droid fell
+fn failing_hull(trace: &str) -> Option<String> {
+    let after = &trace[trace.find("Didn't make it across")?..];
+    return after.lines().find(|line| line.contains('#') && line.bytes().all(|c| c == b'#' || c == b'.'))
+        .map(|line| line.to_string());
+}
+
+// Tries the candidates that survive all hulls seen so far, try_script returns the hull damage
+// or the output of the failed attempt (which tells us another hull to check against)
+fn search<F: FnMut(&[String]) -> Result<i64, String>>(mode: Mode, mut try_script: F) -> (Vec<String>, i64) {
+    let mut hulls: Vec<String> = Vec::new();
+    for script in candidates(mode) {
+        if !hulls.iter().all(|hull| survives(&script, mode, hull.as_bytes())) {
+            continue;
+        }
+        match try_script(&script) {
+            Ok(damage) => return (script, damage),
+            Err(trace) => {
+                let hull = failing_hull(&trace).expect(&format!("Unexpected output:\n{}", trace));
+                assert!(!hulls.contains(&hull), "Script fell on a hull it should survive:\n{}\n{}", script.join("\n"), hull);
+                hulls.push(hull);
+            },
+        }
+    }
+    panic!("No candidate script makes it across all of these hulls:\n{}", hulls.join("\n"));
+}
+
+fn run_droid(program: &Vec<Cell>, script: &[String], mode: Mode) -> Result<i64, String> {
+    let input = format!("{}\n{}\n", script.join("\n"), mode.command());
+    let mut droid: Vm<FromIter<_>, AsciiStringSink> =
+        Vm::with_io(program.clone(), from_iter(input.bytes().map(|b| b as i64)), AsciiStringSink::new());
+    droid.run();
+    return match droid.output_sink.non_ascii_values().last() {
+        Some(&damage) => Ok(damage),
+        None => Err(droid.output_sink.text),
+    };
+}
+
+// --walk <file> / --run <file> use a springscript from a file instead of searching for one
+fn script_arg(name: &str) -> Option<Vec<String>> {
+    let args: Vec<String> = env::args().collect();
+    let filename = args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1))?;
+    let text = fs::read_to_string(filename).expect(&format!("Could not read {}", filename));
+    return Some(text.lines().map(|line| line.trim().to_string()).filter(|line| line.len() > 0).collect());
+}
+
+fn main() {
+    let program = read_patched_program("../input");
+    for &(mode, arg) in [(Mode::Walk, "--walk"), (Mode::Run, "--run")].iter() {
+        let (script, damage) = match script_arg(arg) {
+            Some(script) => {
+                let damage = run_droid(&program, &script, mode).unwrap_or_else(|trace| panic!("{}", trace));
+                (script, damage)
+            },
+            None => search(mode, |script| run_droid(&program, script, mode)),
+        };
+        println!("{}:\n{}", mode.command(), script.join("\n"));
+        println!("Hull damage: {}", damage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(text: &str) -> Vec<String> {
+        return text.lines().map(|line| line.to_string()).collect();
+    }
+
+    #[test]
+    fn springscript() {
+        let jump_if_hole = script("NOT A J\nNOT D T\nAND T J");
+        assert!(!jumps(&jump_if_hole, &[true, true, true, true]));
+        assert!(!jumps(&jump_if_hole, &[false, true, true, true]));
+        assert!(jumps(&jump_if_hole, &[false, true, true, false]));
+        let naive = script("NOT A J");
+        assert!(survives(&naive, Mode::Walk, b"#####.###########"));
+        assert!(!survives(&naive, Mode::Walk, b"#####..#.########"));
+    }
+
+    #[test]
+    fn search_against_traces() {
+        // a fake game that knows hulls the search doesn't
+        let hidden = ["#####.###########", "#####...#########", "#####..#.########", "#####.#.##..#.###", "#####.##.##.#.###"];
+        let mut attempts = 0;
+        let (found, damage) = search(Mode::Run, |script| {
+            attempts += 1;
+            for hull in hidden.iter() {
+                if !survives(script, Mode::Run, hull.as_bytes()) {
+                    return Err(format!("Input instructions:\n\nRunning...\n\n\nDidn't make it across:\n\n.................\n@................\n{}\n", hull));
+                }
+            }
+            return Ok(1234);
+        });
+        assert_eq!(damage, 1234);
+        assert!(attempts <= hidden.len() + 1);
+        for hull in hidden.iter() {
+            assert!(survives(&found, Mode::Run, hull.as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+#[path = "../../intcode/rust/intcode.rs"]
+mod intcode;
+
+use intcode::read_patched_program;
+use intcode::network::{Nat, NatAction, Network, Packet};
+
+const MACHINE_COUNT: usize = 50;
+const NAT_ADDRESS: i64 = 255;
+
+// Remembers the last packet sent to 255 and sends it to 0 when the network is idle,
+// until it would send the same Y twice in a row
+struct Monitor {
+    last: Option<Packet>,
+    first_y: Option<i64>,
+    last_sent_y: Option<i64>,
+    repeated_y: Option<i64>,
+}
+
+impl Monitor {
+    fn new() -> Monitor {
+        return Monitor {
+            last: None,
+            first_y: None,
+            last_sent_y: None,
+            repeated_y: None,
+        };
+    }
+}
+
+impl Nat for Monitor {
+    fn on_packet(&mut self, packet: Packet) -> NatAction {
+        assert!(packet.address == NAT_ADDRESS, "Packet sent to unknown address: {:?}", packet);
+        if self.first_y.is_none() {
+            self.first_y = Some(packet.y);
+        }
+        self.last = Some(packet);
+        return NatAction::Continue;
+    }
+
+    fn on_idle(&mut self) -> NatAction {
+        let packet = self.last.expect("Network went idle before the NAT got a packet");
+        if self.last_sent_y == Some(packet.y) {
+            self.repeated_y = Some(packet.y);
+            return NatAction::Stop;
+        }
+        self.last_sent_y = Some(packet.y);
+        return NatAction::Send(Packet { address: 0, x: packet.x, y: packet.y });
+    }
+}
+
+fn main() {
+    let program = read_patched_program("../input");
+    let mut network = Network::new(&program, MACHINE_COUNT);
+    let mut monitor = Monitor::new();
+    network.run(&mut monitor);
+    println!("First Y sent to {}: {}", NAT_ADDRESS, monitor.first_y.expect("Nothing was sent to the NAT"));
+    println!("First Y the NAT sent twice in a row: {}", monitor.repeated_y.expect("All machines terminated"));
+    println!("({} rounds)", network.rounds);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor() {
+        let mut monitor = Monitor::new();
+        let packet = |y| Packet { address: NAT_ADDRESS, x: 1, y: y };
+        monitor.on_packet(packet(5));
+        monitor.on_packet(packet(6));
+        match monitor.on_idle() {
+            NatAction::Send(sent) => assert_eq!(sent, Packet { address: 0, x: 1, y: 6 }),
+            _ => panic!("Expected the NAT to wake up machine 0"),
+        }
+        monitor.on_packet(packet(6));
+        assert!(match monitor.on_idle() { NatAction::Stop => true, _ => false });
+        assert_eq!((monitor.first_y, monitor.repeated_y), (Some(5), Some(6)));
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::fs;
+
+// A 5x5 grid is a bitmask, bit (y * 5 + x) set means there's a bug. That's also the biodiversity rating.
+type Level = u32;
+
+const SIZE: usize = 5;
+const CENTER: usize = 12;
+
+fn parse_grid(text: &str) -> Level {
+    let cells: Vec<char> = text.chars().filter(|c| *c == '#' || *c == '.').collect();
+    assert!(cells.len() == SIZE * SIZE, "Expected a {}x{} grid", SIZE, SIZE);
+    return cells.iter().enumerate().filter(|&(_, &c)| c == '#').map(|(i, _)| 1 << i).sum();
+}
+
+fn bit(x: usize, y: usize) -> Level {
+    return 1 << (y * SIZE + x);
+}
+
+fn row(y: usize) -> Level {
+    return (0..SIZE).map(|x| bit(x, y)).sum();
+}
+
+fn column(x: usize) -> Level {
+    return (0..SIZE).map(|y| bit(x, y)).sum();
+}
+
+fn lives(bug: bool, neighbours: u32) -> bool {
+    return neighbours == 1 || (!bug && neighbours == 2);
+}
+
+// Per cell: the neighbours on the same level, on the level around it and on the one inside it
+struct Neighbours {
+    same: [Level; SIZE * SIZE],
+    outer: [Level; SIZE * SIZE],
+    inner: [Level; SIZE * SIZE],
+}
+
+fn neighbours(recursive: bool) -> Neighbours {
+    let mut result = Neighbours { same: [0; SIZE * SIZE], outer: [0; SIZE * SIZE], inner: [0; SIZE * SIZE] };
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let i = y * SIZE + x;
+            let steps: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+            for &(dx, dy) in steps.iter() {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= SIZE as i64 || ny >= SIZE as i64 {
+                    // the tile next to the center on the level around this one
+                    result.outer[i] |= bit((2 + dx) as usize, (2 + dy) as usize);
+                } else if recursive && (ny as usize * SIZE + nx as usize) == CENTER {
+                    // the whole edge of the level inside, on this side
+                    result.inner[i] |= match (dx, dy) {
+                        (1, 0) => column(0),
+                        (-1, 0) => column(SIZE - 1),
+                        (0, 1) => row(0),
+                        _ => row(SIZE - 1),
+                    };
+                } else {
+                    result.same[i] |= bit(nx as usize, ny as usize);
+                }
+            }
+        }
+    }
+    if !recursive {
+        result.outer = [0; SIZE * SIZE];
+    }
+    return result;
+}
+
+fn step_level(neighbours: &Neighbours, outer: Level, level: Level, inner: Level, recursive: bool) -> Level {
+    let mut next = 0;
+    for i in 0..SIZE * SIZE {
+        if recursive && i == CENTER {
+            continue;
+        }
+        let count = (level & neighbours.same[i]).count_ones()
+            + (outer & neighbours.outer[i]).count_ones()
+            + (inner & neighbours.inner[i]).count_ones();
+        if lives(level & (1 << i) != 0, count) {
+            next |= 1 << i;
+        }
+    }
+    return next;
+}
+
+fn first_repeated_rating(grid: Level) -> Level {
+    let neighbours = neighbours(false);
+    let mut seen = HashSet::new();
+    let mut grid = grid;
+    while seen.insert(grid) {
+        grid = step_level(&neighbours, 0, grid, 0, false);
+    }
+    return grid;
+}
+
+// levels[0] is the outermost one, there's always an empty level on both ends
+fn bugs_after(grid: Level, minutes: usize) -> u32 {
+    let neighbours = neighbours(true);
+    let mut levels = vec![0, grid, 0];
+    for _ in 0..minutes {
+        let mut next: Vec<Level> = (0..levels.len()).map(|i| {
+            let outer = if i > 0 { levels[i - 1] } else { 0 };
+            let inner = if i + 1 < levels.len() { levels[i + 1] } else { 0 };
+            return step_level(&neighbours, outer, levels[i], inner, true);
+        }).collect();
+        if next[0] != 0 {
+            next.insert(0, 0);
+        }
+        if next[next.len() - 1] != 0 {
+            next.push(0);
+        }
+        levels = next;
+    }
+    return levels.iter().map(|level| level.count_ones()).sum();
+}
+
+fn main() {
+    let text = fs::read_to_string("../input").expect("Could not read input");
+    let grid = parse_grid(&text);
+    println!("First repeated biodiversity rating: {}", first_repeated_rating(grid));
+    println!("Bugs after 200 minutes: {}", bugs_after(grid, 200));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "....#\n#..#.\n#..##\n..#..\n#....\n";
+
+    #[test]
+    fn flat() {
+        assert_eq!(first_repeated_rating(parse_grid(EXAMPLE)), 2129920);
+    }
+
+    #[test]
+    fn recursive() {
+        assert_eq!(bugs_after(parse_grid(EXAMPLE), 10), 99);
+    }
+}
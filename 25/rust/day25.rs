@@ -0,0 +1,238 @@
+#[path = "../../intcode/rust/intcode.rs"]
+mod intcode;
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use intcode::{read_patched_program, AsciiConsoleOutputSink, AsciiStringSink, Cell, ScriptInputSource, Vm, VmState};
+
+// Taking some items makes the game loop forever, so every command gets a budget
+const STEPS_PER_COMMAND: usize = 10_000_000;
+
+enum Reply {
+    Prompt(String), // waiting for the next command
+    GameOver(String),
+    Stuck,
+}
+
+#[derive(Clone)]
+struct Droid {
+    vm: Vm<VecDeque<i64>, AsciiStringSink>,
+}
+
+impl Droid {
+    fn boot(program: &Vec<Cell>) -> (Droid, String) {
+        let mut droid = Droid { vm: Vm::with_io(program.clone(), VecDeque::new(), AsciiStringSink::new()) };
+        return match droid.run() {
+            Reply::Prompt(text) => (droid, text),
+            _ => panic!("Game did not start properly"),
+        };
+    }
+
+    fn run(&mut self) -> Reply {
+        let (_, out_of_budget) = self.vm.run_for(STEPS_PER_COMMAND);
+        let text = self.vm.output_sink.take();
+        if out_of_budget {
+            return Reply::Stuck;
+        }
+        return match self.vm.state {
+            VmState::WaitForInput => Reply::Prompt(text),
+            _ => Reply::GameOver(text),
+        };
+    }
+
+    fn send(&mut self, command: &str) -> Reply {
+        self.vm.input_source.extend(command.bytes().map(|b| b as i64));
+        self.vm.input_source.push_back('\n' as i64);
+        return self.run();
+    }
+
+    // For commands that can't go wrong
+    fn command(&mut self, command: &str) -> String {
+        return match self.send(command) {
+            Reply::Prompt(text) => text,
+            _ => panic!("'{}' ended the game", command),
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Room {
+    name: String,
+    doors: Vec<String>,
+    items: Vec<String>,
+}
+
+// The last room in the output (after being ejected from somewhere there are two)
+fn parse_room(text: &str) -> Option<Room> {
+    let start = text.rfind("\n== ").map(|i| i + 1).or(if text.starts_with("== ") { Some(0) } else { None })?;
+    let mut lines = text[start..].lines();
+    let name = lines.next()?.trim_matches(|c| c == '=' || c == ' ').to_string();
+    let mut room = Room { name: name, doors: Vec::new(), items: Vec::new() };
+    let mut list: Option<&mut Vec<String>> = None;
+    for line in lines {
+        if line == "Doors here lead:" {
+            list = Some(&mut room.doors);
+        } else if line == "Items here:" {
+            list = Some(&mut room.items);
+        } else if line.starts_with("- ") {
+            if let Some(ref mut list) = list {
+                list.push(line[2..].to_string());
+            }
+        } else {
+            list = None;
+        }
+    }
+    return Some(room);
+}
+
+fn opposite(direction: &str) -> &'static str {
+    return match direction {
+        "north" => "south",
+        "south" => "north",
+        "east" => "west",
+        "west" => "east",
+        _ => panic!("Unknown direction '{}'", direction),
+    };
+}
+
+// "... get in by typing 12345 on the keypad ..."
+fn password(text: &str) -> Option<String> {
+    let after = &text[text.find("typing ")? + 7..];
+    let digits: String = after.chars().take_while(|c| c.is_digit(10)).collect();
+    return if digits.len() > 0 { Some(digits) } else { None };
+}
+
+struct Map {
+    paths: HashMap<String, Vec<String>>, // room -> directions from the start
+    items: Vec<(String, String)>, // (item, room), only those that are safe to carry
+    checkpoint: Option<(String, String)>, // (room, direction of the pressure-sensitive floor)
+}
+
+// Taking it must not end the game or loop forever and we have to be able to move afterwards
+fn is_safe(droid: &Droid, room: &Room, item: &str) -> bool {
+    let mut droid = droid.clone();
+    if let Reply::Prompt(_) = droid.send(&format!("take {}", item)) {
+        return room.doors.iter().any(|door| {
+            let reply = droid.clone().send(door);
+            return match reply {
+                Reply::Prompt(text) => parse_room(&text).map_or(false, |next| next.name != room.name),
+                _ => false,
+            };
+        });
+    }
+    return false;
+}
+
+// Depth first, every room gets its own copy of the droid so we never have to walk back
+fn explore(droid: &Droid, room: &Room, path: &mut Vec<String>, map: &mut Map) {
+    for item in &room.items {
+        if is_safe(droid, room, item) {
+            map.items.push((item.clone(), room.name.clone()));
+        } else {
+            println!("Leaving the {} in {}", item, room.name);
+        }
+    }
+    for door in &room.doors {
+        let mut next = droid.clone();
+        let next_room = match next.send(door) {
+            Reply::Prompt(text) => parse_room(&text).expect(&format!("Unexpected output:\n{}", text)),
+            _ => panic!("Going {} from {} ended the game", door, room.name),
+        };
+        if next_room.name == room.name {
+            // sent back, the floor behind the checkpoint
+            map.checkpoint = Some((room.name.clone(), door.clone()));
+            continue;
+        }
+        if map.paths.contains_key(&next_room.name) {
+            continue;
+        }
+        path.push(door.clone());
+        map.paths.insert(next_room.name.clone(), path.clone());
+        explore(&next, &next_room, path, map);
+        path.pop();
+    }
+}
+
+fn walk(droid: &mut Droid, path: &[String]) {
+    for direction in path {
+        droid.command(direction);
+    }
+}
+
+fn walk_back(droid: &mut Droid, path: &[String]) {
+    for direction in path.iter().rev() {
+        droid.command(opposite(direction));
+    }
+}
+
+// Tries every combination of items on the floor, changing one item at a time (Gray code)
+fn pass_checkpoint(droid: &mut Droid, items: &[String], direction: &str) -> Option<String> {
+    let mut holding = vec![true; items.len()];
+    for i in 0..(1usize << items.len()) {
+        let combination = i ^ (i >> 1);
+        for (j, item) in items.iter().enumerate() {
+            let wanted = combination & (1 << j) != 0;
+            if wanted != holding[j] {
+                droid.command(&format!("{} {}", if wanted { "take" } else { "drop" }, item));
+                holding[j] = wanted;
+            }
+        }
+        if let Reply::GameOver(text) = droid.send(direction) {
+            let carried: Vec<&str> = items.iter().zip(holding.iter()).filter(|&(_, &h)| h).map(|(item, _)| item.as_str()).collect();
+            println!("Carrying: {}", carried.join(", "));
+            return password(&text);
+        }
+    }
+    return None;
+}
+
+fn autopilot(program: &Vec<Cell>) -> String {
+    let (mut droid, text) = Droid::boot(program);
+    let start = parse_room(&text).expect("No room at the start");
+    let mut map = Map { paths: HashMap::new(), items: Vec::new(), checkpoint: None };
+    map.paths.insert(start.name.clone(), Vec::new());
+    explore(&droid, &start, &mut Vec::new(), &mut map);
+    println!("Found {} rooms and {} items to carry", map.paths.len(), map.items.len());
+
+    for &(ref item, ref room) in &map.items {
+        let path = map.paths[room].clone();
+        walk(&mut droid, &path);
+        droid.command(&format!("take {}", item));
+        walk_back(&mut droid, &path);
+    }
+    let (checkpoint, direction) = map.checkpoint.expect("Found no security checkpoint");
+    walk(&mut droid, &map.paths[&checkpoint]);
+    let items: Vec<String> = map.items.iter().map(|&(ref item, _)| item.clone()).collect();
+    return pass_checkpoint(&mut droid, &items, &direction).expect("No combination of items gets through the checkpoint");
+}
+
+fn main() {
+    let program = read_patched_program("../input");
+    if env::args().any(|arg| arg == "--interactive") {
+        let mut vm: Vm<_, _> = Vm::with_io(program, ScriptInputSource::stdin(), AsciiConsoleOutputSink::default());
+        vm.run();
+        return;
+    }
+    println!("Password: {}", autopilot(&program));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_output() {
+        let text = "\n\n\n== Hull Breach ==\nYou got in through a hole in the floor here.\n\nDoors here lead:\n- north\n- east\n\nItems here:\n- mug\n\nCommand?\n";
+        assert_eq!(parse_room(text), Some(Room {
+            name: "Hull Breach".to_string(),
+            doors: vec!["north".to_string(), "east".to_string()],
+            items: vec!["mug".to_string()],
+        }));
+        let ejected = "== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n- south\n\nA loud, robotic voice says \"Alert!\" and you are ejected back to the checkpoint.\n\n\n\n== Security Checkpoint ==\nIn the next room, a pressure-sensitive floor will verify your identity.\n\nDoors here lead:\n- north\n- west\n\nCommand?\n";
+        let room = parse_room(ejected).unwrap();
+        assert_eq!((room.name.as_str(), room.doors.len(), room.items.len()), ("Security Checkpoint", 2, 0));
+        assert_eq!(parse_room("Command?\n"), None);
+        assert_eq!(password("You should be able to get in by typing 2147485856 on the keypad at the main airlock."), Some("2147485856".to_string()));
+        assert_eq!(password("Alert!"), None);
+    }
+}
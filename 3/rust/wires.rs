@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Point {
+    pub fn manhattan_length(&self) -> usize {
+        return (self.x.abs() + self.y.abs()) as usize;
+    }
+
+    fn key(&self) -> (i32, i32) {
+        return (self.x, self.y);
+    }
+}
+
+pub enum WireDirection {
+    UP, DOWN, LEFT, RIGHT
+}
+
+pub struct WireSegment {
+    pub direction: WireDirection,
+    pub length: usize,
+}
+
+pub type Wire = Vec<WireSegment>;
+
+pub struct WireIterator<'a> {
+    wire: &'a Wire,
+    segment: usize,
+    segment_index: usize,
+    position: Point,
+}
+
+impl WireIterator<'_> {
+    pub fn new(wire: &Wire) -> WireIterator {
+        return WireIterator { 
+            wire: wire, 
+            segment: 0, 
+            segment_index: 0, 
+            position: Point { x: 0, y: 0 },
+        };
+    }
+}
+
+impl Iterator for WireIterator<'_> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        if self.segment >= self.wire.len() {
+            return None;
+        }
+        match self.wire[self.segment].direction {
+            WireDirection::UP => self.position.y += 1,
+            WireDirection::DOWN => self.position.y -= 1,
+            WireDirection::LEFT => self.position.x -= 1,
+            WireDirection::RIGHT => self.position.x += 1,
+        }
+        self.segment_index += 1;
+        if self.segment_index >= self.wire[self.segment].length {
+            self.segment += 1;
+            self.segment_index = 0;
+        }
+        return Some(Point {
+            x: self.position.x, 
+            y: self.position.y
+        });
+    }
+}
+
+// A piece of a wire as its two ends, steps is how long the wire is up to start
+struct Segment {
+    start: Point,
+    end: Point,
+    steps: usize,
+}
+
+impl Segment {
+    fn steps_to(&self, point: Point) -> usize {
+        return self.steps + ((point.x - self.start.x).abs() + (point.y - self.start.y).abs()) as usize;
+    }
+}
+
+fn segments(wire: &Wire) -> Vec<Segment> {
+    let mut result = Vec::new();
+    let mut position = Point { x: 0, y: 0 };
+    let mut steps = 0;
+    for segment in wire {
+        let length = segment.length as i32;
+        let end = match segment.direction {
+            WireDirection::UP => Point { x: position.x, y: position.y + length },
+            WireDirection::DOWN => Point { x: position.x, y: position.y - length },
+            WireDirection::LEFT => Point { x: position.x - length, y: position.y },
+            WireDirection::RIGHT => Point { x: position.x + length, y: position.y },
+        };
+        result.push(Segment { start: position, end: end, steps: steps });
+        position = end;
+        steps += segment.length;
+    }
+    return result;
+}
+
+// Both segments are axis aligned, so the points they have in common are exactly the
+// overlap of their bounding boxes (a single point if they cross, a line if they overlap)
+fn common_points(a: &Segment, b: &Segment) -> Vec<Point> {
+    let x_min = a.start.x.min(a.end.x).max(b.start.x.min(b.end.x));
+    let x_max = a.start.x.max(a.end.x).min(b.start.x.max(b.end.x));
+    let y_min = a.start.y.min(a.end.y).max(b.start.y.min(b.end.y));
+    let y_max = a.start.y.max(a.end.y).min(b.start.y.max(b.end.y));
+    let mut points = Vec::new();
+    for x in x_min..x_max + 1 {
+        for y in y_min..y_max + 1 {
+            points.push(Point { x: x, y: y });
+        }
+    }
+    return points;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Intersection {
+    pub point: Point,
+    pub manhattan: usize, // distance to the origin
+    pub steps_a: usize, // along the first wire, the first time it gets there
+    pub steps_b: usize,
+}
+
+impl Intersection {
+    pub fn steps(&self) -> usize {
+        return self.steps_a + self.steps_b;
+    }
+}
+
+// Every point (except the origin) where the two wires meet
+pub fn wire_intersections(wires: &[Wire]) -> Vec<Intersection> {
+    assert!(wires.len() == 2, "Expected two wires, got {}", wires.len());
+    let mut result: Vec<Intersection> = Vec::new();
+    for segment_a in &segments(&wires[0]) {
+        for segment_b in &segments(&wires[1]) {
+            for point in common_points(segment_a, segment_b).into_iter().filter(|p| p.x != 0 || p.y != 0) {
+                let (steps_a, steps_b) = (segment_a.steps_to(point), segment_b.steps_to(point));
+                match result.iter_mut().find(|i| i.point == point) {
+                    Some(existing) => {
+                        existing.steps_a = existing.steps_a.min(steps_a);
+                        existing.steps_b = existing.steps_b.min(steps_b);
+                    },
+                    None => result.push(Intersection {
+                        point: point,
+                        manhattan: point.manhattan_length(),
+                        steps_a: steps_a,
+                        steps_b: steps_b,
+                    }),
+                }
+            }
+        }
+    }
+    return result;
+}
+
+// A point a single wire goes through more than once
+#[derive(Clone, PartialEq, Debug)]
+pub struct SelfIntersection {
+    pub point: Point,
+    pub visits: Vec<usize>, // steps at every visit, ascending
+}
+
+// Sorted by the first visit. A wire running back over itself counts too.
+pub fn self_intersections(wire: &Wire) -> Vec<SelfIntersection> {
+    let segments = segments(wire);
+    let mut result: Vec<SelfIntersection> = Vec::new();
+    for (i, a) in segments.iter().enumerate() {
+        for b in &segments[i + 1..] {
+            for point in common_points(a, b) {
+                // the corner between two segments is in both, but it's the same visit
+                let (first, second) = (a.steps_to(point), b.steps_to(point));
+                if first == second {
+                    continue;
+                }
+                let index = match result.iter().position(|s| s.point == point) {
+                    Some(index) => index,
+                    None => {
+                        result.push(SelfIntersection { point: point, visits: Vec::new() });
+                        result.len() - 1
+                    },
+                };
+                for &steps in [first, second].iter() {
+                    if !result[index].visits.contains(&steps) {
+                        result[index].visits.push(steps);
+                    }
+                }
+            }
+        }
+    }
+    for intersection in &mut result {
+        intersection.visits.sort();
+    }
+    result.sort_by_key(|s| s.visits[0]);
+    return result;
+}
+
+pub fn parse_wire(line: &str) -> Wire {
+    return line.trim().split(",").map(wiresegment_from_str).collect();
+}
+
+pub fn wiresegment_from_str(s: &str) -> WireSegment {
+    let direction = match &s[0..1] {
+        "U" => WireDirection::UP,
+        "D" => WireDirection::DOWN,
+        "L" => WireDirection::LEFT,
+        "R" => WireDirection::RIGHT,
+        _ => panic!("Unknown direction")
+    };
+    return WireSegment {
+        direction: direction,
+        length: s[1..].parse::<usize>().unwrap(),
+    };
+}
+
+// The old way, every point of the first wire in a map. Slow, but obviously right.
+pub fn intersections_by_walking(a: &Wire, b: &Wire) -> Vec<(Point, usize)> {
+    let mut pos_set: HashMap<(i32, i32), usize> = HashMap::new();
+    for (i, point) in WireIterator::new(a).enumerate() {
+        pos_set.entry(point.key()).or_insert(i + 1);
+    }
+    let mut result: Vec<(Point, usize)> = Vec::new();
+    for (i, point) in WireIterator::new(b).enumerate() {
+        if let Some(&steps) = pos_set.get(&point.key()) {
+            if !result.iter().any(|&(p, _)| p == point) {
+                result.push((point, (i + 1) + steps));
+            }
+        }
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let examples = [
+            ("R8,U5,L5,D3", "U7,R6,D4,L4", 6, 30),
+            ("R75,D30,R83,U83,L12,D49,R71,U7,L72", "U62,R66,U55,R34,D71,R55,D58,R83", 159, 610),
+            ("R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51", "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7", 135, 410),
+            ("R5,U2,L5", "R2,U1,R2,L8", 1, 2), // overlapping at the start, the second one doubles back over itself
+        ];
+        for &(a, b, closest, fewest_steps) in examples.iter() {
+            let wires = vec![parse_wire(a), parse_wire(b)];
+            let intersections = wire_intersections(&wires);
+            assert_eq!(intersections.iter().map(|i| i.manhattan).min(), Some(closest));
+            assert_eq!(intersections.iter().map(|i| i.steps()).min(), Some(fewest_steps));
+
+            let mut analytic: Vec<(i32, i32, usize)> = intersections.iter().map(|i| (i.point.x, i.point.y, i.steps())).collect();
+            let mut walked: Vec<(i32, i32, usize)> = intersections_by_walking(&wires[0], &wires[1]).iter().map(|&(p, s)| (p.x, p.y, s)).collect();
+            analytic.sort();
+            walked.sort();
+            assert_eq!(analytic, walked);
+        }
+    }
+
+    #[test]
+    fn crossing_itself() {
+        let visits = |wire: &str| -> Vec<(i32, i32, Vec<usize>)> {
+            return self_intersections(&parse_wire(wire)).into_iter().map(|s| (s.point.x, s.point.y, s.visits)).collect();
+        };
+        assert_eq!(visits("U2,R2,D1,L3"), vec![(0, 1, vec![1, 7])]);
+        assert_eq!(visits("R2,U1,R2,L8"), vec![(2, 1, vec![3, 7]), (3, 1, vec![4, 6])]);
+        assert_eq!(visits("U1,R1,D1,L1,U2"), vec![(0, 0, vec![0, 4]), (0, 1, vec![1, 5])]);
+        assert_eq!(visits("R8,U5,L5,D3"), vec![]);
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+
 fn num_less(a: &[i32], b: &[i32]) -> bool {
     assert!(a.len() == b.len());
     for i in 0..a.len() {
@@ -12,7 +16,7 @@ fn num_less(a: &[i32], b: &[i32]) -> bool {
 }
 
 fn is_mono(a: &[i32]) -> bool {
-    for i in 1..a.len()-1 {
+    for i in 0..a.len()-1 {
         if a[i] > a[i+1] {
             return false;
         }
@@ -41,7 +45,6 @@ fn has_special_repeat(a: &[i32]) -> bool {
     let mut i = 0;
     while i < a.len()-1 {
         let rep_len = repeat_length(&a, i);
-        println!("Num = {:?}, index = {}, digit = {}, repeat = {}", a, i, a[i], rep_len);
         if rep_len == 2 {
             return true;
         }
@@ -55,9 +58,9 @@ fn is_valid(a: &[i32]) -> bool {
     //return is_mono(&a) && has_repeat(&a);
 }
 
-fn get_next_mono(a: &[i32; 6]) -> [i32; 6] {
-    let mut mono = a.clone();
-    for i in 1..a.len()-1 {
+fn get_next_mono(a: &[i32]) -> Vec<i32> {
+    let mut mono = a.to_vec();
+    for i in 0..a.len()-1 {
         if a[i] > a[i+1] {
             for j in i+1..a.len() {
                 mono[j] = a[i];
@@ -80,19 +83,180 @@ fn increase_digit(num: &mut[i32], digit: usize) -> i32 {
     return num[digit];
 }
 
-fn main() {
-    let mut cur_number = get_next_mono(&[1, 3, 0, 2, 5, 4]);
+fn to_digits(number: u64) -> Vec<i32> {
+    return number.to_string().bytes().map(|b| (b - b'0') as i32).collect();
+}
+
+// "130254-678275". Passwords have as many digits as the upper bound, so a shorter lower
+// bound is raised to the smallest number with that many digits.
+fn parse_range(text: &str) -> (Vec<i32>, Vec<i32>) {
+    let bounds: Vec<u64> = text.trim().split('-')
+        .map(|s| s.trim().parse::<u64>().unwrap_or_else(|_| panic!("Invalid range '{}'", text.trim())))
+        .collect();
+    assert!(bounds.len() == 2 && bounds[0] <= bounds[1], "Expected a range like 130254-678275, got '{}'", text.trim());
+    let max_number = to_digits(bounds[1]);
+    let smallest = 10u64.pow(max_number.len() as u32 - 1);
+    return (to_digits(bounds[0].max(smallest)), max_number);
+}
+
+// Both bounds are included
+fn count_valid(min_number: &[i32], max_number: &[i32]) -> usize {
+    let mut cur_number = get_next_mono(min_number);
     let digit_num = cur_number.len();
-    println!("First mono: {:?}", cur_number);
-    let max_number = [6, 7, 8, 2, 7, 5];
     let mut counter = 0;
-    while num_less(&cur_number, &max_number) {
-        println!("{:?} - {}", cur_number, is_valid(&cur_number));
+    while !num_less(max_number, &cur_number) {
         if is_valid(&cur_number) {
             counter += 1;
         }
+        if cur_number.iter().all(|&d| d == 9) {
+            break; // can't be increased
+        }
         increase_digit(&mut cur_number, digit_num - 1);
         assert!(is_mono(&cur_number));
     }
-    println!("Count: {}", counter);
+    return counter;
+}
+
+// What the digits after the current one need to know about the ones before it:
+// the last digit, how long its run is (3 means 3 or more) and whether there was a run of exactly 2
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Prefix {
+    last: i32,
+    run: u8,
+    has_pair: bool,
+}
+
+impl Prefix {
+    fn empty() -> Prefix {
+        return Prefix { last: 0, run: 0, has_pair: false };
+    }
+
+    fn push(self, digit: i32) -> Prefix {
+        if self.run > 0 && digit == self.last {
+            return Prefix { last: digit, run: (self.run + 1).min(3), has_pair: self.has_pair };
+        }
+        return Prefix { last: digit, run: 1, has_pair: self.has_pair || self.run == 2 };
+    }
+
+    fn is_valid(self) -> bool {
+        return self.has_pair || self.run == 2;
+    }
+}
+
+// Ways to append `remaining` non-decreasing digits so the whole thing is valid
+fn completions(prefix: Prefix, remaining: usize, memo: &mut HashMap<(Prefix, usize), u64>) -> u64 {
+    if remaining == 0 {
+        return if prefix.is_valid() { 1 } else { 0 };
+    }
+    if let Some(&count) = memo.get(&(prefix, remaining)) {
+        return count;
+    }
+    let count = (prefix.last..10).map(|digit| completions(prefix.push(digit), remaining - 1, memo)).sum();
+    memo.insert((prefix, remaining), count);
+    return count;
+}
+
+// Valid numbers with as many digits as bound that are <= bound (leading zeros allowed).
+// Goes along the digits of the bound: a smaller digit at some position means anything
+// non-decreasing can follow, the bound's own digit means the next position is limited again.
+fn count_up_to(bound: &[i32], memo: &mut HashMap<(Prefix, usize), u64>) -> u64 {
+    let mut count = 0;
+    let mut prefix = Prefix::empty();
+    for (i, &limit) in bound.iter().enumerate() {
+        for digit in prefix.last..limit {
+            count += completions(prefix.push(digit), bound.len() - i - 1, memo);
+        }
+        if limit < prefix.last {
+            return count; // the bound itself isn't non-decreasing, nothing more below it
+        }
+        prefix = prefix.push(limit);
+    }
+    return count + if prefix.is_valid() { 1 } else { 0 };
+}
+
+// Same as count_valid, without looking at any of the numbers
+fn count_combinatorial(min_number: &[i32], max_number: &[i32]) -> u64 {
+    assert!(min_number.len() == max_number.len(), "Both bounds need the same number of digits");
+    let mut memo = HashMap::new();
+    let min_itself = if is_mono(min_number) && is_valid(min_number) { 1 } else { 0 };
+    return count_up_to(max_number, &mut memo) - (count_up_to(min_number, &mut memo) - min_itself);
+}
+
+fn from_digits(digits: &[i32]) -> u64 {
+    return digits.iter().fold(0, |n, &d| n * 10 + d as u64);
+}
+
+// Compares both ways of counting on the range, or on slices spread over it if it's too big to enumerate
+fn verify(min_number: &[i32], max_number: &[i32]) -> bool {
+    const SLICES: u64 = 100;
+    const SLICE_SIZE: u64 = 100000;
+    let (min, max) = (from_digits(min_number), from_digits(max_number));
+    let slices: Vec<(u64, u64)> = if max - min <= SLICES * SLICE_SIZE {
+        vec![(min, max)]
+    } else {
+        (0..SLICES).map(|i| min + i * ((max - min) / SLICES)).map(|start| (start, (start + SLICE_SIZE).min(max))).collect()
+    };
+    let mut ok = true;
+    for (low, high) in slices {
+        let (low_digits, high_digits) = (to_digits(low), to_digits(high));
+        let (enumerated, combinatorial) = (count_valid(&low_digits, &high_digits) as u64, count_combinatorial(&low_digits, &high_digits));
+        if enumerated != combinatorial {
+            println!("{}-{}: enumerating gives {}, counting {}", low, high, enumerated, combinatorial);
+            ok = false;
+        }
+    }
+    return ok;
+}
+
+// day4 [range or file with it] [--combinatorial] [--verify]
+// The range is either given directly or in a file (../input by default).
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arg = args.iter().find(|arg| !arg.starts_with("--")).cloned().unwrap_or("../input".to_string());
+    let text = if arg.contains('-') && arg.chars().all(|c| c.is_digit(10) || c == '-') {
+        arg
+    } else {
+        fs::read_to_string(&arg).unwrap_or_else(|err| panic!("Could not read {}: {}", arg, err))
+    };
+    let (min_number, max_number) = parse_range(&text);
+    if args.iter().any(|arg| arg == "--verify") {
+        let ok = verify(&min_number, &max_number);
+        println!("{}", if ok { "Both ways of counting agree" } else { "Counts differ!" });
+    }
+    if args.iter().any(|arg| arg == "--combinatorial") {
+        println!("Count: {}", count_combinatorial(&min_number, &max_number));
+    } else {
+        println!("First mono: {:?}", get_next_mono(&min_number));
+        println!("Count: {}", count_valid(&min_number, &max_number));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("130254-678275\n"), (vec![1, 3, 0, 2, 5, 4], vec![6, 7, 8, 2, 7, 5]));
+        assert_eq!(parse_range("5-250"), (vec![1, 0, 0], vec![2, 5, 0]));
+        // 112233 and 111122 are valid, 123444 isn't
+        assert_eq!(count_valid(&[1, 1, 2, 2, 3, 3], &[1, 1, 2, 2, 3, 3]), 1);
+        assert_eq!(count_valid(&[1, 2, 3, 4, 4, 4], &[1, 2, 3, 4, 4, 4]), 0);
+        assert_eq!(count_valid(&[1, 1, 1, 1, 2, 2], &[1, 1, 1, 1, 2, 2]), 1);
+        // 11, 22, ..., 99
+        assert_eq!(count_valid(&[1, 0], &[9, 9]), 9);
+        assert_eq!(count_valid(&[5, 4], &[6, 0]), 1);
+    }
+
+    #[test]
+    fn combinatorial() {
+        assert_eq!(count_combinatorial(&[1, 3, 0, 2, 5, 4], &[6, 7, 8, 2, 7, 5]), count_valid(&[1, 3, 0, 2, 5, 4], &[6, 7, 8, 2, 7, 5]) as u64);
+        assert_eq!(count_combinatorial(&[1, 1, 2, 2, 3, 3], &[1, 1, 2, 2, 3, 3]), 1);
+        assert_eq!(count_combinatorial(&[1, 2, 3, 4, 4, 4], &[1, 2, 3, 4, 4, 4]), 0);
+        assert_eq!(count_combinatorial(&[1, 0], &[9, 9]), 9);
+        assert!(verify(&[1, 0, 0], &[9, 9, 9]));
+        assert!(verify(&to_digits(100000000), &to_digits(987654321)));
+        // far too many to enumerate
+        assert!(count_combinatorial(&to_digits(10u64.pow(17)), &to_digits(10u64.pow(18) - 1)) > 0);
+    }
 }
@@ -0,0 +1,70 @@
+// Compares counting orbits by walking up from every object (like the original day 6 did,
+// O(n * depth)) with the memoized depths of the tree module (O(n)), and finding the first
+// common object of YOU and SAN by comparing every pair of their chains with the LCA query.
+// Uses random maps from generate.rs, from a bushy tree to a single chain.
+// Run `./bench [objects] [seed]`, built with optimizations (see timing.rs).
+#[path = "../../tree/rust/tree.rs"]
+mod tree;
+#[path = "../../timing/rust/timing.rs"]
+mod timing;
+#[allow(dead_code)]
+mod generate;
+
+use std::env;
+
+use generate::{generate, OrbitMapConfig};
+use timing::time;
+use tree::ParentTree;
+
+const RUNS: usize = 5;
+
+fn walked_total(orbits: &ParentTree<String>) -> usize {
+    return orbits.parents.keys().map(|object| orbits.ancestors(object).len()).sum();
+}
+
+fn memoized_total(orbits: &ParentTree<String>) -> usize {
+    return orbits.depths().values().sum();
+}
+
+fn pairwise_distance(orbits: &ParentTree<String>) -> usize {
+    let you_chain = orbits.ancestors(&"YOU".to_string());
+    let santa_chain = orbits.ancestors(&"SAN".to_string());
+    for (i, object) in you_chain.iter().enumerate() {
+        if let Some(j) = santa_chain.iter().position(|o| o == object) {
+            return i + j;
+        }
+    }
+    panic!("YOU and SAN are not connected");
+}
+
+fn lca_distance(orbits: &ParentTree<String>) -> usize {
+    let start = orbits.parent(&"YOU".to_string()).unwrap();
+    let end = orbits.parent(&"SAN".to_string()).unwrap();
+    return orbits.path(start, end).unwrap().len() - 1;
+}
+
+fn main() {
+    timing::warn_if_unoptimized();
+    let args: Vec<String> = env::args().collect();
+    let objects = if args.len() > 1 { args[1].parse::<usize>().unwrap() } else { 10000 };
+    let seed = if args.len() > 2 { args[2].parse::<u64>().unwrap() } else { 1 };
+
+    for &branching in [1000, 10, 2, 1].iter() {
+        let mut orbits = ParentTree::new();
+        for (parent, object) in generate(&OrbitMapConfig { objects: objects, branching: branching, seed: seed }) {
+            orbits.add(object, parent);
+        }
+        let (walked_time, walked) = time(RUNS, &|| walked_total(&orbits));
+        let (memoized_time, memoized) = time(RUNS, &|| memoized_total(&orbits));
+        assert!(walked == memoized, "Totals differ: {} vs {}", walked, memoized);
+        let (pairwise_time, pairwise) = time(RUNS, &|| pairwise_distance(&orbits));
+        let (lca_time, lca) = time(RUNS, &|| lca_distance(&orbits));
+        assert!(pairwise == lca, "Distances differ: {} vs {}", pairwise, lca);
+
+        println!("{} objects, branching {}: {} orbits, distance {}", objects, branching, memoized, lca);
+        println!("  Walking up:  {:?}, memoized: {:?} ({:.2}x)", walked_time, memoized_time,
+            walked_time.as_secs_f64() / memoized_time.as_secs_f64());
+        println!("  Every pair:  {:?}, LCA:      {:?} ({:.2}x)", pairwise_time, lca_time,
+            pairwise_time.as_secs_f64() / lca_time.as_secs_f64());
+    }
+}
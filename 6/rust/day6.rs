@@ -1,54 +1,165 @@
-use std::io::BufReader;
-use std::io::BufRead;
-use std::fs::File;
-use std::collections::HashMap;
-
-fn read_orbit_map(filename: &str) -> HashMap<String, String> {
-    let file = File::open(filename).unwrap();
-    let reader = BufReader::new(&file);
-    let mut map: HashMap<String, String> = HashMap::new();
-    for line in reader.lines() {
-    	let line_str: &String = &line.unwrap();
-        let mut split = line_str.splitn(2, ")");
-        let first = split.next().unwrap();
-        let second = split.next().unwrap();
-        map.insert(second.to_string(), first.to_string());
-    }
-    return map;
-}
+#[path = "../../tree/rust/tree.rs"]
+mod tree;
+mod generate;
+
+use std::env;
+use std::fs;
+use std::process;
+use generate::{format_map, generate, OrbitMapConfig};
+use tree::ParentTree;
 
-fn walk_orbit_chain(orbits: &HashMap<String, String>, object: &String, mut chain: &mut Vec<String>) {
-    if orbits.contains_key(object) {
-        chain.push(orbits[object].clone());
-        walk_orbit_chain(orbits, &orbits[object], &mut chain);
+// One "A)B" per line, B orbits A
+fn parse_orbit_map(text: &str) -> Result<ParentTree<String>, String> {
+    let mut orbits = ParentTree::new();
+    for (i, line) in text.lines().enumerate().filter(|&(_, l)| l.trim().len() > 0) {
+        let objects: Vec<&str> = line.trim().split(')').collect();
+        if objects.len() != 2 || objects.iter().any(|o| o.len() == 0) {
+            return Err(format!("line {}: expected something like 'COM)B', got '{}'", i + 1, line.trim()));
+        }
+        if let Some(parent) = orbits.parent(&objects[1].to_string()) {
+            return Err(format!("line {}: {} already orbits {}", i + 1, objects[1], parent));
+        }
+        orbits.add(objects[1].to_string(), objects[0].to_string());
     }
+    return Ok(orbits);
 }
 
-fn get_orbit_chain(orbits: &HashMap<String, String>, object: &String) -> Vec<String> {
-    let mut chain: Vec<String> = Vec::new();
-    walk_orbit_chain(orbits, object, &mut chain);
-    return chain;
+fn read_orbit_map(filename: &str) -> Result<ParentTree<String>, String> {
+    let text = fs::read_to_string(filename).map_err(|err| format!("Could not read {}: {}", filename, err))?;
+    return parse_orbit_map(&text);
 }
 
-fn get_first_common_object(chain_a: &Vec<String>, chain_b: &Vec<String>) -> Option<(usize, usize)> {
-    for (i, object) in chain_a.iter().enumerate() {
-        match chain_b.iter().position(|x| x == object) {
-            Some(j) => return Some((i, j)),
-            None => ()
+// Everything has to orbit COM, directly or indirectly. Otherwise counting orbits or walking
+// up from an object doesn't end (in a cycle) or doesn't end at COM (in a truncated map).
+fn validate(orbits: &ParentTree<String>) -> Result<(), String> {
+    if orbits.parents.len() == 0 {
+        return Err("The map is empty".to_string());
+    }
+    if let Some(cycle) = orbits.find_cycle() {
+        let names: Vec<&str> = cycle.iter().map(|o| o.as_str()).collect();
+        return Err(format!("Objects orbit each other in a cycle: {} -> {}", names.join(" -> "), names[0]));
+    }
+    let mut roots: Vec<&str> = orbits.roots().into_iter().map(|o| o.as_str()).filter(|&o| o != "COM").collect();
+    if roots.len() > 0 {
+        roots.sort();
+        return Err(format!("Objects other than COM that don't orbit anything (is the map incomplete?): {}", roots.join(", ")));
+    }
+    for &object in ["YOU", "SAN"].iter() {
+        if !orbits.parents.contains_key(object) {
+            return Err(format!("{} is not in the map", object));
         }
     }
-    return None;
+    return Ok(());
 }
 
+// Number of direct and indirect orbits of every object (its depth in the tree)
+fn total_orbits(orbits: &ParentTree<String>) -> usize {
+    return orbits.depths().values().sum();
+}
+
+// The objects to orbit on the way from the one a orbits to the one b orbits, both included.
+// Goes up to the first common object and down again.
+fn get_transfer_path(orbits: &ParentTree<String>, a: &str, b: &str) -> Result<Vec<String>, String> {
+    let start = orbits.parent(&a.to_string()).ok_or(format!("{} is not in the map", a))?;
+    let end = orbits.parent(&b.to_string()).ok_or(format!("{} is not in the map", b))?;
+    let path = orbits.path(start, end).ok_or(format!("There is no way from {} to {}", a, b))?;
+    return Ok(path.into_iter().cloned().collect());
+}
+
+// day6 [--generate <objects> <branching> [seed]]
+// Generating prints a random map instead of solving ../input, see generate.rs
 fn main() {
-    let orbits = read_orbit_map("../input");
-    let mut count = 0;
-    for (object, _) in &orbits {
-        count += get_orbit_chain(&orbits, &object).len();
-    }
-    let you_chain = get_orbit_chain(&orbits, &String::from("YOU"));
-    let santa_chain = get_orbit_chain(&orbits, &String::from("SAN"));
-    let (i, j) = get_first_common_object(&you_chain, &santa_chain).unwrap();
-    println!("Distance: {}", i + j);
-    println!("Total orbits: {}", count);
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() > 0 && args[0] == "--generate" {
+        let number = |index: usize, default: Option<u64>| {
+            return args.get(index).map(|arg| arg.parse::<u64>().unwrap_or_else(|_| panic!("Invalid number '{}'", arg)))
+                .or(default).expect("Usage: day6 --generate <objects> <branching> [seed]");
+        };
+        let config = OrbitMapConfig { objects: number(1, None) as usize, branching: number(2, None) as usize, seed: number(3, Some(1)) };
+        print!("{}", format_map(&generate(&config)));
+        return;
+    }
+    let orbits = read_orbit_map("../input").and_then(|orbits| validate(&orbits).map(|_| orbits));
+    let orbits = orbits.unwrap_or_else(|err| {
+        println!("Invalid orbit map: {}", err);
+        process::exit(1);
+    });
+    println!("Total orbits: {}", total_orbits(&orbits));
+    match get_transfer_path(&orbits, "YOU", "SAN") {
+        Ok(path) => {
+            println!("Distance: {}", path.len() - 1);
+            println!("Transfers: {}", path.join(" -> "));
+        },
+        Err(err) => println!("No transfers: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\n";
+
+    #[test]
+    fn total_orbits() {
+        let orbits = parse_orbit_map(EXAMPLE).unwrap();
+        assert_eq!(super::total_orbits(&orbits), 42);
+        let depths = orbits.depths();
+        assert_eq!((depths[&"D".to_string()], depths[&"L".to_string()]), (3, 7));
+        for object in orbits.parents.keys() {
+            assert_eq!(depths[object], orbits.ancestors(object).len());
+        }
+    }
+
+    #[test]
+    fn transfers() {
+        let orbits = parse_orbit_map(&format!("{}K)YOU\nI)SAN\n", EXAMPLE)).unwrap();
+        let path = get_transfer_path(&orbits, "YOU", "SAN").unwrap();
+        assert_eq!(path.len() - 1, 4);
+        assert_eq!(path, vec!["K", "J", "E", "D", "I"]);
+        assert_eq!(get_transfer_path(&orbits, "YOU", "YOU"), Ok(vec!["K".to_string()]));
+        assert_eq!(get_transfer_path(&orbits, "YOU", "SANTA"), Err("SANTA is not in the map".to_string()));
+    }
+
+    #[test]
+    fn invalid_maps() {
+        let transfers = format!("{}K)YOU\nI)SAN\n", EXAMPLE);
+        assert!(validate(&parse_orbit_map(&transfers).unwrap()).is_ok());
+        assert_eq!(validate(&parse_orbit_map(EXAMPLE).unwrap()).unwrap_err(), "YOU is not in the map");
+        assert_eq!(validate(&parse_orbit_map(&transfers.replace("I)SAN\n", "")).unwrap()).unwrap_err(), "SAN is not in the map");
+        assert_eq!(parse_orbit_map("COM)B\nB)\n").err().unwrap(), "line 2: expected something like 'COM)B', got 'B)'");
+        assert_eq!(parse_orbit_map("COM)B\nC)B\n").err().unwrap(), "line 2: B already orbits COM");
+        let truncated = parse_orbit_map(&EXAMPLE.replace("D)E\n", "")).unwrap();
+        assert_eq!(validate(&truncated).unwrap_err(), "Objects other than COM that don't orbit anything (is the map incomplete?): E");
+        let cycle = parse_orbit_map(&format!("{}A)X\nX)Y\nY)A\n", EXAMPLE)).unwrap();
+        assert!(validate(&cycle).unwrap_err().starts_with("Objects orbit each other in a cycle"));
+        assert_eq!(validate(&parse_orbit_map("A)B\n").unwrap()).unwrap_err(), "Objects other than COM that don't orbit anything (is the map incomplete?): A");
+        assert_eq!(validate(&parse_orbit_map("\n").unwrap()).unwrap_err(), "The map is empty");
+    }
+
+    #[test]
+    fn generated_maps() {
+        for seed in 1..20 {
+            for &branching in [1, 2, 5, 1000].iter() {
+                let text = format_map(&generate(&OrbitMapConfig { objects: 200, branching: branching, seed: seed }));
+                let orbits = parse_orbit_map(&text).unwrap();
+                assert_eq!(validate(&orbits), Ok(()));
+                let walked: usize = orbits.parents.keys().map(|object| orbits.ancestors(object).len()).sum();
+                assert_eq!(super::total_orbits(&orbits), walked);
+
+                // against the first common object of both chains, found by comparing every pair
+                let you_chain = orbits.ancestors(&"YOU".to_string());
+                let santa_chain = orbits.ancestors(&"SAN".to_string());
+                let (i, j) = (0..you_chain.len()).filter_map(|i| santa_chain.iter().position(|&o| o == you_chain[i]).map(|j| (i, j)))
+                    .next().unwrap();
+                let path = get_transfer_path(&orbits, "YOU", "SAN").unwrap();
+                assert_eq!(path.len() - 1, i + j);
+                assert_eq!((&path[0], &path[path.len() - 1]), (you_chain[0], santa_chain[0]));
+            }
+        }
+        // a chain of them
+        let chain = parse_orbit_map(&format_map(&generate(&OrbitMapConfig { objects: 100, branching: 1, seed: 7 }))).unwrap();
+        let depths = chain.depths();
+        assert_eq!(super::total_orbits(&chain), 100 * 101 / 2 + depths[&"YOU".to_string()] + depths[&"SAN".to_string()]);
+    }
 }
@@ -0,0 +1,45 @@
+// Random orbit maps for benchmarks and property tests. Every new object orbits one of the
+// `branching` objects added last, so branching 1 is a single long chain (the worst case for
+// walking up from every object) and large values give a wide, shallow tree.
+// YOU and SAN orbit random objects. The lines are shuffled, like in the real input.
+
+#[path = "../../rng/rust/rng.rs"]
+mod rng;
+
+use self::rng::Rng;
+
+pub struct OrbitMapConfig {
+    pub objects: usize, // besides COM, YOU and SAN
+    pub branching: usize,
+    pub seed: u64,
+}
+
+// Object names are hex numbers, so they can't clash with COM, YOU or SAN
+fn name(index: usize) -> String {
+    return if index == 0 { "COM".to_string() } else { format!("{:X}", index) };
+}
+
+// (parent, object) pairs
+pub fn generate(config: &OrbitMapConfig) -> Vec<(String, String)> {
+    assert!(config.branching > 0, "Branching has to be at least 1");
+    let mut rng = Rng::new(config.seed);
+    let mut orbits = Vec::with_capacity(config.objects + 2);
+    for i in 1..config.objects + 1 {
+        let parent = i - 1 - rng.below(config.branching.min(i) as u64) as usize;
+        orbits.push((name(parent), name(i)));
+    }
+    for &object in ["YOU", "SAN"].iter() {
+        let parent = rng.below(config.objects as u64 + 1) as usize;
+        orbits.push((name(parent), object.to_string()));
+    }
+    for i in (1..orbits.len()).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        orbits.swap(i, j);
+    }
+    return orbits;
+}
+
+pub fn format_map(orbits: &[(String, String)]) -> String {
+    let lines: Vec<String> = orbits.iter().map(|&(ref parent, ref object)| format!("{}){}", parent, object)).collect();
+    return lines.join("\n") + "\n";
+}
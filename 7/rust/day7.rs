@@ -1,287 +1,72 @@
-use std::fs;
-use std::collections::VecDeque;
+#[path = "../../intcode/rust/intcode.rs"]
+mod intcode;
 
-trait InputSource {
-    fn read(&mut self) -> i64;
-    fn len(&self) -> usize;
-}
-
-trait OutputSink {
-    fn write(&mut self, value: i64);
-}
-
-impl InputSource for VecDeque<i64> {
-    fn read(&mut self) -> i64 {
-        if self.len() == 0 {
-            panic!("InputSource VecDeque is empty!");
-        }
-        return self.pop_front().unwrap();
-    }
+use std::collections::HashMap;
+use std::env;
+use std::time::Instant;
+use intcode::generator::Rng;
+use intcode::pipeline::Pipeline;
+use intcode::{read_patched_program, Cell};
 
-    fn len(&self) -> usize {
-        return self.len();
-    }
-}
-
-impl OutputSink for VecDeque<i64> {
-    fn write(&mut self, value: i64) {
-        self.push_back(value);
-    }
-}
+const MAX_EXHAUSTIVE_AMPS: usize = 10; // 10! runs of the circuit already take a while
 
-struct ConsoleOutputSink {
+// Where the output of every amp goes. The first amp gets the initial 0 signal (after its
+// phase setting), the last output of the last amp is what is sent to the thrusters.
+struct Topology {
+    amps: usize,
+    edges: Vec<(usize, usize)>, // (from, to), an output goes to every amp it is connected to
 }
 
-impl OutputSink for ConsoleOutputSink {
-    fn write(&mut self, value: i64) {
-        println!("{}", value);
+impl Topology {
+    fn series(amps: usize) -> Topology {
+        return Topology { amps: amps, edges: (1..amps).map(|i| (i - 1, i)).collect() };
     }
-}
-
-#[derive(PartialEq)]
-enum ParamMode {
-    Position,
-    Immediate
-}
 
-impl ParamMode {
-    fn read(instruction: i64, param_num: usize) -> ParamMode {
-        let digit_base = 10i64.pow(param_num as u32 + 1);
-        return match (instruction / digit_base) % 10 {
-            0 => ParamMode::Position,
-            1 => ParamMode::Immediate,
-            _ => panic!("Unrecognized parameter mode digit")
-        }
+    fn ring(amps: usize) -> Topology {
+        let mut topology = Topology::series(amps);
+        topology.edges.push((amps - 1, 0));
+        return topology;
     }
-}
-
-#[derive(PartialEq)]
-enum OpCode {
-    Add,
-    Mul,
-    Input,
-    Output,
-    JumpIfTrue,
-    JumpIfFalse,
-    LessThan,
-    Equals,
-    Terminate
-}
-
-#[derive(PartialEq)]
-enum ParamType {
-    Read,
-    Write
-}
-
-struct Operation {
-    op_code: OpCode,
-    parameters: Vec<ParamType>,
-}
 
-impl Operation {
-    fn read(instruction: i64) -> Operation {
-        // I would make these guys static, but I cannot have a vec in a static, so I
-        // allocate and copy a bunch instead :)
-        return match instruction % 100 {
-            1 => Operation { op_code: OpCode::Add,
-                             parameters: vec![ParamType::Read, ParamType::Read, ParamType::Write] },
-            2 => Operation { op_code: OpCode::Mul,
-                             parameters: vec![ParamType::Read, ParamType::Read, ParamType::Write] },
-            3 => Operation { op_code: OpCode::Input, parameters: vec![ParamType::Write] },
-            4 => Operation { op_code: OpCode::Output, parameters: vec![ParamType::Read] },
-            5 => Operation { op_code: OpCode::JumpIfTrue, 
-                             parameters: vec![ParamType::Read, ParamType::Read] },
-            6 => Operation { op_code: OpCode::JumpIfFalse, 
-                             parameters: vec![ParamType::Read, ParamType::Read] },
-            7 => Operation { op_code: OpCode::LessThan,
-                             parameters: vec![ParamType::Read, ParamType::Read, ParamType::Write] },
-            8 => Operation { op_code: OpCode::Equals,
-                             parameters: vec![ParamType::Read, ParamType::Read, ParamType::Write] },
-            99 => Operation { op_code: OpCode::Terminate, parameters: vec![] },
-            _ => panic!("Unknown opcode: {}", instruction)
-        }
-    }
-    
-    fn get_param_address(&self, memory: &Vec<i64>, ip: usize, param_num: usize) -> usize {
-        let param_pointer = ip + param_num;
-        if param_pointer >= memory.len() {
-            panic!("Cannot read parameter {} for instruction {} at {}. Out of bounds.", param_num, memory[ip], ip);
-        }
-        let mode = ParamMode::read(memory[ip], param_num);
-        match mode {
-            ParamMode::Position => {
-                let address = memory[param_pointer];
-                if address < 0 || address as usize > memory.len() {
-                    panic!("Cannot read address pointed to by parameter: {}. Out of bounds.", address);
-                }
-                return address as usize;
+    // "0>1,1>2,2>0", the number of amps is the highest one mentioned + 1
+    fn parse(text: &str) -> Result<Topology, String> {
+        let mut edges = Vec::new();
+        for edge in text.split(',') {
+            let amps: Vec<Option<usize>> = edge.split('>').map(|amp| amp.trim().parse::<usize>().ok()).collect();
+            match amps.as_slice() {
+                &[Some(from), Some(to)] => edges.push((from, to)),
+                _ => return Err(format!("Invalid connection '{}', expected something like 0>1", edge)),
             }
-            ParamMode::Immediate => {
-                if self.parameters[param_num - 1] == ParamType::Write {
-                    panic!("Write parameter {} must not be in immediate mode for instruction: {}", param_num, memory[ip]);
-                }
-                return param_pointer;
-            }
-        }
-    }
-
-    fn execute<I: InputSource, O: OutputSink>(&self, memory: &mut Vec<i64>, ip: usize, input_source: &mut I, output_sink: &mut O) -> Option<usize> {
-        let param = |param_num: usize| self.get_param_address(&memory, ip, param_num);
-        let validate_addr = |value: i64| {
-            if value < 0 {
-                panic!("Cannot jump to negative address");
-            }
-            return value as usize;
-        };
-        match self.op_code {
-            OpCode::Add => {
-                let addr = param(3);
-                memory[addr] = memory[param(1)] + memory[param(2)];
-            },
-            OpCode::Mul => {
-                let addr = param(3);
-                memory[addr] = memory[param(1)] * memory[param(2)];
-            },
-            OpCode::Input => {
-                let addr = param(1);
-                memory[addr] = input_source.read();
-            },
-            OpCode::Output => {
-                output_sink.write(memory[param(1)]);
-            },
-            OpCode::JumpIfTrue => {
-                let addr = param(1); 
-                if memory[addr] != 0 {
-                    return Some(validate_addr(memory[param(2)]));
-                }
-            },
-            OpCode::JumpIfFalse => {
-                let addr = param(1);
-                if memory[addr] == 0 {
-                    return Some(validate_addr(memory[param(2)]));
-                }
-            },
-            OpCode::LessThan => {
-                let addr = param(3);
-                memory[addr] = if memory[param(1)] < memory[param(2)] { 1 } else { 0 }
-            }
-            OpCode::Equals => {
-                let addr = param(3);
-                memory[addr] = if memory[param(1)] == memory[param(2)] { 1 } else { 0 }
-            }
-            OpCode::Terminate => return None,
-        }
-        return Some(ip + 1 + self.parameters.len());
-    }
-}
-
-fn read_program(filename: &str) -> Vec<i64> {
-    fn parse_int(s: &str) -> i64 {
-        return s.trim().parse::<i64>().unwrap();
-    }
-
-    let program_str = fs::read_to_string(&filename).unwrap();
-    return program_str.split(",").map(parse_int).collect::<Vec<i64>>();
-}
-
-#[derive(Copy, Clone, PartialEq)]
-enum VmState {
-    NotStarted,
-    Running,
-    WaitForInput,
-    Terminated,
-}
-
-struct Vm<I: InputSource, O: OutputSink> {
-    memory: Vec<i64>,
-    instruction_pointer: usize,
-    input_source: I,
-    output_sink: O,
-    state: VmState,
-}
-
-impl<I: InputSource + Default, O: OutputSink + Default> Vm<I, O> {
-    fn new(program: Vec<i64>) -> Vm<I, O> {
-        return Vm {
-            memory: program,
-            instruction_pointer: 0,
-            input_source: I::default(),
-            output_sink: O::default(),
-            state: VmState::NotStarted,
-        };
-    }
-
-    fn step(&mut self) -> VmState {
-        self.state = VmState::Running;
-        let operation = Operation::read(self.memory[self.instruction_pointer]);
-        if operation.op_code == OpCode::Input && self.input_source.len() == 0 {
-            self.state = VmState::WaitForInput;
-            return self.state;
         }
-        let new_ip = operation.execute(&mut self.memory, self.instruction_pointer, &mut self.input_source, &mut self.output_sink);
-        match new_ip {
-            Some(v) => self.instruction_pointer = v,
-            None => self.state = VmState::Terminated,
-        }
-        return self.state;
+        let amps = edges.iter().map(|&(from, to)| from.max(to)).max().unwrap() + 1;
+        return Ok(Topology { amps: amps, edges: edges });
     }
 
-    fn run(&mut self) -> VmState {
-        while self.instruction_pointer < self.memory.len() {
-            match self.step() {
-                VmState::NotStarted => panic!("Invalid state after step()"),
-                VmState::Running => (), // keep going
-                VmState::WaitForInput => break, // suspend
-                VmState::Terminated => break // done
-            }
-        }
-        return self.state;
+    // Signals going back to an earlier amp need the phase settings of the feedback loop mode
+    fn has_feedback(&self) -> bool {
+        return self.edges.iter().any(|&(from, to)| to <= from);
     }
-}
 
-fn test_amp_circuit(program: &Vec<i64>, phase_setting: &Vec<i64>) -> i64 {
-    let mut input = 0;
-    for i in 0..5 {
-        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program.clone());
-        vm.input_source.push_back(phase_setting[i]);
-        vm.input_source.push_back(input);
-        vm.run();
-        assert!(vm.output_sink.len() == 1);
-        input = vm.output_sink[0];
+    // The amps are reused for every phase setting, so we don't allocate new VMs per permutation
+    fn pipeline(&self, program: &Vec<Cell>) -> Pipeline {
+        return Pipeline::copies(program, self.amps).with_connections(&self.edges);
     }
-    return input;
 }
 
-fn test_amp_feedback_circuit(program: &Vec<i64>, phase_setting: &Vec<i64>) -> i64 {
-    let mut amps: Vec<Vm<VecDeque<i64>, VecDeque<i64>>> = Vec::new();
-    const AMP_COUNT: usize = 5;
-    for i in 0..AMP_COUNT {
-        amps.push(Vm::new(program.clone()));
-        amps[i].input_source.push_back(phase_setting[i]);
-    }
-
-    let mut input = 0;
-    loop {
-        for i in 0..AMP_COUNT {
-            assert!(amps[i].state != VmState::Terminated);
-            amps[i].input_source.push_back(input);
-            amps[i].run();
-            assert!(amps[i].output_sink.len() == 1);
-            input = amps[i].output_sink.pop_front().unwrap();
-        }
-        if amps[AMP_COUNT-1].state == VmState::Terminated {
-            return input;
-        }
-    }
+// The amps run in turn, each until it needs input nobody has sent yet, until all of them are done
+fn run_amp_circuit(amps: &mut Pipeline, program: &Vec<Cell>, phase_setting: &Vec<i64>) -> i64 {
+    amps.reset(program);
+    amps.push_first_inputs(phase_setting);
+    return *amps.run(&[0]).last().expect("The last amp didn't output anything");
 }
 
-fn optimize_phase_setting(program: &Vec<i64>, init_phase_setting: &Vec<i64>, system: fn(&Vec<i64>, &Vec<i64>) -> i64) -> (i64, Vec<i64>) {
+fn optimize_phase_setting(program: &Vec<Cell>, topology: &Topology, init_phase_setting: &Vec<i64>) -> (i64, Vec<i64>) {
+    let mut amps = topology.pipeline(program);
     let mut phase_setting = init_phase_setting.clone();
-    let mut max_output = 0;
+    let mut max_output = i64::min_value();
     let mut max_phase_setting = phase_setting.clone();
     loop {
-        let output = system(program, &phase_setting);
+        let output = run_amp_circuit(&mut amps, program, &phase_setting);
         if output > max_output {
             max_output = output;
             max_phase_setting = phase_setting.clone();
@@ -294,6 +79,50 @@ fn optimize_phase_setting(program: &Vec<i64>, init_phase_setting: &Vec<i64>, sys
     return (max_output, max_phase_setting);
 }
 
+// Simulated annealing over swaps of two phases, for when there are too many amps to try
+// every permutation. A worse setting is accepted with a probability that only depends on
+// the temperature, not on how much worse it is: depending on the program outputs range from
+// a few hundred to billions and the last amps only change the last digits.
+fn anneal_phase_setting(program: &Vec<Cell>, topology: &Topology, init_phase_setting: &Vec<i64>, iterations: usize, seed: u64) -> (i64, Vec<i64>) {
+    const START_TEMPERATURE: f64 = 0.5;
+    const END_TEMPERATURE: f64 = 0.001;
+    let mut amps = topology.pipeline(program);
+    let mut outputs: HashMap<Vec<i64>, i64> = HashMap::new(); // settings are visited again a lot
+    let mut evaluate = |phase_setting: &Vec<i64>| {
+        if let Some(&output) = outputs.get(phase_setting) {
+            return output;
+        }
+        let output = run_amp_circuit(&mut amps, program, phase_setting);
+        outputs.insert(phase_setting.clone(), output);
+        return output;
+    };
+    let mut rng = Rng::new(seed);
+    let mut phase_setting = init_phase_setting.clone();
+    let mut output = evaluate(&phase_setting);
+    let (mut max_output, mut max_phase_setting) = (output, phase_setting.clone());
+    if phase_setting.len() < 2 {
+        return (max_output, max_phase_setting);
+    }
+    for i in 0..iterations {
+        let temperature = START_TEMPERATURE * (END_TEMPERATURE / START_TEMPERATURE).powf(i as f64 / iterations as f64);
+        let a = rng.below(phase_setting.len() as u64) as usize;
+        let b = (a + 1 + rng.below(phase_setting.len() as u64 - 1) as usize) % phase_setting.len();
+        let mut candidate = phase_setting.clone();
+        candidate.swap(a, b);
+        let candidate_output = evaluate(&candidate);
+        let random = rng.below(1 << 30) as f64 / (1 << 30) as f64;
+        if candidate_output >= output || random < temperature {
+            phase_setting = candidate;
+            output = candidate_output;
+            if output > max_output {
+                max_output = output;
+                max_phase_setting = phase_setting.clone();
+            }
+        }
+    }
+    return (max_output, max_phase_setting);
+}
+
 // Returns permutation that is greater than the input (as little as possible)
 fn next_permutation<T: PartialOrd + Copy + std::fmt::Debug>(input: &Vec<T>) -> Option<Vec<T>> {
     // find longest weakly decreasing suffix
@@ -324,15 +153,113 @@ fn next_permutation<T: PartialOrd + Copy + std::fmt::Debug>(input: &Vec<T>) -> O
     return Some(out);
 }
 
+fn arg_value(args: &Vec<String>, name: &str) -> Option<String> {
+    return args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1)).cloned();
+}
+
+// Exhaustive search by default. With --anneal only simulated annealing is used, --compare
+// runs both (if there aren't too many amps) and times them.
+fn optimize(program: &Vec<Cell>, topology: &Topology, init_phase_setting: &Vec<i64>, args: &Vec<String>) -> (i64, Vec<i64>) {
+    let iterations = arg_value(args, "--iterations").map(|n| n.parse::<usize>().expect("Invalid number of iterations"))
+        .unwrap_or(200 * topology.amps);
+    let seed = arg_value(args, "--seed").map(|n| n.parse::<u64>().expect("Invalid seed")).unwrap_or(1);
+    if args.iter().any(|arg| arg == "--compare") {
+        let start = Instant::now();
+        let annealed = anneal_phase_setting(program, topology, init_phase_setting, iterations, seed);
+        println!("Annealing ({} iterations): {} {:?} in {:?}", iterations, annealed.0, annealed.1, start.elapsed());
+        if topology.amps > MAX_EXHAUSTIVE_AMPS {
+            println!("Exhaustive search: skipped, {} amps have too many permutations", topology.amps);
+            return annealed;
+        }
+        let start = Instant::now();
+        let exhaustive = optimize_phase_setting(program, topology, init_phase_setting);
+        println!("Exhaustive search: {} {:?} in {:?}", exhaustive.0, exhaustive.1, start.elapsed());
+        if annealed.0 < exhaustive.0 {
+            println!("Annealing missed the optimum by {}", exhaustive.0 - annealed.0);
+        }
+        return exhaustive;
+    }
+    if args.iter().any(|arg| arg == "--anneal") {
+        return anneal_phase_setting(program, topology, init_phase_setting, iterations, seed);
+    }
+    return optimize_phase_setting(program, topology, init_phase_setting);
+}
+
+// day7 [--amps N] [--topology <from>><to>,...] [--anneal | --compare] [--iterations N] [--seed N]
+// Without a topology both the series and the feedback loop of N (5) amps are optimized.
 fn main() {
-    let program = read_program("../input");
-    
-    let init_phase_setting: Vec<i64> = vec![0, 1, 2, 3, 4];
-    let (max_output, max_phase_setting) = optimize_phase_setting(&program, &init_phase_setting, test_amp_circuit);
+    let args: Vec<String> = env::args().collect();
+    let program = read_patched_program("../input");
+
+    if let Some(text) = arg_value(&args, "--topology") {
+        let topology = Topology::parse(&text).unwrap_or_else(|err| panic!("{}", err));
+        let first_phase = if topology.has_feedback() { 5 } else { 0 };
+        let init_phase_setting: Vec<i64> = (first_phase..first_phase + topology.amps as i64).collect();
+        let (max_output, max_phase_setting) = optimize(&program, &topology, &init_phase_setting, &args);
+        println!("Max output: {}. Phase setting: {:?}", max_output, max_phase_setting);
+        return;
+    }
+    let amp_count = arg_value(&args, "--amps").map(|n| n.parse::<usize>().expect("Invalid number of amps")).unwrap_or(5);
+    assert!(amp_count > 0, "Need at least one amp");
+
+    let init_phase_setting: Vec<i64> = (0..amp_count as i64).collect();
+    let (max_output, max_phase_setting) = optimize(&program, &Topology::series(amp_count), &init_phase_setting, &args);
     println!("Max output: {}. Phase setting: {:?}", max_output, max_phase_setting);
 
-    let init_fb_phase_setting: Vec<i64> = vec![5, 6, 7, 8, 9];
-    test_amp_feedback_circuit(&program, &init_fb_phase_setting);
-    let (max_fb_output, max_fb_phase_setting) = optimize_phase_setting(&program, &init_fb_phase_setting, test_amp_feedback_circuit);
+    let init_fb_phase_setting: Vec<i64> = (5..5 + amp_count as i64).collect();
+    let (max_fb_output, max_fb_phase_setting) = optimize(&program, &Topology::ring(amp_count), &init_fb_phase_setting, &args);
     println!("Max feedback system output: {}, Phase setting: {:?}", max_fb_output, max_fb_phase_setting);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::parse_program as parse;
+
+    #[test]
+    fn series() {
+        let program = parse("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        assert_eq!(optimize_phase_setting(&program, &Topology::series(5), &vec![0, 1, 2, 3, 4]), (43210, vec![4, 3, 2, 1, 0]));
+        let program = parse("3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0");
+        assert_eq!(optimize_phase_setting(&program, &Topology::series(5), &vec![0, 1, 2, 3, 4]), (65210, vec![1, 0, 4, 3, 2]));
+        // every amp appends its phase as a digit
+        let program = parse("3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0");
+        let mut amps = Topology::series(3).pipeline(&program);
+        assert_eq!(run_amp_circuit(&mut amps, &program, &vec![4, 2, 3]), 132);
+    }
+
+    #[test]
+    fn feedback() {
+        let program = parse("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5");
+        assert_eq!(optimize_phase_setting(&program, &Topology::ring(5), &vec![5, 6, 7, 8, 9]), (139629729, vec![9, 8, 7, 6, 5]));
+        let program = parse("3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10");
+        assert_eq!(optimize_phase_setting(&program, &Topology::ring(5), &vec![5, 6, 7, 8, 9]), (18216, vec![9, 7, 8, 5, 6]));
+    }
+
+    #[test]
+    fn annealing() {
+        let program = parse("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        assert_eq!(anneal_phase_setting(&program, &Topology::series(5), &vec![0, 1, 2, 3, 4], 500, 1), (43210, vec![4, 3, 2, 1, 0]));
+        let program = parse("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5");
+        assert_eq!(anneal_phase_setting(&program, &Topology::ring(5), &vec![5, 6, 7, 8, 9], 500, 1), (139629729, vec![9, 8, 7, 6, 5]));
+        // far too many permutations to try them all, the best one is descending again
+        let program = parse("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        let (output, phase_setting) = anneal_phase_setting(&program, &Topology::series(12), &(0..12).collect(), 5000, 1);
+        assert_eq!(phase_setting, (0..12).rev().collect::<Vec<i64>>());
+        assert_eq!(output, 1209876543210);
+    }
+
+    #[test]
+    fn topologies() {
+        let ring = Topology::parse("0>1, 1>2,2>0").unwrap();
+        assert_eq!((ring.amps, ring.has_feedback()), (3, true));
+        assert_eq!(ring.edges, Topology::ring(3).edges);
+        assert!(!Topology::series(4).has_feedback());
+        assert!(Topology::parse("0>1,2").is_err());
+        // the first amp feeds both others, the last one halts after using its signal and
+        // never reads the one from the second amp
+        let program = parse("3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0");
+        let mut amps = Topology::parse("0>1,0>2,1>2").unwrap().pipeline(&program);
+        assert_eq!(run_amp_circuit(&mut amps, &program, &vec![4, 2, 3]), 12);
+    }
+}
@@ -0,0 +1,108 @@
+// Just enough PNG to save small images: 8 bit RGBA, no filtering and the zlib stream
+// uses stored (uncompressed) blocks, so no compressor is needed.
+use std::fs;
+use std::io;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_BLOCK: usize = 65535;
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    return !crc;
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    return (b << 16) | a;
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01]; // deflate, 32K window, no preset dictionary
+    let blocks: Vec<&[u8]> = if data.len() == 0 { vec![data] } else { data.chunks(MAX_STORED_BLOCK).collect() };
+    for (i, block) in blocks.iter().enumerate() {
+        out.push(if i == blocks.len() - 1 { 1 } else { 0 }); // last block flag, type 00 = stored
+        let length = block.len() as u16;
+        out.extend_from_slice(&length.to_le_bytes());
+        out.extend_from_slice(&(!length).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    return out;
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Pixels row by row
+pub fn encode_rgba(width: usize, height: usize, pixels: &[[u8; 4]]) -> Vec<u8> {
+    assert!(pixels.len() == width * height, "Expected {}x{} pixels, got {}", width, height, pixels.len());
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]); // bit depth, RGBA, compression, filter, no interlacing
+
+    let mut scanlines = Vec::with_capacity(height * (1 + width * 4));
+    for row in pixels.chunks(width.max(1)).take(height) {
+        scanlines.push(0); // filter type none
+        for pixel in row {
+            scanlines.extend_from_slice(pixel);
+        }
+    }
+
+    let mut out = SIGNATURE.to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib_stored(&scanlines));
+    chunk(&mut out, b"IEND", &[]);
+    return out;
+}
+
+pub fn write_rgba(filename: &str, width: usize, height: usize, pixels: &[[u8; 4]]) -> io::Result<()> {
+    return fs::write(filename, encode_rgba(width, height, pixels));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn encoding() {
+        let png = encode_rgba(2, 1, &[[0, 0, 0, 255], [255, 255, 255, 0]]);
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[8..16], b"\0\0\0\x0dIHDR");
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+        // one block of 9 bytes: the filter byte and two pixels
+        let idat = &png[33 + 8..];
+        assert_eq!(&idat[..7], &[0x78, 0x01, 1, 9, 0, 0xf6, 0xff]);
+
+        // big enough to need several stored blocks
+        let data = vec![7; MAX_STORED_BLOCK * 2 + 10];
+        let zlib = zlib_stored(&data);
+        assert_eq!(zlib.len(), 2 + 3 * 5 + data.len() + 4);
+        assert_eq!(zlib[2 + 5 + MAX_STORED_BLOCK], 0);
+        assert_eq!(zlib[2 + 2 * (5 + MAX_STORED_BLOCK)], 1);
+    }
+}
@@ -0,0 +1,128 @@
+// Shared code for the maze puzzles, included by the individual days via:
+//   #[path = "../../grid/rust/grid.rs"]
+//   mod grid;
+// Not every day uses every part of it.
+#![allow(dead_code, unused_imports)]
+
+use std::fmt;
+
+mod search;
+
+pub use self::search::{bfs, dijkstra, shortest_path};
+
+pub type Pos = (usize, usize); // (x, y)
+
+// A rectangular map of characters, y goes down
+#[derive(Clone, PartialEq, Debug)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u8>,
+}
+
+impl Grid {
+    // Shorter lines are padded with spaces, empty lines at the end are dropped
+    pub fn parse(text: &str) -> Grid {
+        let mut lines: Vec<&str> = text.lines().collect();
+        while lines.last().map_or(false, |line| line.trim().len() == 0) {
+            lines.pop();
+        }
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for line in &lines {
+            cells.extend(line.bytes());
+            cells.extend((line.len()..width).map(|_| b' '));
+        }
+        return Grid {
+            width: width,
+            height: lines.len(),
+            cells: cells,
+        };
+    }
+
+    pub fn at(&self, pos: Pos) -> u8 {
+        assert!(pos.0 < self.width && pos.1 < self.height, "{:?} is outside of the grid", pos);
+        return self.cells[pos.1 * self.width + pos.0];
+    }
+
+    // None outside of the grid
+    pub fn get(&self, x: i64, y: i64) -> Option<u8> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        return Some(self.at((x as usize, y as usize)));
+    }
+
+    pub fn set(&mut self, pos: Pos, c: u8) {
+        assert!(pos.0 < self.width && pos.1 < self.height, "{:?} is outside of the grid", pos);
+        self.cells[pos.1 * self.width + pos.0] = c;
+    }
+
+    // Row by row
+    pub fn positions(&self) -> Vec<Pos> {
+        let width = self.width;
+        return (0..self.height).flat_map(|y| (0..width).map(move |x| (x, y))).collect();
+    }
+
+    pub fn find(&self, c: u8) -> Vec<Pos> {
+        return self.positions().into_iter().filter(|&pos| self.at(pos) == c).collect();
+    }
+
+    // The (up to) four orthogonal neighbours that are inside of the grid
+    pub fn neighbours(&self, pos: Pos) -> Vec<Pos> {
+        let (x, y) = pos;
+        let mut result = Vec::with_capacity(4);
+        if y > 0 {
+            result.push((x, y - 1));
+        }
+        if x > 0 {
+            result.push((x - 1, y));
+        }
+        if x + 1 < self.width {
+            result.push((x + 1, y));
+        }
+        if y + 1 < self.height {
+            result.push((x, y + 1));
+        }
+        return result;
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.cells.chunks(self.width.max(1)) {
+            writeln!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid() {
+        let mut grid = Grid::parse("#.#\n#\n\n");
+        assert_eq!((grid.width, grid.height), (3, 2));
+        assert_eq!(grid.at((1, 1)), b' ');
+        assert_eq!(grid.get(-1, 0), None);
+        assert_eq!(grid.find(b'.'), vec![(1, 0)]);
+        assert_eq!(grid.neighbours((0, 0)), vec![(1, 0), (0, 1)]);
+        grid.set((1, 1), b'@');
+        assert_eq!(grid.to_string(), "#.#\n#@ \n");
+    }
+
+    #[test]
+    fn searches() {
+        let grid = Grid::parse("#####\n#a..#\n#.#.#\n#..b#\n#####");
+        let start = grid.find(b'a')[0];
+        let open = |pos: &Pos| -> Vec<Pos> { grid.neighbours(*pos).into_iter().filter(|&n| grid.at(n) != b'#').collect() };
+        assert_eq!(bfs(start, &open).len(), 8);
+        assert_eq!(shortest_path(start, &open, |&pos| grid.at(pos) == b'b'), Some(4));
+        assert_eq!(shortest_path(start, &open, |_| false), None);
+        // going right costs 5
+        let weighted = |pos: &Pos| -> Vec<(Pos, usize)> { open(pos).into_iter().map(|n| (n, if n.0 > pos.0 { 5 } else { 1 })).collect() };
+        assert_eq!(dijkstra(start, weighted, |&pos| grid.at(pos) == b'b'), Some(12));
+    }
+}
@@ -0,0 +1,70 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+// Distances from start to every node that can be reached. Nodes can be anything,
+// e.g. positions in a grid or (position, level) pairs.
+pub fn bfs<N, F>(start: N, mut neighbours: F) -> HashMap<N, usize>
+    where N: Clone + Eq + Hash, F: FnMut(&N) -> Vec<N> {
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    distances.insert(start.clone(), 0);
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node];
+        for next in neighbours(&node) {
+            if !distances.contains_key(&next) {
+                distances.insert(next.clone(), distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    return distances;
+}
+
+// Number of steps to the closest node for which is_goal is true
+pub fn shortest_path<N, F, G>(start: N, mut neighbours: F, mut is_goal: G) -> Option<usize>
+    where N: Clone + Eq + Hash, F: FnMut(&N) -> Vec<N>, G: FnMut(&N) -> bool {
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    distances.insert(start.clone(), 0);
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        let distance = distances[&node];
+        if is_goal(&node) {
+            return Some(distance);
+        }
+        for next in neighbours(&node) {
+            if !distances.contains_key(&next) {
+                distances.insert(next.clone(), distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    return None;
+}
+
+// Like shortest_path, but neighbours returns (node, cost)
+pub fn dijkstra<N, F, G>(start: N, mut neighbours: F, mut is_goal: G) -> Option<usize>
+    where N: Clone + Eq + Hash + Ord, F: FnMut(&N) -> Vec<(N, usize)>, G: FnMut(&N) -> bool {
+    let mut best = HashMap::new();
+    let mut queue = BinaryHeap::new();
+    best.insert(start.clone(), 0);
+    queue.push(Reverse((0, start)));
+    while let Some(Reverse((distance, node))) = queue.pop() {
+        if best[&node] < distance {
+            continue; // found a shorter way in the meantime
+        }
+        if is_goal(&node) {
+            return Some(distance);
+        }
+        for (next, cost) in neighbours(&node) {
+            let next_distance = distance + cost;
+            if best.get(&next).map_or(true, |&d| next_distance < d) {
+                best.insert(next.clone(), next_distance);
+                queue.push(Reverse((next_distance, next)));
+            }
+        }
+    }
+    return None;
+}
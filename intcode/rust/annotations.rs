@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use super::sourcemap::SourceMap;
+
+// Names and comments for addresses, written while reverse engineering a program (usually
+// from the debugger) and kept next to it as <program>.notes, one entry per line:
+//   name <address> <name>
+//   comment <address> <text>
+// Unlike the source map this is meant to be edited by hand as well.
+#[derive(Clone, Default, Debug)]
+pub struct Annotations {
+    pub names: BTreeMap<usize, String>,
+    pub comments: BTreeMap<usize, String>,
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        return Annotations {
+            names: BTreeMap::new(),
+            comments: BTreeMap::new(),
+        };
+    }
+
+    pub fn notes_filename(program_filename: &str) -> String {
+        return format!("{}.notes", program_filename);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.names.is_empty() && self.comments.is_empty();
+    }
+
+    // An empty name or comment removes it
+    pub fn set_name(&mut self, address: usize, name: &str) {
+        if name.is_empty() {
+            self.names.remove(&address);
+        } else {
+            self.names.insert(address, name.to_string());
+        }
+    }
+
+    pub fn set_comment(&mut self, address: usize, comment: &str) {
+        if comment.is_empty() {
+            self.comments.remove(&address);
+        } else {
+            self.comments.insert(address, comment.to_string());
+        }
+    }
+
+    pub fn save(&self, filename: &str) -> Result<(), String> {
+        let mut text = String::new();
+        for (address, name) in self.names.iter() {
+            text.push_str(&format!("name {} {}\n", address, name));
+        }
+        for (address, comment) in self.comments.iter() {
+            text.push_str(&format!("comment {} {}\n", address, comment));
+        }
+        return fs::write(filename, text).map_err(|e| format!("Could not write {}: {}", filename, e));
+    }
+
+    pub fn load(filename: &str) -> Result<Annotations, String> {
+        let text = fs::read_to_string(filename).map_err(|e| format!("Could not read {}: {}", filename, e))?;
+        let mut notes = Annotations::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts: Vec<&str> = line.splitn(3, ' ').collect();
+            let address = parts.get(1).and_then(|a| a.parse::<usize>().ok())
+                .ok_or(format!("{}:{}: invalid address", filename, i + 1))?;
+            match (parts[0], parts.get(2)) {
+                ("name", Some(name)) if !name.contains(char::is_whitespace) => notes.set_name(address, name),
+                ("comment", Some(comment)) => notes.set_comment(address, comment.trim()),
+                _ => return Err(format!("{}:{}: invalid entry '{}'", filename, i + 1, line)),
+            }
+        }
+        return Ok(notes);
+    }
+
+    // Looks for <program>.notes, no file means no notes yet
+    pub fn load_for_program(program_filename: &str) -> Annotations {
+        let filename = Annotations::notes_filename(program_filename);
+        if !fs::metadata(&filename).is_ok() {
+            return Annotations::new();
+        }
+        return match Annotations::load(&filename) {
+            Ok(notes) => notes,
+            Err(err) => {
+                eprintln!("Ignoring notes: {}", err);
+                Annotations::new()
+            },
+        };
+    }
+
+    // Names become labels (replacing the assembler's), comments are shown in listings
+    pub fn apply(&self, map: &mut SourceMap) {
+        for (&address, name) in self.names.iter() {
+            map.labels.insert(address, name.clone());
+        }
+        for (&address, comment) in self.comments.iter() {
+            map.comments.insert(address, comment.clone());
+        }
+    }
+}
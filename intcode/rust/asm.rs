@@ -0,0 +1,320 @@
+// Assembler for the syntax the disassembler prints, so its output can be edited and
+// assembled again:
+//
+//   start:  in [100]              ; position mode
+//           add [100], -1, [rb+2] ; immediate and relative mode
+//           jt [100], start       ; labels can be used wherever a number can (also label+3)
+//           hlt
+//   table:  data 1, 2, 3
+//
+// Labels end with ':', comments start with ';'. Macros, constants and includes (also of
+// the standard library in stdlib/) are described in preprocess.rs.
+// assemble_object() makes an object for the linker instead, which can import labels.
+use std::collections::{HashMap, HashSet};
+
+use super::disasm;
+use super::link::Object;
+use super::preprocess::{self, SourceLine};
+use super::sourcemap::SourceMap;
+use super::{Cell, OpCode, ParamMode, ParamType};
+
+#[derive(Debug)]
+pub struct Assembled {
+    pub program: Vec<Cell>,
+    pub source_map: SourceMap,
+}
+
+enum Item {
+    Instruction(OpCode, Vec<(ParamMode, String)>),
+    Data(Vec<String>),
+}
+
+struct Line<'a> {
+    source: &'a SourceLine,
+    address: usize,
+    item: Item,
+}
+
+struct Symbols {
+    labels: HashMap<String, usize>,
+    constants: HashMap<String, String>, // expressions, evaluated when used
+    imports: HashSet<String>,
+}
+
+// Value of an expression if the program starts at 0. When it is moved by the linker,
+// base * relocation is added, and the address of the import if there is one.
+struct Value {
+    value: Cell,
+    relocation: Cell,
+    import: Option<String>,
+}
+
+const OP_CODES: [OpCode; 10] = [
+    OpCode::Add, OpCode::Mul, OpCode::Input, OpCode::Output, OpCode::JumpIfTrue,
+    OpCode::JumpIfFalse, OpCode::LessThan, OpCode::Equals, OpCode::AdjustRelativeBase, OpCode::Terminate,
+];
+
+fn op_code_number(op_code: OpCode) -> Cell {
+    return (1..100).find(|&n| OpCode::try_read(n) == Some(op_code)).unwrap() as Cell;
+}
+
+fn is_identifier(s: &str) -> bool {
+    return s.len() > 0 && s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        && !s.chars().next().unwrap().is_digit(10);
+}
+
+fn parse_operand(text: &str) -> Result<(ParamMode, String), String> {
+    let text = text.trim();
+    if text.starts_with('[') && text.ends_with(']') {
+        let inner = text[1..text.len() - 1].trim();
+        if inner == "rb" {
+            return Ok((ParamMode::Relative, "0".to_string()));
+        }
+        if inner.starts_with("rb") && (inner[2..].trim().starts_with('+') || inner[2..].trim().starts_with('-')) {
+            return Ok((ParamMode::Relative, inner[2..].trim().to_string()));
+        }
+        return Ok((ParamMode::Position, inner.to_string()));
+    }
+    if text.len() == 0 {
+        return Err("Missing operand".to_string());
+    }
+    return Ok((ParamMode::Immediate, text.to_string()));
+}
+
+// Numbers, labels and constants joined with + and -
+fn evaluate(expression: &str, symbols: &Symbols) -> Result<Value, String> {
+    return evaluate_nested(expression, symbols, 0);
+}
+
+fn evaluate_nested(expression: &str, symbols: &Symbols, depth: usize) -> Result<Value, String> {
+    let mut value = Value { value: 0, relocation: 0, import: None };
+    let mut sign: Cell = 1;
+    let mut term = String::new();
+    let add_term = |term: &mut String, sign: Cell, value: &mut Value| -> Result<(), String> {
+        let t = term.trim();
+        if t.len() == 0 {
+            term.clear();
+            return Ok(());
+        }
+        let v = if is_identifier(t) {
+            if let Some(&address) = symbols.labels.get(t) {
+                Value { value: address as Cell, relocation: 1, import: None }
+            } else if let Some(constant) = symbols.constants.get(t) {
+                if depth > 32 {
+                    return Err(format!("Constant '{}' is defined in terms of itself", t));
+                }
+                evaluate_nested(constant, symbols, depth + 1)?
+            } else if symbols.imports.contains(t) {
+                Value { value: 0, relocation: 0, import: Some(t.to_string()) }
+            } else {
+                return Err(format!("Unknown label '{}'", t));
+            }
+        } else {
+            Value { value: t.parse::<Cell>().map_err(|_| format!("Invalid number '{}'", t))?, relocation: 0, import: None }
+        };
+        if let Some(import) = v.import {
+            if sign != 1 || value.import.is_some() {
+                return Err(format!("Imported label '{}' can only be added once", import));
+            }
+            value.import = Some(import);
+        }
+        value.value += sign * v.value;
+        value.relocation += sign * v.relocation;
+        term.clear();
+        return Ok(());
+    };
+    for c in expression.chars() {
+        if c == '+' || c == '-' {
+            if term.trim().len() > 0 {
+                add_term(&mut term, sign, &mut value)?;
+                sign = 1;
+            }
+            if c == '-' {
+                sign = -sign;
+            }
+        } else {
+            term.push(c);
+        }
+    }
+    if term.trim().len() == 0 {
+        return Err(format!("Invalid expression '{}'", expression));
+    }
+    add_term(&mut term, sign, &mut value)?;
+    return Ok(value);
+}
+
+fn parse_item(text: &str) -> Result<Item, String> {
+    let (mnemonic, rest) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim()),
+        None => (text, ""),
+    };
+    let operands: Vec<&str> = if rest.len() > 0 { rest.split(',').map(|s| s.trim()).collect() } else { Vec::new() };
+    if mnemonic == "data" {
+        if operands.is_empty() {
+            return Err("data needs at least one value".to_string());
+        }
+        return Ok(Item::Data(operands.iter().map(|s| s.to_string()).collect()));
+    }
+    let op_code = match OP_CODES.iter().find(|&&op| disasm::mnemonic(op) == mnemonic) {
+        Some(&op_code) => op_code,
+        None => return Err(format!("Unknown instruction '{}'", mnemonic)),
+    };
+    let param_count = op_code.get_param_count();
+    if operands.len() != param_count {
+        return Err(format!("{} takes {} operands, got {}", mnemonic, param_count, operands.len()));
+    }
+    let mut params: Vec<(ParamMode, String)> = Vec::new();
+    for (i, operand) in operands.iter().enumerate() {
+        let param = parse_operand(operand)?;
+        if param.0 == ParamMode::Immediate && op_code.get_param_type(i + 1) == ParamType::Write {
+            return Err(format!("Operand {} of {} is written to and can't be immediate", i + 1, mnemonic));
+        }
+        params.push(param);
+    }
+    return Ok(Item::Instruction(op_code, params));
+}
+
+// `filename` only ends up in the source map (and error messages)
+pub fn assemble(source: &str, filename: &str) -> Result<Assembled, String> {
+    let (object, source_map) = assemble_with_map(source, filename)?;
+    if let Some(&(_, ref name)) = object.imports.first() {
+        return Err(format!("{}: '{}' is imported, assemble to an object and link it", filename, name));
+    }
+    return Ok(Assembled { program: object.code, source_map: source_map });
+}
+
+pub fn assemble_object(source: &str, filename: &str) -> Result<Object, String> {
+    return assemble_with_map(source, filename).map(|(object, _)| object);
+}
+
+fn assemble_with_map(source: &str, filename: &str) -> Result<(Object, SourceMap), String> {
+    let mut source_map = SourceMap::new(filename);
+    let preprocessed = preprocess::preprocess(source, filename)?;
+    let (source_lines, constants) = (preprocessed.lines, preprocessed.constants);
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut lines: Vec<Line> = Vec::new();
+    let mut address = 0;
+
+    // First pass: find out where everything goes
+    for source_line in source_lines.iter() {
+        let error = |msg: String| format!("{}:{}: {}", source_line.file, source_line.number, msg);
+        let mut text = source_line.text.as_str();
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_identifier(label) {
+                return Err(error(format!("Invalid label '{}'", label)));
+            }
+            if constants.contains_key(label) || preprocessed.imports.contains(label)
+                    || labels.insert(label.to_string(), address).is_some() {
+                return Err(error(format!("Label '{}' defined twice", label)));
+            }
+            source_map.labels.insert(address, label.to_string());
+            text = text[colon + 1..].trim();
+        }
+        if text.len() == 0 {
+            continue;
+        }
+        let item = parse_item(text).map_err(&error)?;
+        source_map.lines.insert(address, source_line.origin); // the map only knows the main file
+        let length = match item {
+            Item::Instruction(_, ref params) => {
+                source_map.code.insert(address);
+                1 + params.len()
+            },
+            Item::Data(ref values) => values.len(),
+        };
+        lines.push(Line { source: source_line, address: address, item: item });
+        address += length;
+    }
+
+    // Second pass: encode with all labels known
+    let symbols = Symbols { labels: labels, constants: constants, imports: preprocessed.imports };
+    let mut object = Object::new(filename);
+    for name in preprocessed.exports {
+        match symbols.labels.get(&name) {
+            Some(&address) => object.exports.insert(name, address),
+            None => return Err(format!("{}: Exported label '{}' is not defined", filename, name)),
+        };
+    }
+    for line in lines.iter() {
+        assert!(object.code.len() == line.address);
+        let error = |msg: String| format!("{}:{}: {}", line.source.file, line.source.number, msg);
+        match line.item {
+            Item::Instruction(op_code, ref params) => {
+                let mut instruction = op_code_number(op_code);
+                let mut digit: Cell = 100;
+                for &(mode, _) in params.iter() {
+                    instruction += digit * match mode {
+                        ParamMode::Position => 0,
+                        ParamMode::Immediate => 1,
+                        ParamMode::Relative => 2,
+                    };
+                    digit *= 10;
+                }
+                object.add(instruction, 0, None);
+                for &(_, ref expression) in params.iter() {
+                    let value = evaluate(expression, &symbols).map_err(&error)?;
+                    object.add(value.value, value.relocation, value.import);
+                }
+            },
+            Item::Data(ref values) => {
+                for expression in values.iter() {
+                    let value = evaluate(expression, &symbols).map_err(&error)?;
+                    object.add(value.value, value.relocation, value.import);
+                }
+            },
+        }
+    }
+    return Ok((object, source_map));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    const SOURCE: &'static str = "
+%include <stack.asm>
+%include <math.asm>
+%include <memcpy.asm>
+%define LENGTH 3
+%define LAST LENGTH - 1
+
+%macro print value
+        push value
+        call print_number
+%endmacro
+
+        init_stack stack
+        memcpy copy, table, LENGTH
+        shl [copy+LAST], 4, [result]    ; 3 * 16
+        print [result]
+        out 10
+done:   print -205
+        print 0
+        hlt
+
+%include <print_number.asm>
+table:  data 1, 2, 3
+copy:   data 0, 0, 0
+result: data 0
+stack:  data 0
+";
+
+    #[test]
+    fn macros_and_stdlib() {
+        let assembled = assemble(SOURCE, "test.asm").unwrap();
+        let output: String = run(&assembled.program, &[]).unwrap().iter().map(|&v| v as u8 as char).collect();
+        assert_eq!(output, "48\n-2050");
+        // lines of macro expansions and includes are attributed to the line that caused them
+        let done = assembled.source_map.address_of("done").unwrap();
+        assert_eq!(assembled.source_map.lines.get(&done), Some(&18));
+    }
+
+    #[test]
+    fn preprocessor_errors() {
+        assert!(assemble("%macro x\nhlt\n", "test.asm").unwrap_err().contains("missing its %endmacro"));
+        assert_eq!(assemble("%include <nope.asm>", "test.asm").unwrap_err(), "test.asm:1: No 'nope.asm' in the standard library");
+        assert_eq!(assemble("%macro m a\nout a\n%endmacro\nm 1, 2", "test.asm").unwrap_err(), "test.asm:4: m takes 1 arguments, got 2");
+    }
+}
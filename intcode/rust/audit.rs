@@ -0,0 +1,68 @@
+// Finds reads of memory past the end of the program that was never written. The vm
+// returns 0 for those, which hand-written programs tend to rely on by accident.
+use std::collections::HashSet;
+
+use super::VmError;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct UninitializedRead {
+    pub ip: usize, // the instruction that read it
+    pub address: usize,
+}
+
+#[derive(Clone)]
+pub struct ReadAudit {
+    pub program_len: usize,
+    pub strict: bool, // fault on the first uninitialized read instead of collecting them
+    written: Vec<bool>, // index is address - program_len
+    pub reads: Vec<UninitializedRead>, // every instruction/address pair only once
+    seen: HashSet<(usize, usize)>,
+}
+
+impl ReadAudit {
+    pub fn new(program_len: usize, strict: bool) -> ReadAudit {
+        return ReadAudit {
+            program_len: program_len,
+            strict: strict,
+            written: Vec::new(),
+            reads: Vec::new(),
+            seen: HashSet::new(),
+        };
+    }
+
+    pub fn record_write(&mut self, address: usize) {
+        if address < self.program_len {
+            return;
+        }
+        let index = address - self.program_len;
+        if index >= self.written.len() {
+            self.written.resize(index + 1, false);
+        }
+        self.written[index] = true;
+    }
+
+    // Only fails in strict mode
+    pub fn record_read(&mut self, ip: usize, address: usize) -> Result<(), VmError> {
+        if address < self.program_len {
+            return Ok(());
+        }
+        let index = address - self.program_len;
+        if index < self.written.len() && self.written[index] {
+            return Ok(());
+        }
+        if self.strict {
+            return Err(VmError::UninitializedRead(address));
+        }
+        if self.seen.insert((ip, address)) {
+            self.reads.push(UninitializedRead { ip: ip, address: address });
+        }
+        return Ok(());
+    }
+
+    pub fn print_summary(&self) {
+        println!("{} reads of uninitialized memory", self.reads.len());
+        for read in &self.reads {
+            println!("  {:>6}: read {}", read.ip, read.address);
+        }
+    }
+}
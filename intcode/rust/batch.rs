@@ -0,0 +1,65 @@
+// Runs one program over many input lists (like day 19, which asks thousands of times
+// whether a point is in the beam). Every worker thread keeps the decode cache of its
+// previous runs around, so each instruction is only decoded once per worker.
+use std::collections::VecDeque;
+use std::thread;
+
+use super::{Cell, RunResult, Vm};
+
+type BatchVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+// Runs until the program halts or needs more input than it was given. A fault (or a
+// pause) only fails this input, the worker carries on with the next one.
+fn run_one(template: &mut BatchVm, input: &Vec<i64>) -> Result<Vec<i64>, RunResult> {
+    let mut vm = template.clone();
+    vm.input_source.extend(input.iter());
+    let result = vm.try_run();
+    template.decode_cache = vm.decode_cache; // entries are checked against memory, so always safe
+    return match result {
+        RunResult::Halted | RunResult::NeedsInput => Ok(vm.output_sink.into_iter().collect()),
+        result => Err(result),
+    };
+}
+
+pub fn run_batch(program: &[Cell], inputs: &[Vec<i64>]) -> Vec<Result<Vec<i64>, RunResult>> {
+    let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(inputs.len()).max(1);
+    let mut results: Vec<Result<Vec<i64>, RunResult>> = vec![Ok(Vec::new()); inputs.len()];
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|worker| {
+            scope.spawn(move || {
+                let mut template: BatchVm = Vm::new(program.to_vec());
+                return (worker..inputs.len()).step_by(workers)
+                    .map(|i| (i, run_one(&mut template, &inputs[i])))
+                    .collect::<Vec<(usize, Result<Vec<i64>, RunResult>)>>();
+            })
+        }).collect();
+        for handle in handles {
+            for (i, result) in handle.join().expect("Batch worker panicked") {
+                results[i] = result;
+            }
+        }
+    });
+    return results;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_program;
+
+    #[test]
+    fn faulting_input() {
+        // jumps to the address it reads, there is an invalid opcode at 9
+        let program = parse_program("3,4,1105,1,0,99,104,1,99,98");
+        let inputs: Vec<Vec<i64>> = vec![vec![5], vec![6], vec![9], vec![]];
+        let results = run_batch(&program, &inputs);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], Ok(vec![]));
+        assert_eq!(results[1], Ok(vec![1]));
+        match results[2] {
+            Err(RunResult::Faulted(_)) => (),
+            ref result => panic!("Expected a fault, got {:?}", result),
+        }
+        assert_eq!(results[3], Ok(vec![]));
+    }
+}
@@ -0,0 +1,114 @@
+// Compares the shared Vm against a straightforward interpreter that decodes every
+// instruction from scratch and allocates its parameter list on every step (like the
+// original day 5 and 7 VMs did). Uses the day 9 BOOST program in sensor boost mode.
+// If the program fits into i32, a Vm with i32 cells is timed as well.
+// Run `./bench [program] [input]`, built with optimizations (see timing.rs).
+#[path = "intcode.rs"]
+mod intcode;
+#[path = "../../timing/rust/timing.rs"]
+mod timing;
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::env;
+
+use intcode::{read_program, Cell, InfiniteTape, IntcodeCell, RunResult, Vm};
+use timing::time;
+
+const RUNS: usize = 10;
+
+#[derive(PartialEq)]
+enum ParamType {
+    Read,
+    Write
+}
+
+fn reference_run(program: &Vec<Cell>, input: i64) -> Vec<i64> {
+    let mut memory = program.clone();
+    let mut output: Vec<i64> = Vec::new();
+    let mut ip: usize = 0;
+    let mut relative_base: Cell = 0;
+    loop {
+        let instruction = memory[ip];
+        let params = match instruction % 100 {
+            1 | 2 | 7 | 8 => vec![ParamType::Read, ParamType::Read, ParamType::Write],
+            3 => vec![ParamType::Write],
+            4 | 9 => vec![ParamType::Read],
+            5 | 6 => vec![ParamType::Read, ParamType::Read],
+            99 => break,
+            _ => panic!("Unknown opcode: {}", instruction)
+        };
+        let mut addresses: Vec<usize> = Vec::new();
+        for i in 0..params.len() {
+            let digit_base = (10 as Cell).pow(i as u32 + 2);
+            let address = match (instruction / digit_base) % 10 {
+                0 => memory[ip + i + 1],
+                1 => (ip + i + 1) as Cell,
+                2 => memory[ip + i + 1] + relative_base,
+                _ => panic!("Unrecognized parameter mode digit")
+            };
+            assert!(address >= 0);
+            if address as usize >= memory.len() {
+                memory.resize(address as usize + 1, 0);
+            }
+            addresses.push(address as usize);
+        }
+        let p = |i: usize| memory[addresses[i]];
+        let mut next_ip = ip + 1 + params.len();
+        match instruction % 100 {
+            1 => memory[addresses[2]] = p(0) + p(1),
+            2 => memory[addresses[2]] = p(0) * p(1),
+            3 => memory[addresses[0]] = input as Cell,
+            4 => output.push(p(0) as i64),
+            5 => if p(0) != 0 { next_ip = p(1) as usize },
+            6 => if p(0) == 0 { next_ip = p(1) as usize },
+            7 => memory[addresses[2]] = if p(0) < p(1) { 1 } else { 0 },
+            8 => memory[addresses[2]] = if p(0) == p(1) { 1 } else { 0 },
+            9 => relative_base += p(0),
+            _ => unreachable!()
+        }
+        ip = next_ip;
+    }
+    return output;
+}
+
+// Faults are returned, e.g. when the program overflows i32 cells
+fn vm_run<T: IntcodeCell>(program: &Vec<T>, input: i64) -> Result<Vec<i64>, RunResult> {
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>, InfiniteTape<T>> = Vm::new(program.clone());
+    vm.input_source.push_back(input);
+    return match vm.try_run() {
+        RunResult::Halted => Ok(vm.output_sink.into_iter().collect()),
+        result => Err(result),
+    };
+}
+
+fn main() {
+    timing::warn_if_unoptimized();
+    let args: Vec<String> = env::args().collect();
+    let filename = if args.len() > 1 { args[1].as_str() } else { "../../9/input" };
+    let input = if args.len() > 2 { args[2].parse::<i64>().unwrap() } else { 2 };
+    let program = read_program(filename);
+
+    let (reference_time, reference_output) = time(RUNS, &|| reference_run(&program, input));
+    let (vm_time, vm_output) = time(RUNS, &|| vm_run(&program, input));
+    let vm_output = vm_output.unwrap_or_else(|result| panic!("Vm did not halt: {}", result));
+    assert!(reference_output == vm_output, "Outputs differ: {:?} vs {:?}", reference_output, vm_output);
+
+    println!("Output: {:?}", vm_output);
+    println!("Reference interpreter: {:?} (best of {})", reference_time, RUNS);
+    println!("Vm:                    {:?} (best of {})", vm_time, RUNS);
+    println!("Speedup: {:.2}x", reference_time.as_secs_f64() / vm_time.as_secs_f64());
+
+    let small_program: Option<Vec<i32>> = program.iter().map(|&v| i32::try_from(v).ok()).collect();
+    if let Some(small_program) = small_program {
+        let (small_time, small_output) = time(RUNS, &|| vm_run(&small_program, input));
+        match small_output {
+            Ok(ref small_output) if *small_output == vm_output => {
+                println!("Vm (i32 cells):        {:?} (best of {})", small_time, RUNS);
+                println!("Speedup over i64: {:.2}x", vm_time.as_secs_f64() / small_time.as_secs_f64());
+            },
+            Ok(_) => println!("Vm (i32 cells): outputs differ"),
+            Err(result) => println!("Vm (i32 cells): the program overflows i32\n{}", result),
+        }
+    }
+}
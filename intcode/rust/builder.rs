@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use super::{InputSource, IntcodeCell, Limits, MemoryBackend, OutputSink, Patch, Vm};
+
+// Vm::builder(program).patch(0, 2).noun(12).verb(2).input(vec![5]).build()
+pub struct VmBuilder<I: InputSource, O: OutputSink, M: MemoryBackend> {
+    vm: Vm<I, O, M>,
+}
+
+impl<I: InputSource + Default, O: OutputSink + Default, M: MemoryBackend> VmBuilder<I, O, M> {
+    pub fn new(program: Vec<M::Cell>) -> VmBuilder<I, O, M> {
+        return VmBuilder {
+            vm: Vm::new(program),
+        };
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: MemoryBackend> VmBuilder<I, O, M> {
+    pub fn with_io(program: Vec<M::Cell>, input_source: I, output_sink: O) -> VmBuilder<I, O, M> {
+        return VmBuilder {
+            vm: Vm::with_io(program, input_source, output_sink),
+        };
+    }
+
+    // Overwrites a cell of the program before it runs
+    pub fn patch(mut self, address: usize, value: M::Cell) -> VmBuilder<I, O, M> {
+        self.vm.write_mem(address, value);
+        return self;
+    }
+
+    pub fn apply_patch(mut self, patch: &Patch) -> VmBuilder<I, O, M> {
+        for &(address, value) in &patch.writes {
+            self.vm.write_mem(address, M::Cell::from_i64(value));
+        }
+        return self;
+    }
+
+    // The day 2 convention for the two parameters of a program
+    pub fn noun(self, value: M::Cell) -> VmBuilder<I, O, M> {
+        return self.patch(1, value);
+    }
+
+    pub fn verb(self, value: M::Cell) -> VmBuilder<I, O, M> {
+        return self.patch(2, value);
+    }
+
+    pub fn read_only(mut self, range: Range<usize>) -> VmBuilder<I, O, M> {
+        self.vm.protect(range);
+        return self;
+    }
+
+    // Call after the patches, the range is the program as it is at that point
+    pub fn protect_program(mut self) -> VmBuilder<I, O, M> {
+        self.vm.protect_program();
+        return self;
+    }
+
+    pub fn limits(mut self, limits: Limits) -> VmBuilder<I, O, M> {
+        self.vm.set_limits(limits);
+        return self;
+    }
+
+    pub fn build(self) -> Vm<I, O, M> {
+        return self.vm;
+    }
+}
+
+impl<O: OutputSink, M: MemoryBackend> VmBuilder<VecDeque<i64>, O, M> {
+    pub fn input<V: IntoIterator<Item = i64>>(mut self, values: V) -> VmBuilder<VecDeque<i64>, O, M> {
+        self.vm.input_source.extend(values);
+        return self;
+    }
+}
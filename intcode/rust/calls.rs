@@ -0,0 +1,181 @@
+// Heuristic detection of the call/return idiom most Intcode programs use:
+//
+//     add <return address>, 0, [rb+n]   ; push the address after the jump
+//     jt 1, <function>                  ; unconditional jump
+//     ...
+//     arb -k                            ; function: pop its frame
+//     jf 0, [rb+0]                      ; and jump to the pushed address
+//
+// A call is an unconditional jump with a fixed target right after an instruction that
+// stores the address following the jump. A return is an unconditional jump to an address
+// read through the relative base. Functions get names like sub_123 and consist of the
+// instructions reachable from their entry without following calls.
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::disasm::{self, Instruction};
+use super::{Cell, OpCode, ParamMode};
+
+pub struct Function {
+    pub entry: usize,
+    pub name: String,
+    pub callers: Vec<usize>, // addresses of the call jumps
+    pub instructions: BTreeSet<usize>,
+}
+
+pub struct CallGraph {
+    pub functions: BTreeMap<usize, Function>,
+    pub calls: BTreeMap<usize, usize>, // jump address -> function entry
+    pub returns: BTreeSet<usize>,
+}
+
+impl CallGraph {
+    // The function an instruction belongs to, if any. Shared code belongs to the first one.
+    pub fn function_at(&self, address: usize) -> Option<&Function> {
+        return self.functions.values().find(|f| f.instructions.contains(&address));
+    }
+}
+
+// Some(true) for a jump that is always taken, Some(false) for one that never is
+fn always_taken(instruction: &Instruction) -> Option<bool> {
+    if instruction.modes[0] != ParamMode::Immediate {
+        return None;
+    }
+    return match instruction.op_code {
+        OpCode::JumpIfTrue => Some(instruction.params[0] != 0),
+        OpCode::JumpIfFalse => Some(instruction.params[0] == 0),
+        _ => None,
+    };
+}
+
+fn is_jump(instruction: &Instruction) -> bool {
+    return instruction.op_code == OpCode::JumpIfTrue || instruction.op_code == OpCode::JumpIfFalse;
+}
+
+fn is_return(instruction: &Instruction) -> bool {
+    return is_jump(instruction) && always_taken(instruction) == Some(true)
+        && instruction.modes[1] == ParamMode::Relative;
+}
+
+// Stores the address after `jump` somewhere, e.g. "add 42, 0, [rb+1]" or "mul 1, 42, [10]"
+fn pushes_return_address(instruction: &Instruction, jump: &Instruction) -> bool {
+    let return_address = jump.next_address() as Cell;
+    let imm = |i: usize| if instruction.modes[i] == ParamMode::Immediate { Some(instruction.params[i]) } else { None };
+    return match instruction.op_code {
+        OpCode::Add => match (imm(0), imm(1)) {
+            (Some(a), Some(b)) => a.checked_add(b) == Some(return_address),
+            _ => false,
+        },
+        OpCode::Mul => match (imm(0), imm(1)) {
+            (Some(a), Some(b)) => a.checked_mul(b) == Some(return_address),
+            _ => false,
+        },
+        _ => false,
+    };
+}
+
+// The instructions in the order they appear in `code`
+fn instructions(program: &[Cell], code: &[bool]) -> BTreeMap<usize, Instruction> {
+    let mut instructions = BTreeMap::new();
+    let mut address = 0;
+    while address < program.len() {
+        if address < code.len() && code[address] {
+            if let Some(instruction) = disasm::decode(program, address) {
+                address = instruction.next_address();
+                instructions.insert(instruction.address, instruction);
+                continue;
+            }
+        }
+        address += 1;
+    }
+    return instructions;
+}
+
+pub fn detect(program: &[Cell], code: &[bool]) -> CallGraph {
+    let instructions = instructions(program, code);
+    let mut calls: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut returns: BTreeSet<usize> = BTreeSet::new();
+    let mut previous: Option<&Instruction> = None;
+    for instruction in instructions.values() {
+        if is_return(instruction) {
+            returns.insert(instruction.address);
+        } else if is_jump(instruction) && always_taken(instruction) == Some(true)
+            && instruction.modes[1] == ParamMode::Immediate && instruction.params[1] >= 0 {
+            if let Some(previous) = previous {
+                if previous.next_address() == instruction.address && pushes_return_address(previous, instruction) {
+                    calls.insert(instruction.address, instruction.params[1] as usize);
+                }
+            }
+        }
+        previous = Some(instruction);
+    }
+
+    let mut functions: BTreeMap<usize, Function> = BTreeMap::new();
+    for (&call, &entry) in calls.iter() {
+        if !instructions.contains_key(&entry) {
+            continue;
+        }
+        functions.entry(entry).or_insert_with(|| Function {
+            entry: entry,
+            name: format!("sub_{}", entry),
+            callers: Vec::new(),
+            instructions: BTreeSet::new(),
+        }).callers.push(call);
+    }
+    for function in functions.values_mut() {
+        let mut worklist = vec![function.entry];
+        while let Some(address) = worklist.pop() {
+            let instruction = match instructions.get(&address) {
+                Some(instruction) => instruction,
+                None => continue,
+            };
+            if !function.instructions.insert(address) || returns.contains(&address) {
+                continue;
+            }
+            match instruction.op_code {
+                OpCode::Terminate => continue,
+                // Calls come back to the next instruction
+                _ if calls.contains_key(&address) => (),
+                OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                    let taken = always_taken(instruction);
+                    if taken != Some(false) && instruction.modes[1] == ParamMode::Immediate && instruction.params[1] >= 0 {
+                        worklist.push(instruction.params[1] as usize);
+                    }
+                    if taken == Some(true) {
+                        continue;
+                    }
+                },
+                _ => (),
+            }
+            worklist.push(instruction.next_address());
+        }
+    }
+    return CallGraph { functions: functions, calls: calls, returns: returns };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_program;
+
+    #[test]
+    fn call_and_return() {
+        // arb 20; add 9, 0, [rb+0]; jt 1, 10; halt; sub_10: out 5; jf 0, [rb+0]
+        let program = parse_program("109,20,21101,9,0,0,1105,1,10,99,104,5,2106,0,0");
+        let graph = detect(&program, &vec![true; program.len()]);
+        assert_eq!(graph.calls.iter().collect::<Vec<_>>(), vec![(&6, &10)]);
+        assert_eq!(graph.returns.iter().collect::<Vec<_>>(), vec![&12]);
+        let function = graph.function_at(12).unwrap();
+        assert_eq!((function.entry, function.name.as_str(), &function.callers), (10, "sub_10", &vec![6]));
+        assert_eq!(function.instructions.iter().collect::<Vec<_>>(), vec![&10, &12]);
+        assert!(graph.function_at(9).is_none());
+    }
+
+    #[test]
+    fn overflowing_push() {
+        let program = parse_program("1101,9223372036854775807,1,0,1105,1,9,99,99,99");
+        let graph = detect(&program, &vec![true; program.len()]);
+        assert!(graph.calls.is_empty() && graph.functions.is_empty());
+        let program = parse_program("1102,4611686018427387904,2,0,1105,1,9,99,99,99");
+        assert!(detect(&program, &vec![true; program.len()]).calls.is_empty());
+    }
+}
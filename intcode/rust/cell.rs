@@ -0,0 +1,79 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Mul, Rem};
+use std::str::FromStr;
+
+// Integer type of the memory cells. i64 is what the puzzles need, i32 is faster if a
+// program's values fit and i128 runs programs that overflow i64.
+// Inputs and outputs stay i64 and are converted when an instruction reads/writes them.
+pub trait IntcodeCell: Copy + PartialEq + PartialOrd + fmt::Debug + fmt::Display + FromStr
+        + Add<Output = Self> + Mul<Output = Self> + Rem<Output = Self> + Send + Sync + 'static {
+    const ZERO: Self;
+    const ONE: Self;
+
+    // Panics if the value doesn't fit
+    fn from_i64(value: i64) -> Self {
+        return Self::try_from_i64(value).expect("Value does not fit into memory cell");
+    }
+    fn try_from_i64(value: i64) -> Option<Self>;
+    fn to_i64(self) -> Option<i64>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_intcode_cell {
+    ($t:ty) => {
+        impl IntcodeCell for $t {
+            const ZERO: $t = 0;
+            const ONE: $t = 1;
+
+            fn try_from_i64(value: i64) -> Option<$t> {
+                return <$t>::try_from(value).ok();
+            }
+
+            fn to_i64(self) -> Option<i64> {
+                return i64::try_from(self).ok();
+            }
+
+            fn checked_add(self, other: $t) -> Option<$t> {
+                return <$t>::checked_add(self, other);
+            }
+
+            fn checked_mul(self, other: $t) -> Option<$t> {
+                return <$t>::checked_mul(self, other);
+            }
+        }
+    };
+}
+
+impl_intcode_cell!(i32);
+impl_intcode_cell!(i64);
+impl_intcode_cell!(i128);
+
+// Default cell type. Build with `--cfg 'feature="i128"'` to make it i128.
+#[cfg(not(feature = "i128"))]
+pub type Cell = i64;
+#[cfg(feature = "i128")]
+pub type Cell = i128;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use super::*;
+    use super::super::{parse_program_as, InfiniteTape, RunResult, Vm, VmError};
+
+    #[test]
+    fn input_too_large() {
+        assert_eq!((i32::try_from_i64(-7), i32::try_from_i64(1 << 40)), (Some(-7), None));
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>, InfiniteTape<i32>> = Vm::new(parse_program_as::<i32>("3,0,99"));
+        vm.input_source.push_back(1 << 40);
+        match vm.try_run() {
+            RunResult::Faulted(fault) => match fault.error {
+                VmError::Overflow(_) => (),
+                error => panic!("Expected an overflow, got {}", error),
+            },
+            result => panic!("Expected a fault, got {:?}", result),
+        }
+        assert_eq!(vm.input_source.len(), 1); // not lost
+    }
+}
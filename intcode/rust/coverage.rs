@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use super::disasm;
+use super::Cell;
+
+// Addresses past the program and above this are kept in a HashSet, so code at a huge
+// address (which the sparse and paged tapes allow) doesn't allocate everything below it
+const DENSE_COVERAGE: usize = 1 << 16;
+
+#[derive(Clone)]
+pub struct Coverage {
+    pub program_len: usize,
+    pub executed: Vec<bool>, // instruction start addresses that were executed
+    pub covered: Vec<bool>, // all cells belonging to an executed instruction
+    pub far_executed: HashSet<usize>,
+    pub far_covered: HashSet<usize>,
+}
+
+impl Coverage {
+    pub fn new(program_len: usize) -> Coverage {
+        return Coverage {
+            program_len: program_len,
+            executed: vec![false; program_len],
+            covered: vec![false; program_len],
+            far_executed: HashSet::new(),
+            far_covered: HashSet::new(),
+        };
+    }
+
+    pub fn record(&mut self, address: usize, length: usize) {
+        let end = address.saturating_add(length);
+        if end <= self.program_len.max(DENSE_COVERAGE) {
+            if end > self.executed.len() {
+                self.executed.resize(end, false);
+                self.covered.resize(end, false);
+            }
+            self.executed[address] = true;
+            for i in address..end {
+                self.covered[i] = true;
+            }
+        } else {
+            self.far_executed.insert(address);
+            self.far_covered.extend(address..end);
+        }
+    }
+
+    pub fn is_executed(&self, address: usize) -> bool {
+        if address < self.executed.len() {
+            return self.executed[address];
+        }
+        return self.far_executed.contains(&address);
+    }
+
+    pub fn is_covered(&self, address: usize) -> bool {
+        if address < self.covered.len() {
+            return self.covered[address];
+        }
+        return self.far_covered.contains(&address);
+    }
+
+    // Ranges [start, end) inside the original program that were never touched by an
+    // executed instruction
+    pub fn uncovered_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut start: Option<usize> = None;
+        for address in 0..self.program_len {
+            match (self.is_covered(address), start) {
+                (false, None) => start = Some(address),
+                (true, Some(s)) => {
+                    ranges.push((s, address));
+                    start = None;
+                },
+                _ => (),
+            }
+        }
+        if let Some(s) = start {
+            ranges.push((s, self.program_len));
+        }
+        return ranges;
+    }
+
+    pub fn print_summary(&self) {
+        let instructions = self.executed.iter().filter(|&&e| e).count() + self.far_executed.len();
+        let covered = (0..self.program_len).filter(|&a| self.is_covered(a)).count();
+        println!("Coverage: {} distinct instructions executed, {} of {} program cells covered ({:.1}%)",
+            instructions, covered, self.program_len, covered as f64 / self.program_len as f64 * 100.0);
+        for (start, end) in self.uncovered_ranges() {
+            println!("  not covered: {}..{} ({} cells)", start, end, end - start);
+        }
+    }
+
+    // Disassembly of the program, executed instructions are marked with a '*'. Cells
+    // that were not executed and don't decode into an instruction are shown as data.
+    pub fn print_annotated(&self, program: &[Cell]) {
+        let mut address = 0;
+        while address < program.len() {
+            let executed = self.is_executed(address);
+            let (text, length) = match disasm::disassemble_instruction(program, address) {
+                // Unexecuted cells are only decoded if they don't overlap executed code
+                Some((text, length)) if executed || (address..address + length).all(|a| !self.is_covered(a)) => {
+                    (text, length)
+                },
+                // Executed instructions might not decode, if the program modified itself
+                _ => (format!("data {}", program[address]), 1),
+            };
+            println!("{} {:>6}: {}", if executed { "*" } else { " " }, address, text);
+            address += length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use super::super::{parse_program, SparseTape, Vm, VmState};
+
+    #[test]
+    fn far_address() {
+        // writes a halt to 1000000000 and jumps there
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>, SparseTape> = Vm::new(parse_program("1101,0,99,1000000000,1105,1,1000000000"));
+        vm.enable_coverage();
+        assert_eq!(vm.run(), VmState::Terminated);
+        let coverage = vm.coverage.unwrap();
+        assert!(coverage.is_executed(0) && coverage.is_executed(4) && coverage.is_executed(1000000000));
+        assert!(coverage.is_covered(6) && coverage.is_covered(1000000000));
+        assert!(!coverage.is_executed(1) && !coverage.is_covered(999999999) && !coverage.is_covered(1000000001));
+        assert_eq!(coverage.covered.len(), 7);
+        assert_eq!(coverage.uncovered_ranges(), vec![]);
+    }
+}
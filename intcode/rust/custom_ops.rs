@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::{IntcodeCell, ParamType};
+
+// Handlers get the values of the read parameters and the current values of the write
+// parameters (which they may change) in order. Returning Some(address) jumps there.
+// Shared between clones of a vm, so a forked vm calls the same handler (with the same state).
+pub type CustomHandler<T> = Arc<Mutex<dyn FnMut(&[T], &mut [T]) -> Option<usize> + Send>>;
+
+#[derive(Clone)]
+pub struct CustomOp<T: IntcodeCell> {
+    pub params: Vec<ParamType>,
+    pub handler: CustomHandler<T>,
+}
+
+// Gets the call number and the memory block the program passed (which it may change).
+// Shared between clones like the custom op handlers.
+pub type HostCallHandler<T> = Arc<Mutex<dyn FnMut(T, &mut [T]) + Send>>;
+
+// Longer blocks fault, so a bad length can't make the vm allocate all the memory there is.
+// The memory limit applies to the block too.
+pub const MAX_HOST_CALL_BLOCK: usize = 1 << 20;
+
+#[derive(Clone)]
+pub struct HostCall<T: IntcodeCell> {
+    pub number: u8,
+    pub handler: HostCallHandler<T>,
+}
+
+// Extra op codes on top of the regular instruction set, keyed by op code number (the last two digits)
+#[derive(Clone)]
+pub struct CustomOps<T: IntcodeCell> {
+    pub ops: HashMap<u8, CustomOp<T>>,
+    pub host_call: Option<HostCall<T>>,
+}
+
+impl<T: IntcodeCell> CustomOps<T> {
+    pub fn new() -> CustomOps<T> {
+        return CustomOps {
+            ops: HashMap::new(),
+            host_call: None,
+        };
+    }
+
+    pub fn is_host_call(&self, number: u8) -> bool {
+        return match self.host_call {
+            Some(ref host_call) => host_call.number == number,
+            None => false,
+        };
+    }
+
+    pub fn get(&self, number: u8) -> Option<&CustomOp<T>> {
+        if self.ops.is_empty() {
+            return None;
+        }
+        return self.ops.get(&number);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use super::super::{parse_program, LimitExceeded, Limits, ParamType, Vm, VmError, VmState};
+
+    type TestVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+    // 50 call, start, length adds the call number to the first cell of the block
+    fn host_call_vm(program: &str) -> TestVm {
+        let mut vm: TestVm = Vm::new(parse_program(program));
+        vm.enable_host_calls(50, |call, block| {
+            if block.len() > 0 {
+                block[0] += call;
+            }
+        });
+        return vm;
+    }
+
+    #[test]
+    fn clone_keeps_custom_ops() {
+        // 42 a, b: b = 2 * a
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("42,7,8,4,8,99,0,21,0"));
+        vm.register_op(42, &[ParamType::Read, ParamType::Write], |reads, writes| {
+            writes[0] = 2 * reads[0];
+            return None;
+        });
+        vm.step(); // the instruction at 0 is decoded and cached now
+        let mut fork = vm.clone();
+        fork.instruction_pointer = 0;
+        fork.write_mem(7, 50);
+        assert_eq!(fork.run(), VmState::Terminated);
+        assert_eq!(fork.output_sink, vec![100]);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink, vec![42]);
+    }
+
+    #[test]
+    fn host_calls() {
+        let mut vm = host_call_vm("11150,7,0,3,1106,0,0");
+        vm.step();
+        assert_eq!(vm.read_mem(0), 11157);
+        let mut fork = vm.clone();
+        fork.write_mem(0, 11150);
+        fork.step();
+        fork.step(); // back at 0, which is cached
+        assert_eq!(fork.step(), VmState::Running);
+        assert_eq!(fork.read_mem(0), 11157);
+
+        let mut huge = host_call_vm("11150,1,0,9223372036854775807,99");
+        assert_eq!(huge.step(), VmState::Faulted);
+        assert_eq!(huge.fault.unwrap().error, VmError::InvalidHostCallBlock { start: 0, length: 9223372036854775807 });
+        let mut overflow = host_call_vm("11150,1,9223372036854775807,9223372036854775807,99");
+        assert_eq!(overflow.step(), VmState::Faulted);
+
+        let mut limited = host_call_vm("11150,1,0,11,99");
+        limited.set_limits(Limits { max_memory: Some(10), max_steps: None, max_outputs: None });
+        assert_eq!(limited.step(), VmState::LimitExceeded);
+        assert_eq!(limited.limit_exceeded, Some(LimitExceeded::Memory { address: 10, limit: 10 }));
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::{self, BufRead, Write};
+
+use super::annotations::Annotations;
+use super::disasm;
+use super::expr::{Expr, ExprContext};
+use super::sourcemap::SourceMap;
+use super::{Cell, MemoryBackend, Vm, VmState};
+
+pub type DebugVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+pub struct Breakpoint {
+    pub condition: Option<Expr>,
+    pub condition_text: String,
+}
+
+// Simple interactive debugger with (conditional) breakpoints and reverse stepping
+pub struct Debugger {
+    pub vm: DebugVm,
+    pub breakpoints: BTreeMap<usize, Breakpoint>,
+    pub output_breakpoints: BTreeSet<i64>,
+    pub break_on_any_output: bool,
+    pub source_map: Option<SourceMap>, // for labels and line numbers
+    pub notes: Annotations,
+    pub notes_file: Option<String>, // where name and comment save the notes
+    printed_outputs: usize,
+}
+
+// Variables available in breakpoint conditions
+impl ExprContext for Debugger {
+    fn mem(&self, address: usize) -> Cell {
+        return self.vm.read_mem(address);
+    }
+
+    fn var(&self, name: &str) -> Option<Cell> {
+        return match name {
+            "ip" => Some(self.vm.instruction_pointer as Cell),
+            "rb" => Some(self.vm.relative_base as Cell),
+            "steps" => Some(self.vm.steps as Cell),
+            "out" => Some(*self.vm.output_sink.back().unwrap_or(&0) as Cell), // last output
+            "outputs" => Some(self.vm.output_sink.len() as Cell),
+            _ => match self.source_map {
+                Some(ref map) => map.address_of(name).map(|address| address as Cell),
+                None => None,
+            },
+        };
+    }
+}
+
+impl Debugger {
+    pub fn new(program: Vec<Cell>, history_limit: usize) -> Debugger {
+        let mut vm = DebugVm::new(program);
+        vm.enable_history(history_limit);
+        return Debugger {
+            vm: vm,
+            breakpoints: BTreeMap::new(),
+            output_breakpoints: BTreeSet::new(),
+            break_on_any_output: false,
+            source_map: None,
+            notes: Annotations::new(),
+            notes_file: None,
+            printed_outputs: 0,
+        };
+    }
+
+    fn can_step(&self) -> bool {
+        return match self.vm.state {
+            VmState::Terminated => false,
+            VmState::LimitExceeded => false,
+            VmState::Faulted => false,
+            VmState::WaitForInput => self.vm.input_source.len() > 0,
+            _ => true,
+        };
+    }
+
+    pub fn step(&mut self, count: usize) -> usize {
+        let mut steps = 0;
+        while steps < count && self.can_step() {
+            self.vm.step();
+            steps += 1;
+        }
+        return steps;
+    }
+
+    pub fn step_back(&mut self, count: usize) -> usize {
+        let mut steps = 0;
+        while steps < count && self.vm.step_back() {
+            steps += 1;
+        }
+        return steps;
+    }
+
+    fn hit_breakpoint(&self, output_count: usize) -> bool {
+        if self.vm.output_sink.len() > output_count {
+            let value = *self.vm.output_sink.back().unwrap();
+            if self.break_on_any_output || self.output_breakpoints.contains(&value) {
+                println!("Breakpoint: output {}", value);
+                return true;
+            }
+        }
+        let address = self.vm.instruction_pointer;
+        return match self.breakpoints.get(&address) {
+            Some(&Breakpoint { condition: None, .. }) => true,
+            Some(&Breakpoint { condition: Some(ref condition), ref condition_text }) => match condition.eval(self) {
+                Ok(value) => value != 0,
+                Err(err) => {
+                    println!("Could not evaluate condition of breakpoint {} ({}): {}", address, condition_text, err);
+                    true
+                },
+            },
+            None => false,
+        };
+    }
+
+    // Runs until a breakpoint is hit, the vm waits for input or terminates
+    pub fn continue_(&mut self) -> usize {
+        let mut steps = 0;
+        while self.can_step() {
+            let output_count = self.vm.output_sink.len();
+            self.vm.step();
+            steps += 1;
+            if self.vm.state == VmState::WaitForInput {
+                break;
+            }
+            if self.hit_breakpoint(output_count) {
+                break;
+            }
+        }
+        return steps;
+    }
+
+    fn print_breakpoints(&self) {
+        for (address, breakpoint) in self.breakpoints.iter() {
+            if breakpoint.condition.is_some() {
+                println!("  {} if {}", address, breakpoint.condition_text);
+            } else {
+                println!("  {}", address);
+            }
+        }
+        if self.break_on_any_output {
+            println!("  any output");
+        }
+        for value in self.output_breakpoints.iter() {
+            println!("  output {}", value);
+        }
+    }
+
+    // A number or a label from the source map
+    fn parse_address(&self, text: &str) -> Option<usize> {
+        if let Ok(address) = text.parse::<usize>() {
+            return Some(address);
+        }
+        return self.source_map.as_ref().and_then(|map| map.address_of(text));
+    }
+
+    // break <addr> [if <condition>] | break out [value]
+    fn add_breakpoint(&mut self, args: &[&str]) {
+        if args.len() >= 1 && args[0] == "out" {
+            match args.get(1).map(|v| v.parse::<i64>()) {
+                None => self.break_on_any_output = true,
+                Some(Ok(value)) => { self.output_breakpoints.insert(value); },
+                Some(Err(_)) => println!("Invalid number"),
+            }
+            return;
+        }
+        let address = match args.get(0).and_then(|v| self.parse_address(v)) {
+            Some(address) => address,
+            None => {
+                println!("Usage: break <addr> [if <condition>] | break out [value]");
+                return;
+            },
+        };
+        let mut breakpoint = Breakpoint { condition: None, condition_text: String::new() };
+        if args.len() > 1 {
+            if args[1] != "if" {
+                println!("Expected 'if' after the address");
+                return;
+            }
+            breakpoint.condition_text = args[2..].join(" ");
+            match Expr::parse(&breakpoint.condition_text) {
+                Ok(condition) => breakpoint.condition = Some(condition),
+                Err(err) => {
+                    println!("Invalid condition: {}", err);
+                    return;
+                },
+            }
+        }
+        self.breakpoints.insert(address, breakpoint);
+    }
+
+    // delete <addr> | delete out [value]
+    fn delete_breakpoint(&mut self, args: &[&str]) {
+        if args.len() >= 1 && args[0] == "out" {
+            match args.get(1).map(|v| v.parse::<i64>()) {
+                None => self.break_on_any_output = false,
+                Some(Ok(value)) => { self.output_breakpoints.remove(&value); },
+                Some(Err(_)) => println!("Invalid number"),
+            }
+            return;
+        }
+        match args.get(0).and_then(|v| self.parse_address(v)) {
+            Some(address) => { self.breakpoints.remove(&address); },
+            _ => println!("Usage: delete <addr> | delete out [value]"),
+        }
+    }
+
+    // name <addr> [name] | comment <addr> [text]. Without a name/text it is removed.
+    // The notes are saved right away, so nothing is lost if the debugger is killed.
+    fn annotate(&mut self, args: &[&str], is_name: bool) {
+        let address = match args.get(0).and_then(|v| self.parse_address(v)) {
+            Some(address) => address,
+            None => {
+                println!("Usage: {} <addr> [{}]", if is_name { "name" } else { "comment" }, if is_name { "name" } else { "text" });
+                return;
+            },
+        };
+        let text = args[1..].join(" ");
+        if is_name && args.len() > 2 {
+            println!("Names can't contain spaces");
+            return;
+        }
+        let map = self.source_map.get_or_insert_with(|| SourceMap::new(""));
+        if is_name {
+            self.notes.set_name(address, &text);
+            match self.notes.names.get(&address) {
+                Some(name) => { map.labels.insert(address, name.clone()); },
+                None => { map.labels.remove(&address); },
+            }
+        } else {
+            self.notes.set_comment(address, &text);
+            match self.notes.comments.get(&address) {
+                Some(comment) => { map.comments.insert(address, comment.clone()); },
+                None => { map.comments.remove(&address); },
+            }
+        }
+        if let Some(ref filename) = self.notes_file {
+            if let Err(err) = self.notes.save(filename) {
+                println!("{}", err);
+            }
+        }
+    }
+
+    fn print_notes(&self) {
+        for (address, name) in self.notes.names.iter() {
+            println!("{:>6}: {}", address, name);
+        }
+        for (address, comment) in self.notes.comments.iter() {
+            println!("{:>6}: ; {}", address, comment);
+        }
+    }
+
+    fn print_location(&self) {
+        let address = self.vm.instruction_pointer;
+        let map = self.source_map.as_ref();
+        let location = match map {
+            Some(map) => format!("  {}", map.describe(address)),
+            None => String::new(),
+        };
+        match disasm::disassemble_instruction_with_map(&self.vm.memory.data, address, map) {
+            Some((text, _)) => println!("{:>6}: {}{}", address, text, location),
+            None => println!("{:>6}: ???{}", address, location),
+        }
+        println!("        state: {:?}", self.vm.state);
+        if let Some(ref fault) = self.vm.fault {
+            println!("{}", fault);
+        }
+    }
+
+    fn print_new_outputs(&mut self) {
+        let outputs = &self.vm.output_sink;
+        // outputs might have been taken back by stepping backwards
+        if self.printed_outputs > outputs.len() {
+            self.printed_outputs = outputs.len();
+        }
+        for value in outputs.iter().skip(self.printed_outputs) {
+            println!("output: {}", value);
+        }
+        self.printed_outputs = outputs.len();
+    }
+
+    fn print_help() {
+        println!("Commands:");
+        println!("  s, step [n]            execute n instructions (default 1)");
+        println!("  b, back [n]            undo n instructions (default 1)");
+        println!("  c, continue            run until breakpoint, input or halt");
+        println!("  break <addr> [if <condition>]");
+        println!("                         set breakpoint, e.g. break 732 if mem[386] > 10000");
+        println!("                         conditions can use mem[..], ip, rb, steps, out, outputs and labels");
+        println!("  break out [value]      break on any output / a specific output value");
+        println!("  delete <addr> | delete out [value]");
+        println!("                         remove breakpoint");
+        println!("  breakpoints            list breakpoints");
+        println!("  i, input <values...>   queue input values");
+        println!("  m, mem <addr> [count]  print memory");
+        println!("  set <addr> <value>     write memory");
+        println!("  r, regs                print registers");
+        println!("  d, dis [count]         disassemble from instruction pointer");
+        println!("  name <addr> [name]     name an address (without a name: remove it)");
+        println!("  comment <addr> [text]  comment an address (without text: remove it)");
+        println!("  notes                  list names and comments (saved in <program>.notes)");
+        println!("  q, quit");
+    }
+
+    // Returns false if the debugger should quit
+    pub fn execute_command(&mut self, line: &str) -> bool {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() == 0 {
+            return true;
+        }
+        // These take more than numbers
+        match parts[0] {
+            "break" => {
+                self.add_breakpoint(&parts[1..]);
+                self.print_breakpoints();
+                return true;
+            },
+            "delete" => {
+                self.delete_breakpoint(&parts[1..]);
+                self.print_breakpoints();
+                return true;
+            },
+            "breakpoints" => {
+                self.print_breakpoints();
+                return true;
+            },
+            "name" | "comment" => {
+                self.annotate(&parts[1..], parts[0] == "name");
+                return true;
+            },
+            "notes" => {
+                self.print_notes();
+                return true;
+            },
+            _ => (),
+        }
+        let numbers: Result<Vec<i64>, _> = parts[1..].iter().map(|p| p.parse::<i64>()).collect();
+        let numbers = match numbers {
+            Ok(numbers) => numbers,
+            Err(_) => {
+                println!("Invalid number");
+                return true;
+            }
+        };
+        let arg = |i: usize, default: i64| -> i64 { *numbers.get(i).unwrap_or(&default) };
+
+        match parts[0] {
+            "s" | "step" => {
+                let steps = self.step(arg(0, 1) as usize);
+                println!("{} steps", steps);
+                self.print_location();
+            }
+            "b" | "back" => {
+                let steps = self.step_back(arg(0, 1) as usize);
+                println!("{} steps back", steps);
+                self.print_location();
+            }
+            "c" | "continue" => {
+                let steps = self.continue_();
+                println!("{} steps", steps);
+                self.print_location();
+            }
+            "i" | "input" => {
+                self.vm.input_source.extend(numbers.iter());
+            }
+            "m" | "mem" if numbers.len() >= 1 => {
+                if numbers[0] < 0 || arg(1, 1) < 0 {
+                    println!("Address and count must not be negative");
+                    return true;
+                }
+                let start = numbers[0] as usize;
+                let count = arg(1, 1) as usize;
+                let end = match start.checked_add(count) {
+                    Some(end) => end,
+                    None => {
+                        println!("Address range is too large");
+                        return true;
+                    }
+                };
+                for address in start..end {
+                    println!("{:>6}: {}", address, self.vm.read_mem(address));
+                }
+            }
+            "set" if numbers.len() == 2 => {
+                if numbers[0] < 0 {
+                    println!("Invalid address: {}", numbers[0]);
+                    return true;
+                }
+                self.vm.write_mem(numbers[0] as usize, numbers[1] as Cell);
+            }
+            "r" | "regs" => {
+                println!("ip: {}", self.vm.instruction_pointer);
+                println!("rb: {}", self.vm.relative_base);
+                println!("state: {:?}", self.vm.state);
+                println!("queued input: {:?}", self.vm.input_source);
+            }
+            "d" | "dis" => {
+                let mut address = self.vm.instruction_pointer;
+                let map = self.source_map.as_ref();
+                for _ in 0..arg(0, 5) {
+                    match disasm::disassemble_instruction_with_map(&self.vm.memory.data, address, map) {
+                        Some((text, len)) => {
+                            if let Some(label) = map.and_then(|m| m.labels.get(&address)) {
+                                println!("{}:", label);
+                            }
+                            match map.and_then(|m| m.comments.get(&address)) {
+                                Some(comment) => println!("{:>6}: {:<32} ; {}", address, text, comment),
+                                None => println!("{:>6}: {}", address, text),
+                            }
+                            address += len;
+                        }
+                        None => break,
+                    }
+                }
+            }
+            "h" | "help" => Debugger::print_help(),
+            "q" | "quit" => return false,
+            _ => println!("Unknown command or wrong arguments (try 'help')"),
+        }
+        self.print_new_outputs();
+        return true;
+    }
+
+    pub fn repl(&mut self) {
+        self.print_location();
+        let stdin = io::stdin();
+        loop {
+            print!("> ");
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            if !self.execute_command(&line) {
+                break;
+            }
+        }
+    }
+}
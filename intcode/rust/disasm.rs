@@ -0,0 +1,243 @@
+use super::calls::CallGraph;
+use super::sourcemap::SourceMap;
+use super::{Cell, OpCode, ParamMode};
+
+pub fn mnemonic(op_code: OpCode) -> &'static str {
+    return match op_code {
+        OpCode::Add => "add",
+        OpCode::Mul => "mul",
+        OpCode::Input => "in",
+        OpCode::Output => "out",
+        OpCode::JumpIfTrue => "jt",
+        OpCode::JumpIfFalse => "jf",
+        OpCode::LessThan => "lt",
+        OpCode::Equals => "eq",
+        OpCode::AdjustRelativeBase => "arb",
+        OpCode::Terminate => "hlt",
+        OpCode::Custom(_) => "ext",
+        OpCode::HostCall => "sys",
+    };
+}
+
+fn format_param(mode: ParamMode, value: Cell) -> String {
+    return match mode {
+        ParamMode::Position => format!("[{}]", value),
+        ParamMode::Immediate => format!("{}", value),
+        ParamMode::Relative => format!("[rb{:+}]", value),
+    };
+}
+
+fn cell(memory: &[Cell], address: usize) -> Cell {
+    return if address < memory.len() { memory[address] } else { 0 };
+}
+
+pub struct Instruction {
+    pub address: usize,
+    pub op_code: OpCode,
+    pub modes: Vec<ParamMode>,
+    pub params: Vec<Cell>,
+}
+
+impl Instruction {
+    pub fn len(&self) -> usize {
+        return 1 + self.params.len();
+    }
+
+    pub fn next_address(&self) -> usize {
+        return self.address + self.len();
+    }
+}
+
+// Returns None if the cell at address is not a valid instruction
+pub fn decode(memory: &[Cell], address: usize) -> Option<Instruction> {
+    let instruction = cell(memory, address);
+    let digits = (instruction % 100000) as i64; // op code and parameter modes
+    let op_code = match OpCode::try_read(digits) {
+        Some(op_code) => op_code,
+        None => return None,
+    };
+    let mut modes: Vec<ParamMode> = Vec::new();
+    let mut params: Vec<Cell> = Vec::new();
+    for param_num in 1..op_code.get_param_count() + 1 {
+        match ParamMode::try_read(digits, param_num) {
+            Some(mode) => modes.push(mode),
+            None => return None,
+        }
+        params.push(cell(memory, address + param_num));
+    }
+    return Some(Instruction {
+        address: address,
+        op_code: op_code,
+        modes: modes,
+        params: params,
+    });
+}
+
+// Returns the instruction text and its length in cells or None if the cell at address
+// is not a valid instruction.
+pub fn disassemble_instruction(memory: &[Cell], address: usize) -> Option<(String, usize)> {
+    return disassemble_instruction_with_map(memory, address, None);
+}
+
+// With a source map, addresses in position mode operands and jump targets are shown as labels
+pub fn disassemble_instruction_with_map(memory: &[Cell], address: usize, map: Option<&SourceMap>) -> Option<(String, usize)> {
+    let instruction = match decode(memory, address) {
+        Some(instruction) => instruction,
+        None => return None,
+    };
+    let is_jump = instruction.op_code == OpCode::JumpIfTrue || instruction.op_code == OpCode::JumpIfFalse;
+    let label = |value: Cell| -> Option<&String> {
+        return match map {
+            Some(map) if value >= 0 => map.labels.get(&(value as usize)),
+            _ => None,
+        };
+    };
+    let params: Vec<String> = instruction.modes.iter().zip(instruction.params.iter()).enumerate()
+        .map(|(i, (&mode, &value))| match (mode, label(value)) {
+            (ParamMode::Position, Some(name)) => format!("[{}]", name),
+            (ParamMode::Immediate, Some(name)) if is_jump && i == 1 => name.clone(),
+            _ => format_param(mode, value),
+        }).collect();
+    let text = if params.is_empty() {
+        mnemonic(instruction.op_code).to_string()
+    } else {
+        format!("{} {}", mnemonic(instruction.op_code), params.join(", "))
+    };
+    return Some((text, instruction.len()));
+}
+
+// Linear sweep over the whole memory. Cells that don't decode (or would reach past the
+// end) are emitted as data, so the output can be assembled into the same program again.
+pub fn disassemble(memory: &[Cell]) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut address = 0;
+    while address < memory.len() {
+        match disassemble_instruction(memory, address) {
+            Some((text, length)) if address + length <= memory.len() => {
+                lines.push((address, text));
+                address += length;
+            },
+            _ => {
+                lines.push((address, format!("data {}", memory[address])));
+                address += 1;
+            }
+        }
+    }
+    return lines;
+}
+
+// Like disassemble(), but the source map tells code from data, labels are shown on their
+// own lines (with the address of the next cell) and source line numbers and the comments
+// from the notes as comments
+pub fn disassemble_with_map(memory: &[Cell], map: &SourceMap) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut address = 0;
+    while address < memory.len() {
+        if let Some(label) = map.labels.get(&address) {
+            lines.push((address, format!("{}:", label)));
+        }
+        let instruction = if map.code.contains(&address) || map.code.is_empty() {
+            disassemble_instruction_with_map(memory, address, Some(map))
+                .filter(|&(_, length)| address + length <= memory.len())
+        } else {
+            None
+        };
+        let (mut text, length) = instruction.unwrap_or_else(|| (format!("data {}", memory[address]), 1));
+        let mut notes: Vec<String> = Vec::new();
+        if let Some(line) = map.lines.get(&address) {
+            notes.push(format!("{}:{}", map.file, line));
+        }
+        if let Some(comment) = map.comments.get(&address) {
+            notes.push(comment.clone());
+        }
+        if notes.len() > 0 {
+            text = format!("{:<32} ; {}", text, notes.join(" - "));
+        }
+        lines.push((address, text));
+        address += length;
+    }
+    return lines;
+}
+
+// Printable ASCII and newlines, which is how the puzzle programs store their text
+fn is_text(value: Cell) -> bool {
+    return value == 10 || (value >= 32 && value <= 126);
+}
+
+const MIN_TEXT_LEN: usize = 4;
+const TEXT_PER_LINE: usize = 32;
+const DATA_PER_LINE: usize = 8;
+
+// Splits a run of data cells into lines: strings of printable values and chunks of numbers
+fn data_rows(memory: &[Cell], start: usize, end: usize, rows: &mut Vec<(usize, Vec<Cell>, String)>) {
+    let mut address = start;
+    while address < end {
+        let text_len = (address..end).take_while(|&a| is_text(memory[a])).count();
+        if text_len >= MIN_TEXT_LEN {
+            let len = text_len.min(TEXT_PER_LINE);
+            let text: String = memory[address..address + len].iter().map(|&v| v as u8 as char).collect();
+            rows.push((address, memory[address..address + len].to_vec(), format!("data {:?}", text)));
+            address += len;
+            continue;
+        }
+        // Numbers up to the next string
+        let mut len = 0;
+        while address + len < end && len < DATA_PER_LINE {
+            let a = address + len;
+            if (a..end).take_while(|&a| is_text(memory[a])).count() >= MIN_TEXT_LEN {
+                break;
+            }
+            len += 1;
+        }
+        let values = memory[address..address + len].to_vec();
+        let text: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        rows.push((address, values, format!("data {}", text.join(", "))));
+        address += len;
+    }
+}
+
+// Listing with the raw cells next to the disassembly. `code` says which cells belong to
+// instructions (from coverage or a static analysis), everything else is shown as data,
+// with runs of printable values as string literals. With a call graph, functions get a
+// header and calls and returns are marked.
+pub fn annotated_listing(memory: &[Cell], code: &[bool], calls: Option<&CallGraph>) -> Vec<String> {
+    let is_code = |a: usize| a < code.len() && code[a];
+    let mut rows: Vec<(usize, Vec<Cell>, String)> = Vec::new();
+    let mut address = 0;
+    while address < memory.len() {
+        if is_code(address) {
+            if let Some((text, length)) = disassemble_instruction(memory, address) {
+                if address + length <= memory.len() && (address..address + length).all(|a| is_code(a)) {
+                    let text = match calls {
+                        Some(calls) if calls.calls.contains_key(&address) =>
+                            format!("{:<24} ; call {}", text, calls.functions[&calls.calls[&address]].name),
+                        Some(calls) if calls.returns.contains(&address) => format!("{:<24} ; return", text),
+                        _ => text,
+                    };
+                    rows.push((address, memory[address..address + length].to_vec(), text));
+                    address += length;
+                    continue;
+                }
+            }
+        }
+        let end = (address + 1..memory.len()).find(|&a| is_code(a)).unwrap_or(memory.len());
+        data_rows(memory, address, end, &mut rows);
+        address = end;
+    }
+
+    let cells: Vec<String> = rows.iter()
+        .map(|&(_, ref values, _)| values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" "))
+        .collect();
+    // Long strings would push everything to the right, so they don't count for the width
+    let width = cells.iter().map(|c| c.len()).filter(|&l| l <= 40).max().unwrap_or(0);
+    let mut lines: Vec<String> = Vec::new();
+    for (&(address, _, ref text), cells) in rows.iter().zip(cells.iter()) {
+        if let Some(function) = calls.and_then(|calls| calls.functions.get(&address)) {
+            let callers: Vec<String> = function.callers.iter().map(|a| a.to_string()).collect();
+            lines.push(String::new());
+            lines.push(format!("{}:  ; called from {}", function.name, callers.join(", ")));
+        }
+        lines.push(format!("{:>6}:  {:<width$}  {}", address, cells, text, width = width));
+    }
+    return lines;
+}
@@ -0,0 +1,271 @@
+// Tiny expression language for debugger conditions, e.g. `mem[386] > 10000 && rb != 0`.
+// Integers, mem[...], variables (provided by the context), + - * / %, comparisons,
+// && || ! and parentheses with the usual precedence. Comparisons and logic give 1 or 0,
+// anything nonzero is true.
+use std::convert::TryFrom;
+
+use super::Cell;
+
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Number(Cell),
+    Var(String),
+    Mem(Box<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+}
+
+pub trait ExprContext {
+    fn mem(&self, address: usize) -> Cell;
+    fn var(&self, name: &str) -> Option<Cell>;
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            let two: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            if ["<=", ">=", "==", "!=", "&&", "||"].contains(&two.as_str()) {
+                tokens.push(two);
+                i += 2;
+            } else if "+-*/%<>!()[]".contains(c) {
+                tokens.push(c.to_string());
+                i += 1;
+            } else {
+                return Err(format!("Unexpected character '{}'", c));
+            }
+        }
+    }
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+// Binary operators by precedence, lowest first
+const LEVELS: [&[&str]; 5] = [
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<", "<=", ">", ">="],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        return self.tokens.get(self.pos).map(|t| t.as_str());
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        match self.tokens.get(self.pos) {
+            Some(token) => {
+                self.pos += 1;
+                return Ok(token.clone());
+            },
+            None => return Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        let next = self.next()?;
+        if next != token {
+            return Err(format!("Expected '{}', got '{}'", token, next));
+        }
+        return Ok(());
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = self.peek().map(|t| t.to_string()) {
+            if !LEVELS[level].contains(&op.as_str()) {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        return Ok(lhs);
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let token = self.next()?;
+        return match token.as_str() {
+            "-" => Ok(Expr::Neg(Box::new(self.unary()?))),
+            "!" => Ok(Expr::Not(Box::new(self.unary()?))),
+            "(" => {
+                let inner = self.binary(0)?;
+                self.expect(")")?;
+                Ok(inner)
+            },
+            "mem" => {
+                self.expect("[")?;
+                let address = self.binary(0)?;
+                self.expect("]")?;
+                Ok(Expr::Mem(Box::new(address)))
+            },
+            _ => {
+                if token.chars().next().unwrap().is_digit(10) {
+                    match token.parse::<Cell>() {
+                        Ok(value) => Ok(Expr::Number(value)),
+                        Err(_) => Err(format!("Invalid number '{}'", token)),
+                    }
+                } else if token.chars().next().unwrap().is_alphabetic() || token.starts_with('_') {
+                    Ok(Expr::Var(token))
+                } else {
+                    Err(format!("Unexpected '{}'", token))
+                }
+            },
+        };
+    }
+}
+
+fn truth(value: bool) -> Cell {
+    return if value { 1 } else { 0 };
+}
+
+fn checked(result: Option<Cell>, a: Cell, op: &str, b: Cell) -> Result<Cell, String> {
+    return result.ok_or_else(|| format!("Overflow in {} {} {}", a, op, b));
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Expr, String> {
+        let mut parser = Parser { tokens: tokenize(text)?, pos: 0 };
+        let expr = parser.binary(0)?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected '{}' after expression", token));
+        }
+        return Ok(expr);
+    }
+
+    pub fn eval(&self, context: &dyn ExprContext) -> Result<Cell, String> {
+        return match *self {
+            Expr::Number(value) => Ok(value),
+            Expr::Var(ref name) => context.var(name).ok_or(format!("Unknown variable '{}'", name)),
+            Expr::Mem(ref address) => {
+                let address = address.eval(context)?;
+                match usize::try_from(address) {
+                    Ok(address) => Ok(context.mem(address)),
+                    Err(_) => Err(format!("Invalid address: {}", address)),
+                }
+            },
+            Expr::Not(ref inner) => Ok(truth(inner.eval(context)? == 0)),
+            Expr::Neg(ref inner) => {
+                let value = inner.eval(context)?;
+                value.checked_neg().ok_or_else(|| format!("Overflow in -{}", value))
+            },
+            Expr::Binary(ref op, ref lhs, ref rhs) => {
+                let a = lhs.eval(context)?;
+                // Short-circuit, so `mem[x] != 0 && mem[mem[x]] > 5` works
+                if op == "&&" && a == 0 {
+                    return Ok(0);
+                }
+                if op == "||" && a != 0 {
+                    return Ok(1);
+                }
+                let b = rhs.eval(context)?;
+                match op.as_str() {
+                    "+" => checked(a.checked_add(b), a, op, b),
+                    "-" => checked(a.checked_sub(b), a, op, b),
+                    "*" => checked(a.checked_mul(b), a, op, b),
+                    "/" | "%" if b == 0 => Err("Division by zero".to_string()),
+                    "/" => checked(a.checked_div(b), a, op, b),
+                    "%" => checked(a.checked_rem(b), a, op, b),
+                    "<" => Ok(truth(a < b)),
+                    "<=" => Ok(truth(a <= b)),
+                    ">" => Ok(truth(a > b)),
+                    ">=" => Ok(truth(a >= b)),
+                    "==" => Ok(truth(a == b)),
+                    "!=" => Ok(truth(a != b)),
+                    "&&" | "||" => Ok(truth(b != 0)),
+                    _ => unreachable!(),
+                }
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Context {
+        memory: Vec<Cell>,
+    }
+
+    impl ExprContext for Context {
+        fn mem(&self, address: usize) -> Cell {
+            return *self.memory.get(address).unwrap_or(&0);
+        }
+
+        fn var(&self, name: &str) -> Option<Cell> {
+            return match name {
+                "ip" => Some(2),
+                "rb" => Some(-5),
+                _ => None,
+            };
+        }
+    }
+
+    fn eval(text: &str) -> Result<Cell, String> {
+        let context = Context { memory: vec![10, 0, 20, 3] };
+        return Expr::parse(text)?.eval(&context);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("10 - 4 - 3"), Ok(3)); // left associative
+        assert_eq!(eval("1 + 1 == 2 && 3 < 4 || 0"), Ok(1));
+        assert_eq!(eval("!0 + -rb"), Ok(6));
+        assert_eq!(eval("mem[mem[3] - 1] % 7"), Ok(6));
+        assert!(Expr::parse("").is_err());
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("(1").is_err());
+        assert!(Expr::parse("mem 1").is_err());
+        assert!(Expr::parse("1 2").is_err());
+        assert!(Expr::parse("1 $ 2").is_err());
+        assert!(Expr::parse("99999999999999999999999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn eval_errors() {
+        assert_eq!(eval("mem[ip] > 10"), Ok(1));
+        assert!(eval("foo").is_err());
+        assert!(eval("mem[rb]").is_err());
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("1 % (ip - 2)").is_err());
+        // short-circuited, so neither side errors
+        assert_eq!(eval("mem[1] != 0 && 1 / mem[1]"), Ok(0));
+        assert_eq!(eval("1 || foo"), Ok(1));
+    }
+
+    #[test]
+    fn overflow() {
+        let max = Cell::max_value().to_string();
+        assert!(eval(&format!("{} + 1", max)).is_err());
+        assert!(eval(&format!("-{} - 2", max)).is_err());
+        assert!(eval(&format!("{} * 2", max)).is_err());
+        assert!(eval(&format!("(-{} - 1) / -1", max)).is_err());
+        assert!(eval(&format!("(-{} - 1) % -1", max)).is_err());
+        assert!(eval(&format!("-(-{} - 1)", max)).is_err());
+        assert_eq!(eval(&format!("{} - 1 + 1", max)), Ok(Cell::max_value()));
+    }
+}
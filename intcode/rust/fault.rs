@@ -0,0 +1,141 @@
+// What went wrong when a program does something invalid. step() stops on the faulting
+// instruction with VmState::Faulted and Vm::fault says why, run() and friends panic with
+// the whole description, try_run() returns it as RunResult::Faulted.
+use std::fmt;
+use std::fs;
+
+use super::LimitExceeded;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum VmError {
+    UnknownOpCode(String),
+    InvalidParamMode(String),
+    ImmediateWrite { param: usize, instruction: String },
+    InvalidAddress(i64),
+    NegativeJump(i64),
+    ValueOutOfRange(String),
+    Overflow(String), // the operation, e.g. "3 * 9223372036854775807"
+    ReadOnlyWrite(usize),
+    UninitializedRead(usize),
+    InvalidHostCallBlock { start: i64, length: i64 },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            VmError::UnknownOpCode(ref raw) => write!(f, "Unknown opcode: {}", raw),
+            VmError::InvalidParamMode(ref raw) => write!(f, "Unrecognized parameter mode digit in instruction: {}", raw),
+            VmError::ImmediateWrite { param, ref instruction } =>
+                write!(f, "Write parameter {} must not be in immediate mode for instruction: {}", param, instruction),
+            VmError::InvalidAddress(address) => write!(f, "Invalid address: {}", address),
+            VmError::NegativeJump(address) => write!(f, "Cannot jump to negative address: {}", address),
+            VmError::ValueOutOfRange(ref value) => write!(f, "Value out of range: {}", value),
+            VmError::Overflow(ref operation) => write!(f, "Arithmetic overflow: {}", operation),
+            VmError::ReadOnlyWrite(address) => write!(f, "Write to read-only address {}", address),
+            VmError::UninitializedRead(address) => write!(f, "Read of uninitialized address {}", address),
+            VmError::InvalidHostCallBlock { start, length } =>
+                write!(f, "Invalid host call block: {}, length {}", start, length),
+        };
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fault {
+    pub error: VmError,
+    pub ip: usize,
+    pub instruction: Vec<String>, // the cells of the faulting instruction
+    pub recent_trace: Vec<usize>, // addresses of the last executed instructions, oldest first
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.error)?;
+        write!(f, "  at address {}: {}", self.ip, self.instruction.join(","))?;
+        if self.recent_trace.len() > 0 {
+            let trace: Vec<String> = self.recent_trace.iter().map(|a| a.to_string()).collect();
+            write!(f, "\n  executed before: {}", trace.join(" -> "))?;
+        }
+        return Ok(());
+    }
+}
+
+impl Fault {
+    // The description plus the memory, so it can be loaded and disassembled again
+    pub fn save(&self, filename: &str, memory_image: &str) {
+        let text = format!("{}\n\n{}\n", self, memory_image);
+        fs::write(filename, text).expect("Could not write crash dump");
+    }
+}
+
+// The last few executed instruction addresses, kept all the time so faults can show them
+pub const TRACE_LEN: usize = 16;
+
+#[derive(Clone)]
+pub struct Trace {
+    addresses: [usize; TRACE_LEN],
+    count: usize,
+}
+
+impl Trace {
+    pub fn new() -> Trace {
+        return Trace { addresses: [0; TRACE_LEN], count: 0 };
+    }
+
+    pub fn record(&mut self, address: usize) {
+        self.addresses[self.count % TRACE_LEN] = address;
+        self.count += 1;
+    }
+
+    pub fn recent(&self) -> Vec<usize> {
+        let start = self.count.saturating_sub(TRACE_LEN);
+        return (start..self.count).map(|i| self.addresses[i % TRACE_LEN]).collect();
+    }
+}
+
+// How try_run() stopped
+#[derive(Clone, PartialEq, Debug)]
+pub enum RunResult {
+    Halted,
+    NeedsInput,
+    Paused, // by the interrupt callback
+    LimitExceeded(LimitExceeded),
+    Faulted(Fault),
+}
+
+impl fmt::Display for RunResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            RunResult::Halted => write!(f, "Halted"),
+            RunResult::NeedsInput => write!(f, "Program needs more input"),
+            RunResult::Paused => write!(f, "Paused by interrupt"),
+            RunResult::LimitExceeded(ref limit) => write!(f, "{}", limit),
+            RunResult::Faulted(ref fault) => write!(f, "{}", fault),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use super::*;
+    use super::super::{parse_program, Vm, VmState};
+
+    #[test]
+    fn read_only_write() {
+        // adds 1 + 1 into 9, then into 0
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("1101,1,1,9,1101,1,1,0,99,0"));
+        vm.protect(0..9);
+        match vm.try_run() {
+            RunResult::Faulted(fault) => {
+                assert_eq!(fault.error, VmError::ReadOnlyWrite(0));
+                assert_eq!(fault.ip, 4);
+            },
+            result => panic!("Expected a fault, got {:?}", result),
+        }
+        assert_eq!(vm.state, VmState::Faulted);
+        assert_eq!((vm.read_mem(0), vm.read_mem(9)), (1101, 2));
+        // only the program is stopped, writes from the outside still work
+        vm.write_mem(0, 5);
+        assert_eq!(vm.read_mem(0), 5);
+    }
+}
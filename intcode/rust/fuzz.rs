@@ -0,0 +1,151 @@
+// Fuzz targets for the program parser and the vm, in the shape cargo-fuzz expects
+// (fn(&[u8])), with a small driver so they run without a cargo setup. Invalid programs
+// make the vm fault, so any panic is a bug (overflows, index out of bounds, unwraps, ...).
+// Inputs that cause one are written to crash-<target>-<n>.bin and can be replayed with --replay.
+// Build *without* optimizations so overflow checks are on: `rustc fuzz.rs -o fuzz`, then
+// `./fuzz <target> [iterations] [seed]` or `./fuzz <target> --replay <file>`. The targets
+// are parse, vm and generated (random valid programs, compared to a reference run).
+#[path = "intcode.rs"]
+mod intcode;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::panic;
+use std::process;
+
+use intcode::{Cell, Limits, Vm};
+use intcode::generator::{Generator, GeneratorConfig, Rng};
+
+thread_local! {
+    static LAST_PANIC: RefCell<String> = RefCell::new(String::new());
+}
+
+// Text that is mostly digits, signs, commas and whitespace, so it sometimes parses.
+// Errors have to point at the token they complain about.
+pub fn fuzz_parse(data: &[u8]) {
+    let alphabet = b"0123456789-,, \n+x#";
+    let text: String = data.iter().map(|&b| alphabet[b as usize % alphabet.len()] as char).collect();
+    match intcode::try_parse_program_as::<Cell>(&text) {
+        Ok(program) => assert!(program.len() <= text.split(",").count()),
+        Err(err) => {
+            // Comments inside a token are blanked out, so only its first word is in the text as is
+            let first_word = err.token.split_whitespace().next().unwrap_or("");
+            assert!(text[err.offset..].starts_with(first_word), "Wrong offset in: {}", err);
+        },
+    }
+}
+
+// Bytes below 128 become instructions (so programs get past the first step), the
+// others mostly small values and a few huge ones. The first bytes are used as inputs.
+fn program_from_bytes(data: &[u8]) -> (Vec<Cell>, VecDeque<i64>) {
+    let op_codes: [Cell; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+    let program = data.iter().map(|&b| {
+        if b < 128 {
+            let modes = ((b / 10) % 3) as Cell * 100 + ((b / 30) % 3) as Cell * 1000 + ((b / 90) % 3) as Cell * 10000;
+            return op_codes[b as usize % 10] + modes;
+        }
+        if b >= 248 {
+            return (b as Cell - 251) << 60;
+        }
+        return b as Cell - 192;
+    }).collect();
+    let inputs = data.iter().take(4).map(|&b| b as i64 - 128).collect();
+    return (program, inputs);
+}
+
+pub fn fuzz_vm(data: &[u8]) {
+    let (program, inputs) = program_from_bytes(data);
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program);
+    vm.input_source = inputs;
+    vm.set_limits(Limits { max_memory: Some(1 << 16), max_steps: Some(10000), max_outputs: Some(1000) });
+    vm.try_run();
+}
+
+// Random valid programs, the outputs have to match the generator's reference run
+pub fn fuzz_generated(data: &[u8]) {
+    let seed = data.iter().fold(0u64, |seed, &b| seed.wrapping_mul(31).wrapping_add(b as u64));
+    let generated = Generator::new(GeneratorConfig::default(), seed).generate();
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(generated.program);
+    vm.input_source.extend(generated.inputs.iter());
+    vm.run();
+    assert_eq!(vm.output_sink.iter().cloned().collect::<Vec<i64>>(), generated.outputs);
+    assert_eq!(vm.steps, generated.steps);
+}
+
+fn run_target(target: &str, data: &[u8]) -> Result<(), String> {
+    let data = data.to_vec();
+    let target = target.to_string();
+    let result = panic::catch_unwind(move || {
+        match target.as_str() {
+            "parse" => fuzz_parse(&data),
+            "vm" => fuzz_vm(&data),
+            "generated" => fuzz_generated(&data),
+            _ => unreachable!(),
+        }
+    });
+    return match result {
+        Ok(()) => Ok(()),
+        Err(_) => Err(LAST_PANIC.with(|p| p.borrow().clone())),
+    };
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: fuzz <parse|vm|generated> [iterations] [seed]");
+    eprintln!("       fuzz <parse|vm|generated> --replay <file>");
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || !["parse", "vm", "generated"].contains(&args[1].as_str()) {
+        usage();
+    }
+    let target = args[1].as_str();
+
+    panic::set_hook(Box::new(|info| {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "<unknown panic>".to_string()
+        };
+        let location = info.location().map(|l| format!(" ({}:{})", l.file(), l.line())).unwrap_or(String::new());
+        LAST_PANIC.with(|p| *p.borrow_mut() = message + &location);
+    }));
+
+    if args.len() > 2 && args[2] == "--replay" {
+        if args.len() < 4 {
+            usage();
+        }
+        let data = fs::read(&args[3]).expect("Could not read input file");
+        match run_target(target, &data) {
+            Ok(()) => println!("No panic"),
+            Err(message) => println!("Panic: {}", message),
+        }
+        return;
+    }
+
+    let iterations = args.get(2).map(|s| s.parse::<usize>().expect("Invalid iteration count")).unwrap_or(100000);
+    let seed = args.get(3).map(|s| s.parse::<u64>().expect("Invalid seed")).unwrap_or(0x2545f4914f6cdd1d);
+    let mut rng = Rng::new(seed);
+    let mut crashes = 0;
+    for _ in 0..iterations {
+        let data = rng.bytes(256);
+        match run_target(target, &data) {
+            Ok(()) => (),
+            Err(message) => {
+                let filename = format!("crash-{}-{}.bin", target, crashes);
+                fs::write(&filename, &data).expect("Could not write crash file");
+                println!("{}: {}", filename, message);
+                crashes += 1;
+            },
+        }
+    }
+    println!("{} runs, {} crashes", iterations, crashes);
+    if crashes > 0 {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,264 @@
+// Random but valid Intcode programs for fuzzing, benchmarking and differential testing.
+//
+// Layout: [prologue][body][halt][loop counter][data]. Code never writes into itself:
+// position mode writes go into the data area and the relative base starts at the data
+// area and only ever grows. Every jump in the body is preceded by a guard that counts
+// down the loop counter and jumps to the halt when it reaches zero, so a program runs
+// at most (loop_limit + 1) * body length instructions.
+//
+// Every program is executed once with checked arithmetic before it is returned, programs
+// that overflow an i64 are thrown away. That run also makes up the inputs the program
+// consumes and counts the steps it takes.
+#[path = "../../rng/rust/rng.rs"]
+mod rng;
+
+use super::{Cell, OpCode, ParamMode};
+pub use self::rng::Rng;
+
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    pub length: usize, // instructions in the body, not counting the loop guards
+    pub weights: [u32; 9], // relative frequency of op codes 1 to 9
+    pub max_value: i64, // immediates and inputs are in -max_value..=max_value
+    pub data_size: usize,
+    pub loop_limit: i64, // how many jumps may be executed before the program halts
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> GeneratorConfig {
+        return GeneratorConfig {
+            length: 50,
+            weights: [10, 6, 3, 4, 3, 3, 3, 3, 2],
+            max_value: 100,
+            data_size: 32,
+            loop_limit: 100,
+        };
+    }
+}
+
+pub struct Generated {
+    pub program: Vec<Cell>,
+    pub inputs: Vec<i64>, // exactly the inputs the program reads
+    pub outputs: Vec<i64>,
+    pub steps: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Param {
+    mode: ParamMode,
+    value: i64,
+}
+
+struct Instruction {
+    op_code: OpCode,
+    params: Vec<Param>,
+    jump: Option<usize>, // index of the instruction a jump goes to
+}
+
+impl Instruction {
+    fn len(&self) -> usize {
+        return 1 + self.params.len();
+    }
+}
+
+const OP_CODES: [OpCode; 9] = [
+    OpCode::Add, OpCode::Mul, OpCode::Input, OpCode::Output, OpCode::JumpIfTrue,
+    OpCode::JumpIfFalse, OpCode::LessThan, OpCode::Equals, OpCode::AdjustRelativeBase,
+];
+
+fn op_code_number(op_code: OpCode) -> i64 {
+    return match op_code {
+        OpCode::Add => 1,
+        OpCode::Mul => 2,
+        OpCode::Input => 3,
+        OpCode::Output => 4,
+        OpCode::JumpIfTrue => 5,
+        OpCode::JumpIfFalse => 6,
+        OpCode::LessThan => 7,
+        OpCode::Equals => 8,
+        OpCode::AdjustRelativeBase => 9,
+        OpCode::Terminate => 99,
+        OpCode::Custom(_) | OpCode::HostCall => unreachable!(),
+    };
+}
+
+fn mode_number(mode: ParamMode) -> i64 {
+    return match mode {
+        ParamMode::Position => 0,
+        ParamMode::Immediate => 1,
+        ParamMode::Relative => 2,
+    };
+}
+
+pub struct Generator {
+    pub config: GeneratorConfig,
+    rng: Rng,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig, seed: u64) -> Generator {
+        return Generator { config: config, rng: Rng::new(seed) };
+    }
+
+    fn pick_op_code(&mut self) -> OpCode {
+        let total: u32 = self.config.weights.iter().sum();
+        let mut n = self.rng.below(total.max(1) as u64) as u32;
+        for (i, &weight) in self.config.weights.iter().enumerate() {
+            if n < weight {
+                return OP_CODES[i];
+            }
+            n -= weight;
+        }
+        return OpCode::Add;
+    }
+
+    // Position mode params are offsets into the data area until the program is assembled
+    fn read_param(&mut self) -> Param {
+        let max_value = self.config.max_value;
+        let data_size = self.config.data_size as i64;
+        return match self.rng.below(5) {
+            0 | 1 => Param { mode: ParamMode::Position, value: self.rng.range(0, data_size - 1) },
+            2 | 3 => Param { mode: ParamMode::Immediate, value: self.rng.range(-max_value, max_value) },
+            _ => Param { mode: ParamMode::Relative, value: self.rng.range(0, data_size - 1) },
+        };
+    }
+
+    // Relative writes can't reach the code, because the relative base never goes below the data area
+    fn write_param(&mut self) -> Param {
+        let data_size = self.config.data_size as i64;
+        let mode = if self.rng.below(3) == 0 { ParamMode::Relative } else { ParamMode::Position };
+        return Param { mode: mode, value: self.rng.range(0, data_size - 1) };
+    }
+
+    fn generate_body(&mut self) -> Vec<Instruction> {
+        let mut body = Vec::new();
+        for _ in 0..self.config.length {
+            let op_code = self.pick_op_code();
+            let params = match op_code {
+                OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals =>
+                    vec![self.read_param(), self.read_param(), self.write_param()],
+                OpCode::Input => vec![self.write_param()],
+                OpCode::Output => vec![self.read_param()],
+                OpCode::JumpIfTrue | OpCode::JumpIfFalse =>
+                    vec![self.read_param(), Param { mode: ParamMode::Immediate, value: 0 }],
+                OpCode::AdjustRelativeBase =>
+                    vec![Param { mode: ParamMode::Immediate, value: self.rng.range(0, 3) }],
+                _ => unreachable!(),
+            };
+            let jump = match op_code {
+                OpCode::JumpIfTrue | OpCode::JumpIfFalse => Some(self.rng.below(self.config.length as u64) as usize),
+                _ => None,
+            };
+            body.push(Instruction { op_code: op_code, params: params, jump: jump });
+        }
+        return body;
+    }
+
+    fn assemble(&self, body: &[Instruction]) -> Vec<i64> {
+        let prologue_len = 2 + 4;
+        let guard_len = 4 + 3;
+        let mut addresses = Vec::new();
+        let mut address = prologue_len;
+        for instruction in body {
+            addresses.push(address); // jumps go to the guard, so loops are counted
+            if instruction.jump.is_some() {
+                address += guard_len;
+            }
+            address += instruction.len();
+        }
+        let halt = address as i64;
+        let counter = halt + 1;
+        let data_start = counter + 1;
+
+        let mut program = vec![
+            109, data_start, // arb data_start
+            1101, self.config.loop_limit, 0, counter, // add loop_limit, 0, [counter]
+        ];
+        for instruction in body {
+            let mut raw = op_code_number(instruction.op_code);
+            for (i, param) in instruction.params.iter().enumerate() {
+                raw += mode_number(param.mode) * [100, 1000, 10000][i];
+            }
+            let params: Vec<i64> = instruction.params.iter().map(|p| match p.mode {
+                ParamMode::Position => data_start + p.value,
+                _ => p.value,
+            }).collect();
+            if let Some(target) = instruction.jump {
+                program.extend_from_slice(&[1001, counter, -1, counter]); // add [counter], -1, [counter]
+                program.extend_from_slice(&[1006, counter, halt]); // jf [counter], halt
+                program.push(raw);
+                program.push(params[0]);
+                program.push(addresses[target] as i64);
+            } else {
+                program.push(raw);
+                program.extend(params);
+            }
+        }
+        program.push(99);
+        program.push(0); // loop counter
+        return program;
+    }
+
+    pub fn generate(&mut self) -> Generated {
+        for _ in 0..1000 {
+            let body = self.generate_body();
+            let program = self.assemble(&body);
+            let max_value = self.config.max_value;
+            let rng = &mut self.rng;
+            if let Some(mut generated) = check(&program, || rng.range(-max_value, max_value)) {
+                generated.program = program.iter().map(|&v| v as Cell).collect();
+                return generated;
+            }
+        }
+        panic!("Could not generate a program that doesn't overflow, try a smaller max_value or loop_limit");
+    }
+}
+
+// Runs the program with checked arithmetic, None if it overflows
+fn check<F: FnMut() -> i64>(program: &[i64], mut next_input: F) -> Option<Generated> {
+    let mut memory = program.to_vec();
+    let mut ip = 0;
+    let mut rb: i64 = 0;
+    let mut generated = Generated { program: Vec::new(), inputs: Vec::new(), outputs: Vec::new(), steps: 0 };
+    loop {
+        let raw = memory[ip];
+        let op_code = OpCode::read(raw);
+        if op_code == OpCode::Terminate {
+            generated.steps += 1;
+            return Some(generated);
+        }
+        let count = op_code.get_param_count();
+        let mut addresses = [0usize; 3];
+        for i in 0..count {
+            let address = match ParamMode::read(raw, i + 1) {
+                ParamMode::Position => memory[ip + 1 + i],
+                ParamMode::Immediate => (ip + 1 + i) as i64,
+                ParamMode::Relative => memory[ip + 1 + i] + rb,
+            } as usize;
+            if address >= memory.len() {
+                memory.resize(address + 1, 0);
+            }
+            addresses[i] = address;
+        }
+        let p = |i: usize| memory[addresses[i]];
+        let mut next_ip = ip + 1 + count;
+        match op_code {
+            OpCode::Add => memory[addresses[2]] = p(0).checked_add(p(1))?,
+            OpCode::Mul => memory[addresses[2]] = p(0).checked_mul(p(1))?,
+            OpCode::Input => {
+                let value = next_input();
+                generated.inputs.push(value);
+                memory[addresses[0]] = value;
+            },
+            OpCode::Output => generated.outputs.push(p(0)),
+            OpCode::JumpIfTrue => if p(0) != 0 { next_ip = p(1) as usize },
+            OpCode::JumpIfFalse => if p(0) == 0 { next_ip = p(1) as usize },
+            OpCode::LessThan => memory[addresses[2]] = if p(0) < p(1) { 1 } else { 0 },
+            OpCode::Equals => memory[addresses[2]] = if p(0) == p(1) { 1 } else { 0 },
+            OpCode::AdjustRelativeBase => rb += p(0),
+            _ => unreachable!(),
+        }
+        ip = next_ip;
+        generated.steps += 1;
+    }
+}
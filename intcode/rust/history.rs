@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+use super::{IntcodeCell, VmState};
+
+// Everything needed to undo a single step
+#[derive(Clone)]
+pub struct StepRecord<T: IntcodeCell> {
+    pub instruction_pointer: usize,
+    pub relative_base: i64,
+    pub state: VmState, // before the step
+    pub writes: Vec<(usize, T)>, // address, previous value
+    pub input: Option<i64>,
+    pub output: bool,
+}
+
+// Undo log for stepping backwards. Only the last `limit` steps are kept.
+#[derive(Clone)]
+pub struct History<T: IntcodeCell> {
+    pub records: VecDeque<StepRecord<T>>,
+    pub limit: usize,
+    current: Option<StepRecord<T>>,
+}
+
+impl<T: IntcodeCell> History<T> {
+    pub fn new(limit: usize) -> History<T> {
+        return History {
+            records: VecDeque::new(),
+            limit: limit,
+            current: None,
+        };
+    }
+
+    pub fn begin(&mut self, instruction_pointer: usize, relative_base: i64, state: VmState) {
+        self.current = Some(StepRecord {
+            instruction_pointer: instruction_pointer,
+            relative_base: relative_base,
+            state: state,
+            writes: Vec::new(),
+            input: None,
+            output: false,
+        });
+    }
+
+    pub fn record_write(&mut self, address: usize, previous_value: T) {
+        if let Some(ref mut record) = self.current {
+            record.writes.push((address, previous_value));
+        }
+    }
+
+    pub fn record_input(&mut self, value: i64) {
+        if let Some(ref mut record) = self.current {
+            record.input = Some(value);
+        }
+    }
+
+    pub fn record_output(&mut self) {
+        if let Some(ref mut record) = self.current {
+            record.output = true;
+        }
+    }
+
+    pub fn commit(&mut self) {
+        if let Some(record) = self.current.take() {
+            if self.records.len() >= self.limit {
+                self.records.pop_front();
+            }
+            self.records.push_back(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{parse_program, Vm};
+
+    type TestVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+    fn snapshot(vm: &TestVm) -> (usize, i64, VmState, String, VecDeque<i64>, VecDeque<i64>) {
+        return (vm.instruction_pointer, vm.relative_base, vm.state, vm.memory_image(),
+            vm.input_source.clone(), vm.output_sink.clone());
+    }
+
+    // arb 3; in [12]; add [12], 5, [13]; out [13]; halt
+    const PROGRAM: &'static str = "109,3,3,12,1001,12,5,13,4,13,99,0,0,0";
+
+    #[test]
+    fn step_back() {
+        let mut vm: TestVm = Vm::new(parse_program(PROGRAM));
+        vm.enable_history(100);
+        vm.input_source.push_back(7);
+        let mut snapshots = Vec::new();
+        while vm.state != VmState::Terminated {
+            snapshots.push(snapshot(&vm));
+            vm.step();
+        }
+        assert_eq!(snapshots.len(), 5);
+        assert_eq!((vm.read_mem(12), vm.read_mem(13), vm.output_sink.clone()), (7, 12, VecDeque::from(vec![12])));
+        while let Some(expected) = snapshots.pop() {
+            assert!(vm.step_back());
+            assert_eq!(snapshot(&vm), expected);
+        }
+        assert!(!vm.step_back());
+        // and forward again, the input is read a second time
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink.clone(), VecDeque::from(vec![12]));
+    }
+
+    #[test]
+    fn limit() {
+        let mut vm: TestVm = Vm::new(parse_program(PROGRAM));
+        vm.enable_history(2);
+        vm.input_source.push_back(7);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.history.as_ref().unwrap().records.len(), 2);
+        assert!(vm.step_back() && vm.step_back());
+        assert!(!vm.step_back());
+        // only the output and the halt were undone
+        assert_eq!((vm.instruction_pointer, vm.relative_base, vm.state), (8, 3, VmState::Running));
+        assert_eq!((vm.read_mem(12), vm.read_mem(13), vm.output_sink.len()), (7, 12, 0));
+    }
+}
@@ -0,0 +1,1180 @@
+// Shared Intcode VM, included by the individual days via:
+//   #[path = "../../intcode/rust/intcode.rs"]
+//   mod intcode;
+// Not every day uses every part of it.
+#![allow(dead_code, unused_imports)]
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+pub mod annotations;
+pub mod asm;
+mod audit;
+mod batch;
+mod builder;
+pub mod calls;
+mod cell;
+mod history;
+mod io;
+mod limits;
+mod metrics;
+mod memory;
+mod mmio;
+pub mod network;
+mod observer;
+mod parse;
+mod patch;
+mod preprocess;
+pub mod pipeline;
+mod profiler;
+mod coverage;
+mod custom_ops;
+mod fault;
+pub mod debugger;
+pub mod disasm;
+pub mod expr;
+pub mod generator;
+pub mod link;
+pub mod selftest;
+pub mod sourcemap;
+pub mod symbolic;
+pub mod tcp;
+pub mod testfile;
+pub mod transpile;
+mod transcript;
+pub mod wasm;
+pub use self::audit::{ReadAudit, UninitializedRead};
+pub use self::batch::run_batch;
+pub use self::builder::VmBuilder;
+pub use self::cell::{Cell, IntcodeCell};
+pub use self::io::*;
+pub use self::limits::{LimitExceeded, Limits};
+pub use self::metrics::Metrics;
+use self::metrics::RunTimer;
+pub use self::observer::VmObserver;
+pub use self::parse::{try_parse_program_as, ParseError};
+pub use self::patch::Patch;
+pub use self::memory::{MemoryBackend, InfiniteTape, SparseTape, PagedTape};
+use self::mmio::MemoryMap;
+pub use self::profiler::Profiler;
+pub use self::coverage::Coverage;
+pub use self::fault::{Fault, RunResult, VmError};
+use self::fault::Trace;
+use self::custom_ops::{CustomOp, CustomOps, HostCall, MAX_HOST_CALL_BLOCK};
+pub use self::history::{History, StepRecord};
+pub use self::transcript::{Event, EventKind, ReplayInputSource, Transcript};
+
+// For addresses, relative base offsets and jump targets. Values that don't even fit into
+// i64 can't be valid there.
+fn cell_to_i64<T: IntcodeCell>(value: T) -> i64 {
+    return value.to_i64().unwrap_or_else(|| panic!("Value out of range: {}", value));
+}
+
+fn try_cell_to_i64<T: IntcodeCell>(value: T) -> Result<i64, VmError> {
+    return value.to_i64().ok_or_else(|| VmError::ValueOutOfRange(value.to_string()));
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ParamMode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl ParamMode {
+    pub fn read(instruction: i64, param_num: usize) -> ParamMode {
+        return ParamMode::try_read(instruction, param_num).expect("Unrecognized parameter mode digit");
+    }
+
+    pub fn try_read(instruction: i64, param_num: usize) -> Option<ParamMode> {
+        let digit_base = 10i64.pow(param_num as u32 + 1);
+        return match (instruction / digit_base) % 10 {
+            0 => Some(ParamMode::Position),
+            1 => Some(ParamMode::Immediate),
+            2 => Some(ParamMode::Relative),
+            _ => None
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum OpCode {
+    Add,
+    Mul,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Terminate,
+    Custom(u8), // registered with Vm::register_op
+    HostCall, // enabled with Vm::enable_host_calls
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ParamType {
+    Read,
+    Write
+}
+
+impl OpCode {
+    pub fn read(instruction: i64) -> OpCode {
+        match OpCode::try_read(instruction) {
+            Some(op_code) => return op_code,
+            None => panic!("Unknown opcode: {}", instruction)
+        }
+    }
+
+    pub fn try_read(instruction: i64) -> Option<OpCode> {
+        // I would make these guys static, but I cannot have a vec in a static, so I
+        // allocate and copy a bunch instead :)
+        return match instruction % 100 {
+            1 => Some(OpCode::Add),
+            2 => Some(OpCode::Mul),
+            3 => Some(OpCode::Input),
+            4 => Some(OpCode::Output),
+            5 => Some(OpCode::JumpIfTrue),
+            6 => Some(OpCode::JumpIfFalse),
+            7 => Some(OpCode::LessThan),
+            8 => Some(OpCode::Equals),
+            9 => Some(OpCode::AdjustRelativeBase),
+            99 => Some(OpCode::Terminate),
+            _ => None
+        }
+    }
+
+    fn get_param_count(&self) -> usize {
+        return match self {
+            OpCode::Add => 3,
+            OpCode::Mul => 3,
+            OpCode::Input => 1,
+            OpCode::Output => 1,
+            OpCode::JumpIfTrue => 2,
+            OpCode::JumpIfFalse => 2,
+            OpCode::LessThan => 3,
+            OpCode::Equals => 3,
+            OpCode::AdjustRelativeBase => 1,
+            OpCode::Terminate => 0,
+            OpCode::Custom(n) => panic!("Parameters of custom op code {} are only known to the vm", n),
+            OpCode::HostCall => 3,
+        }
+    }
+
+    fn get_param_type(&self, param_num: usize) -> ParamType {
+        return match self {
+            OpCode::Add => match param_num {
+                1 | 2 => ParamType::Read,
+                3 => ParamType::Write,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::Mul => match param_num {
+                1 | 2 => ParamType::Read,
+                3 => ParamType::Write,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::Input => match param_num {
+                1 => ParamType::Write,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::Output => match param_num {
+                1 => ParamType::Read,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::JumpIfTrue => match param_num {
+                1 | 2 => ParamType::Read,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::JumpIfFalse => match param_num {
+                1 | 2 => ParamType::Read,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::LessThan => match param_num {
+                1 | 2 => ParamType::Read,
+                3 => ParamType::Write,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::Equals => match param_num {
+                1 | 2 => ParamType::Read,
+                3 => ParamType::Write,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::AdjustRelativeBase => match param_num {
+                1 => ParamType::Read,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::Terminate => match param_num {
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+            OpCode::Custom(n) => panic!("Parameters of custom op code {} are only known to the vm", n),
+            OpCode::HostCall => match param_num {
+                1 | 2 | 3 => ParamType::Read,
+                _ => panic!("Invalid param number {} for op code {:?}!", param_num, self)
+            },
+        }
+    }
+}
+
+// Everything step() needs to know about an instruction, so it doesn't have to be
+// extracted from the digits (and validated) again every time it is executed
+#[derive(Clone, Copy)]
+struct DecodedInstruction<T: IntcodeCell> {
+    raw: T,
+    op_code: OpCode,
+    modes: [ParamMode; 3],
+    length: usize,
+}
+
+impl<T: IntcodeCell> DecodedInstruction<T> {
+    fn decode(raw: T, custom_ops: &CustomOps<T>) -> Result<DecodedInstruction<T>, VmError> {
+        // Op code and three parameter modes, the remaining digits don't matter
+        let digits = (raw % T::from_i64(100000)).to_i64().unwrap();
+        let (op_code, param_types) = match OpCode::try_read(digits) {
+            Some(op_code) => {
+                let count = op_code.get_param_count();
+                (op_code, (1..count + 1).map(|n| op_code.get_param_type(n)).collect())
+            },
+            None => {
+                let number = (digits % 100) as u8;
+                if custom_ops.is_host_call(number) {
+                    (OpCode::HostCall, vec![ParamType::Read; 3])
+                } else {
+                    match custom_ops.get(number) {
+                        Some(op) => (OpCode::Custom(number), op.params.clone()),
+                        None => return Err(VmError::UnknownOpCode(raw.to_string())),
+                    }
+                }
+            },
+        };
+        let param_count = param_types.len();
+        let mut modes = [ParamMode::Position; 3];
+        for i in 0..param_count {
+            modes[i] = ParamMode::try_read(digits, i + 1).ok_or_else(|| VmError::InvalidParamMode(raw.to_string()))?;
+            if modes[i] == ParamMode::Immediate && param_types[i] == ParamType::Write {
+                return Err(VmError::ImmediateWrite { param: i + 1, instruction: raw.to_string() });
+            }
+        }
+        return Ok(DecodedInstruction {
+            raw: raw,
+            op_code: op_code,
+            modes: modes,
+            length: 1 + param_count,
+        });
+    }
+}
+
+// Addresses below this are cached in a Vec, the ones above in a HashMap, so a jump to
+// some huge address (fine with SparseTape or PagedTape) doesn't allocate the cache up to there
+const DENSE_DECODE_CACHE: usize = 1 << 16;
+
+#[derive(Clone)]
+struct DecodeCache<T: IntcodeCell> {
+    dense: Vec<Option<DecodedInstruction<T>>>,
+    far: HashMap<usize, DecodedInstruction<T>>,
+}
+
+impl<T: IntcodeCell> DecodeCache<T> {
+    fn new() -> DecodeCache<T> {
+        return DecodeCache { dense: Vec::new(), far: HashMap::new() };
+    }
+
+    fn get(&self, address: usize) -> Option<DecodedInstruction<T>> {
+        if address < DENSE_DECODE_CACHE {
+            return self.dense.get(address).cloned().unwrap_or(None);
+        }
+        return self.far.get(&address).cloned();
+    }
+
+    fn insert(&mut self, address: usize, decoded: DecodedInstruction<T>) {
+        if address < DENSE_DECODE_CACHE {
+            if address >= self.dense.len() {
+                self.dense.resize(address + 1, None);
+            }
+            self.dense[address] = Some(decoded);
+        } else {
+            self.far.insert(address, decoded);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+        self.far.clear();
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum VmState {
+    NotStarted,
+    Running,
+    WaitForInput,
+    Paused,
+    Terminated,
+    LimitExceeded, // see Vm::limit_exceeded
+    Faulted, // see Vm::fault
+}
+
+impl fmt::Display for VmState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.write_str(match *self {
+            VmState::NotStarted => "not started",
+            VmState::Running => "running",
+            VmState::WaitForInput => "waiting for input",
+            VmState::Paused => "paused",
+            VmState::Terminated => "terminated",
+            VmState::LimitExceeded => "limit exceeded",
+            VmState::Faulted => "faulted",
+        });
+    }
+}
+
+// Called every `interval` executed instructions. If the callback returns true, the VM
+// pauses (run() returns VmState::Paused) and can be resumed by calling run() again.
+struct Interrupt {
+    interval: usize,
+    countdown: usize,
+    callback: Box<dyn FnMut() -> bool + Send>,
+}
+
+pub struct Vm<I: InputSource, O: OutputSink, M: MemoryBackend = InfiniteTape> {
+    memory: M, // use read_mem()/write_mem() from the outside
+    pub instruction_pointer: usize,
+    pub input_source: I,
+    pub output_sink: O,
+    pub state: VmState,
+    pub relative_base: i64, // may be negative, only the addresses computed from it may not
+    capture_output: bool,
+    captured_output: Option<i64>,
+    interrupt: Option<Interrupt>,
+    pub profiler: Option<Profiler>,
+    pub metrics: Option<Metrics>,
+    pub coverage: Option<Coverage>,
+    pub read_audit: Option<ReadAudit>,
+    memory_map: MemoryMap<M::Cell>,
+    read_only: Vec<Range<usize>>, // writes here fault with VmError::ReadOnlyWrite, see protect()
+    decode_cache: DecodeCache<M::Cell>,
+    pub history: Option<History<M::Cell>>,
+    pub transcript: Option<Transcript>,
+    custom_ops: CustomOps<M::Cell>,
+    limits: Limits,
+    pub limit_exceeded: Option<LimitExceeded>,
+    pub fault: Option<Fault>,
+    trace: Trace,
+    crash_dump: Option<String>, // file the fault and memory are written to
+    pub steps: u64, // executed instructions
+    output_count: u64,
+    observer: Option<Box<dyn VmObserver>>,
+}
+
+impl<I: InputSource + Default, O: OutputSink + Default, M: MemoryBackend> Vm<I, O, M> {
+    pub fn new(program: Vec<M::Cell>) -> Vm<I, O, M> {
+        return Vm::with_io(program, I::default(), O::default());
+    }
+
+    pub fn builder(program: Vec<M::Cell>) -> VmBuilder<I, O, M> {
+        return VmBuilder::new(program);
+    }
+}
+
+impl<I: InputSource, O: OutputSink, M: MemoryBackend> Vm<I, O, M> {
+    // For sources/sinks that need to be set up first, e.g. channel endpoints or files
+    pub fn with_io(program: Vec<M::Cell>, input_source: I, output_sink: O) -> Vm<I, O, M> {
+        return Vm {
+            memory: M::from_program(program),
+            instruction_pointer: 0,
+            input_source: input_source,
+            output_sink: output_sink,
+            state: VmState::NotStarted,
+            relative_base: 0,
+            capture_output: false,
+            captured_output: None,
+            interrupt: None,
+            profiler: None,
+            metrics: None,
+            coverage: None,
+            read_audit: None,
+            memory_map: MemoryMap::new(),
+            read_only: Vec::new(),
+            decode_cache: DecodeCache::new(),
+            history: None,
+            transcript: None,
+            custom_ops: CustomOps::new(),
+            limits: Limits::default(),
+            limit_exceeded: None,
+            fault: None,
+            trace: Trace::new(),
+            crash_dump: None,
+            steps: 0,
+            output_count: 0,
+            observer: None,
+        };
+    }
+
+    // Loads the program again and rewinds everything, without reallocating the memory.
+    // Input source, output sink and everything that was enabled/registered are kept, the
+    // decode cache too (it is checked against memory anyway).
+    pub fn reset(&mut self, program: &[M::Cell]) {
+        self.memory.load(program);
+        self.instruction_pointer = 0;
+        self.relative_base = 0;
+        self.state = VmState::NotStarted;
+        self.captured_output = None;
+        self.limit_exceeded = None;
+        self.fault = None;
+        self.trace = Trace::new();
+        self.steps = 0;
+        self.output_count = 0;
+        if let Some(ref mut history) = self.history {
+            *history = History::new(history.limit);
+        }
+        if self.transcript.is_some() {
+            self.transcript = Some(Transcript::new());
+        }
+        if let Some(ref mut audit) = self.read_audit {
+            *audit = ReadAudit::new(program.len(), audit.strict);
+        }
+    }
+
+    // Cache entries are only used if the cell still contains the same value, so writes
+    // into code (by the program or from outside) invalidate them
+    fn decode(&mut self, address: usize) -> Result<DecodedInstruction<M::Cell>, VmError> {
+        let raw = self.memory.get(address);
+        if address >= self.memory.len() {
+            // Nothing was ever written there, so this faults anyway
+            return DecodedInstruction::decode(raw, &self.custom_ops);
+        }
+        if let Some(decoded) = self.decode_cache.get(address) {
+            if decoded.raw == raw {
+                return Ok(decoded);
+            }
+        }
+        let decoded = DecodedInstruction::decode(raw, &self.custom_ops)?;
+        self.decode_cache.insert(address, decoded);
+        return Ok(decoded);
+    }
+
+    fn get_param_address(&self, instruction: &DecodedInstruction<M::Cell>, param_num: usize) -> Result<usize, VmError> {
+        let ip = self.instruction_pointer;
+        let param_pointer = ip + param_num;
+        match instruction.modes[param_num - 1] {
+            ParamMode::Position => {
+                let address = try_cell_to_i64(self.memory.get(param_pointer))?;
+                if address < 0 {
+                    return Err(VmError::InvalidAddress(address));
+                }
+                return Ok(address as usize);
+            }
+            ParamMode::Immediate => return Ok(param_pointer), // write params were checked in decode()
+            ParamMode::Relative => {
+                let offset = try_cell_to_i64(self.memory.get(param_pointer))?;
+                let address = offset.checked_add(self.relative_base)
+                    .ok_or_else(|| VmError::Overflow(format!("{} + {}", self.relative_base, offset)))?;
+                if address < 0 {
+                    return Err(VmError::InvalidAddress(address));
+                }
+                return Ok(address as usize);
+            }
+        }
+    }
+
+    // Memory access by the program, through the memory map and the checks
+    fn load(&mut self, address: usize) -> Result<M::Cell, VmError> {
+        if let Some(value) = self.memory_map.read(address) {
+            return Ok(value);
+        }
+        if let Some(ref mut audit) = self.read_audit {
+            audit.record_read(self.instruction_pointer, address)?;
+        }
+        return Ok(self.memory.get(address));
+    }
+
+    fn store(&mut self, address: usize, value: M::Cell) -> Result<(), VmError> {
+        if self.read_only.iter().any(|range| range.contains(&address)) {
+            return Err(VmError::ReadOnlyWrite(address));
+        }
+        if let Some(limit) = self.limits.max_memory {
+            if address >= limit {
+                self.limit_exceeded = Some(LimitExceeded::Memory { address: address, limit: limit });
+                return Ok(());
+            }
+        }
+        if let Some(ref mut audit) = self.read_audit {
+            audit.record_write(address);
+        }
+        if !self.memory_map.write(address, value) {
+            if let Some(ref mut history) = self.history {
+                history.record_write(address, self.memory.get(address));
+            }
+            self.memory.set(address, value);
+        }
+        return Ok(());
+    }
+
+    fn get_param(&mut self, instruction: &DecodedInstruction<M::Cell>, param_num: usize) -> Result<M::Cell, VmError> {
+        let address = self.get_param_address(instruction, param_num)?;
+        return self.load(address);
+    }
+
+    fn execute_operation(&mut self, instruction: &DecodedInstruction<M::Cell>) -> Result<Option<usize>, VmError> {
+        let validate_addr = |value: M::Cell| {
+            let value = try_cell_to_i64(value)?;
+            if value < 0 {
+                return Err(VmError::NegativeJump(value));
+            }
+            return Ok(value as usize);
+        };
+        match instruction.op_code {
+            OpCode::Add => {
+                let addr = self.get_param_address(instruction, 3)?;
+                let (a, b) = (self.get_param(instruction, 1)?, self.get_param(instruction, 2)?);
+                let value = a.checked_add(b).ok_or_else(|| VmError::Overflow(format!("{} + {}", a, b)))?;
+                self.store(addr, value)?;
+            },
+            OpCode::Mul => {
+                let addr = self.get_param_address(instruction, 3)?;
+                let (a, b) = (self.get_param(instruction, 1)?, self.get_param(instruction, 2)?);
+                let value = a.checked_mul(b).ok_or_else(|| VmError::Overflow(format!("{} * {}", a, b)))?;
+                self.store(addr, value)?;
+            },
+            OpCode::Input => {
+                let addr = self.get_param_address(instruction, 1)?;
+                let value = self.input_source.read();
+                let cell = match M::Cell::try_from_i64(value) {
+                    Some(cell) => cell,
+                    None => {
+                        self.input_source.unread(value); // so it is still there after the fault
+                        return Err(VmError::Overflow(format!("input {} does not fit into a memory cell", value)));
+                    }
+                };
+                if let Some(ref mut history) = self.history {
+                    history.record_input(value);
+                }
+                if let Some(ref mut transcript) = self.transcript {
+                    transcript.record(EventKind::Input, value);
+                }
+                if let Some(ref mut observer) = self.observer {
+                    observer.on_input(value);
+                }
+                if let Some(ref mut metrics) = self.metrics {
+                    metrics.inputs += 1;
+                }
+                self.store(addr, cell)?;
+            },
+            OpCode::Output => {
+                let value = try_cell_to_i64(self.get_param(instruction, 1)?)?;
+                if let Some(limit) = self.limits.max_outputs {
+                    if self.output_count >= limit {
+                        self.limit_exceeded = Some(LimitExceeded::Outputs { limit: limit });
+                        return Ok(Some(self.instruction_pointer));
+                    }
+                }
+                self.output_count += 1;
+                if let Some(ref mut metrics) = self.metrics {
+                    metrics.outputs += 1;
+                }
+                if let Some(ref mut observer) = self.observer {
+                    observer.on_output(value);
+                }
+                if let Some(ref mut transcript) = self.transcript {
+                    transcript.record(EventKind::Output, value);
+                }
+                if self.capture_output {
+                    self.captured_output = Some(value);
+                } else {
+                    self.output_sink.write(value);
+                    if let Some(ref mut history) = self.history {
+                        history.record_output();
+                    }
+                }
+            },
+            OpCode::JumpIfTrue => {
+                if self.get_param(instruction, 1)? != M::Cell::ZERO {
+                    return Ok(Some(validate_addr(self.get_param(instruction, 2)?)?));
+                }
+            },
+            OpCode::JumpIfFalse => {
+                if self.get_param(instruction, 1)? == M::Cell::ZERO {
+                    return Ok(Some(validate_addr(self.get_param(instruction, 2)?)?));
+                }
+            },
+            OpCode::LessThan => {
+                let addr = self.get_param_address(instruction, 3)?;
+                let value = if self.get_param(instruction, 1)? < self.get_param(instruction, 2)? { M::Cell::ONE } else { M::Cell::ZERO };
+                self.store(addr, value)?;
+            },
+            OpCode::Equals => {
+                let addr = self.get_param_address(instruction, 3)?;
+                let value = if self.get_param(instruction, 1)? == self.get_param(instruction, 2)? { M::Cell::ONE } else { M::Cell::ZERO };
+                self.store(addr, value)?;
+            },
+            OpCode::AdjustRelativeBase => {
+                let offset = try_cell_to_i64(self.get_param(instruction, 1)?)?;
+                self.relative_base = self.relative_base.checked_add(offset)
+                    .ok_or_else(|| VmError::Overflow(format!("{} + {}", self.relative_base, offset)))?;
+            }
+            OpCode::Terminate => return Ok(None),
+            OpCode::Custom(number) => {
+                let op = self.custom_ops.get(number).cloned()
+                    .ok_or_else(|| VmError::UnknownOpCode(self.memory.get(self.instruction_pointer).to_string()))?;
+                let mut reads: Vec<M::Cell> = Vec::new();
+                let mut writes: Vec<M::Cell> = Vec::new();
+                let mut write_addrs: Vec<usize> = Vec::new();
+                for (i, &param_type) in op.params.iter().enumerate() {
+                    match param_type {
+                        ParamType::Read => reads.push(self.get_param(instruction, i + 1)?),
+                        ParamType::Write => {
+                            let addr = self.get_param_address(instruction, i + 1)?;
+                            write_addrs.push(addr);
+                            writes.push(self.load(addr)?);
+                        },
+                    }
+                }
+                let jump = (&mut *op.handler.lock().unwrap())(&reads, &mut writes);
+                for (&addr, &value) in write_addrs.iter().zip(writes.iter()) {
+                    self.store(addr, value)?;
+                }
+                if jump.is_some() {
+                    return Ok(jump);
+                }
+            },
+            OpCode::HostCall => {
+                let call = self.get_param(instruction, 1)?;
+                let start = try_cell_to_i64(self.get_param(instruction, 2)?)?;
+                let length = try_cell_to_i64(self.get_param(instruction, 3)?)?;
+                if start < 0 || length < 0 {
+                    return Err(VmError::InvalidHostCallBlock { start: start, length: length });
+                }
+                let invalid_block = VmError::InvalidHostCallBlock { start: start, length: length };
+                let host_call = self.custom_ops.host_call.clone().ok_or_else(|| VmError::UnknownOpCode(self.memory.get(self.instruction_pointer).to_string()))?;
+                let (start, length) = (start as usize, length as usize);
+                let end = match start.checked_add(length) {
+                    Some(end) if length <= MAX_HOST_CALL_BLOCK => end,
+                    _ => return Err(invalid_block),
+                };
+                if let Some(limit) = self.limits.max_memory {
+                    if end > limit {
+                        self.limit_exceeded = Some(LimitExceeded::Memory { address: end - 1, limit: limit });
+                        return Ok(None);
+                    }
+                }
+                let before = (start..end).map(|addr| self.load(addr)).collect::<Result<Vec<M::Cell>, VmError>>()?;
+                let mut block = before.clone();
+                (&mut *host_call.handler.lock().unwrap())(call, &mut block);
+                for i in 0..block.len() {
+                    if block[i] != before[i] {
+                        self.store(start + i, block[i])?;
+                    }
+                }
+            },
+        }
+        return Ok(Some(self.instruction_pointer + instruction.length));
+    }
+
+    // Stops on the faulting instruction, the vm can't continue after that
+    fn fault(&mut self, error: VmError, length: usize) -> VmState {
+        let ip = self.instruction_pointer;
+        let fault = Fault {
+            error: error,
+            ip: ip,
+            instruction: (ip..ip + length).map(|address| self.memory.get(address).to_string()).collect(),
+            recent_trace: self.trace.recent(),
+        };
+        if let Some(ref filename) = self.crash_dump {
+            fault.save(filename, &self.memory_image());
+        }
+        self.fault = Some(fault);
+        self.state = VmState::Faulted;
+        return self.state;
+    }
+
+    pub fn step(&mut self) -> VmState {
+        // a halted vm stays halted, until reset() or step_back()
+        if self.state == VmState::Terminated || self.state == VmState::LimitExceeded || self.state == VmState::Faulted {
+            return self.state;
+        }
+        let previous_state = self.state;
+        self.state = VmState::Running;
+        let instruction = match self.decode(self.instruction_pointer) {
+            Ok(instruction) => instruction,
+            Err(error) => {
+                // Show the parameters too, if it's a regular instruction with a bad mode
+                let raw = try_cell_to_i64(self.memory.get(self.instruction_pointer)).unwrap_or(0);
+                let length = OpCode::try_read(raw).map_or(1, |op_code| 1 + op_code.get_param_count());
+                return self.fault(error, length);
+            },
+        };
+        let op_code = instruction.op_code;
+        if op_code == OpCode::Input && self.input_source.len() == 0 {
+            self.state = VmState::WaitForInput;
+            return self.state;
+        }
+        if let Some(limit) = self.limits.max_steps {
+            if self.steps >= limit {
+                self.limit_exceeded = Some(LimitExceeded::Steps { limit: limit });
+                self.state = VmState::LimitExceeded;
+                return self.state;
+            }
+        }
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.record(self.instruction_pointer, op_code);
+        }
+        if let Some(ref mut coverage) = self.coverage {
+            coverage.record(self.instruction_pointer, instruction.length);
+        }
+        if let Some(ref mut observer) = self.observer {
+            observer.on_step(self.instruction_pointer, op_code);
+        }
+        if let Some(ref mut history) = self.history {
+            history.begin(self.instruction_pointer, self.relative_base, previous_state);
+        }
+        let new_ip = match self.execute_operation(&instruction) {
+            Ok(new_ip) => new_ip,
+            Err(error) => return self.fault(error, instruction.length),
+        };
+        if self.limit_exceeded.is_some() {
+            // The instruction was not (completely) executed, so stay on it
+            self.state = VmState::LimitExceeded;
+            return self.state;
+        }
+        self.steps += 1;
+        self.trace.record(self.instruction_pointer);
+        if let Some(ref mut history) = self.history {
+            history.commit();
+        }
+        if let Some(ref mut transcript) = self.transcript {
+            transcript.steps += 1;
+        }
+        match new_ip {
+            Some(v) => self.instruction_pointer = v,
+            None => {
+                self.state = VmState::Terminated;
+                if let Some(ref profiler) = self.profiler {
+                    profiler.print_report();
+                }
+                if let Some(ref mut observer) = self.observer {
+                    observer.on_halt();
+                }
+            },
+        }
+        if self.state == VmState::Running {
+            if let Some(ref mut interrupt) = self.interrupt {
+                interrupt.countdown -= 1;
+                if interrupt.countdown == 0 {
+                    interrupt.countdown = interrupt.interval;
+                    if (interrupt.callback)() {
+                        self.state = VmState::Paused;
+                    }
+                }
+            }
+        }
+        return self.state;
+    }
+
+    pub fn set_interrupt<F: FnMut() -> bool + Send + 'static>(&mut self, interval: usize, callback: F) {
+        assert!(interval > 0, "Interrupt interval must be positive");
+        self.interrupt = Some(Interrupt {
+            interval: interval,
+            countdown: interval,
+            callback: Box::new(callback),
+        });
+    }
+
+    pub fn clear_interrupt(&mut self) {
+        self.interrupt = None;
+    }
+
+    // Reads from this address (by the program, not the host) return the value of the
+    // callback instead of the memory contents
+    pub fn map_read<F: FnMut() -> M::Cell + Send + 'static>(&mut self, address: usize, callback: F) {
+        self.memory_map.reads.insert(address, Box::new(callback));
+    }
+
+    // Writes to this address are passed to the callback instead of ending up in memory
+    pub fn map_write<F: FnMut(M::Cell) + Send + 'static>(&mut self, address: usize, callback: F) {
+        self.memory_map.writes.insert(address, Box::new(callback));
+    }
+
+    pub fn unmap(&mut self, address: usize) {
+        self.memory_map.reads.remove(&address);
+        self.memory_map.writes.remove(&address);
+    }
+
+    // Adds an extra instruction for op code `number` (which must not be a regular one).
+    // At most 3 parameters are supported.
+    pub fn register_op<F>(&mut self, number: u8, params: &[ParamType], handler: F)
+            where F: FnMut(&[M::Cell], &mut [M::Cell]) -> Option<usize> + Send + 'static {
+        assert!(number < 100, "Op codes only have two digits");
+        assert!(OpCode::try_read(number as i64).is_none(), "Op code {} is already taken", number);
+        assert!(params.len() <= 3, "Custom op codes can have at most 3 parameters");
+        assert!(!self.custom_ops.is_host_call(number), "Op code {} is already the host call", number);
+        self.custom_ops.ops.insert(number, CustomOp {
+            params: params.to_vec(),
+            handler: Arc::new(Mutex::new(handler)),
+        });
+        self.decode_cache.clear();
+    }
+
+    // Makes op code `number` a host call: `<number> call, address, length` passes the
+    // call number and memory[address..address + length] to the handler, changes to the block
+    // are written back. Off by default, so regular programs don't accidentally trigger it.
+    pub fn enable_host_calls<F>(&mut self, number: u8, handler: F)
+            where F: FnMut(M::Cell, &mut [M::Cell]) + Send + 'static {
+        assert!(number < 100, "Op codes only have two digits");
+        assert!(OpCode::try_read(number as i64).is_none(), "Op code {} is already taken", number);
+        assert!(self.custom_ops.get(number).is_none(), "Op code {} is already a custom op code", number);
+        self.custom_ops.host_call = Some(HostCall {
+            number: number,
+            handler: Arc::new(Mutex::new(handler)),
+        });
+        self.decode_cache.clear();
+    }
+
+    pub fn set_observer<T: VmObserver + 'static>(&mut self, observer: T) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // Keeps an undo log of the last `limit` steps, so step_back() can be used
+    pub fn enable_history(&mut self, limit: usize) {
+        self.history = Some(History::new(limit));
+    }
+
+    // Undoes the last step. Memory writes, instruction pointer, relative base and state
+    // are always restored, input and output only if the source/sink supports it.
+    // Returns false if there is no history (left).
+    pub fn step_back(&mut self) -> bool {
+        let record = match self.history {
+            Some(ref mut history) => match history.records.pop_back() {
+                Some(record) => record,
+                None => return false,
+            },
+            None => return false,
+        };
+        for &(address, value) in record.writes.iter().rev() {
+            self.memory.set(address, value);
+        }
+        if let Some(value) = record.input {
+            self.input_source.unread(value);
+        }
+        if record.output {
+            self.output_sink.unwrite();
+        }
+        self.instruction_pointer = record.instruction_pointer;
+        self.relative_base = record.relative_base;
+        self.state = record.state;
+        return true;
+    }
+
+    // Memory access from the outside. Unlike the program's own accesses these don't go
+    // through the memory map, the read-only ranges or the audit.
+    pub fn read_mem(&self, address: usize) -> M::Cell {
+        return self.memory.get(address);
+    }
+
+    pub fn write_mem(&mut self, address: usize, value: M::Cell) {
+        self.memory.set(address, value);
+    }
+
+    // One past the highest address that was initialized or written
+    pub fn mem_len(&self) -> usize {
+        return self.memory.len();
+    }
+
+    // Makes the program trap when it writes into the range. Writes from the outside
+    // (write_mem(), patches) still work.
+    pub fn protect(&mut self, range: Range<usize>) {
+        self.read_only.push(range);
+    }
+
+    // Protects the program as it is loaded right now, for programs that shouldn't modify
+    // their own code
+    pub fn protect_program(&mut self) {
+        let len = self.memory.len();
+        self.protect(0..len);
+    }
+
+    // The whole tape as comma separated values, in the same format as the programs
+    pub fn memory_image(&self) -> String {
+        let values: Vec<String> = (0..self.memory.len()).map(|i| self.memory.get(i).to_string()).collect();
+        return values.join(",");
+    }
+
+    // Write the memory to a file, e.g. to diff it against the program or another dump
+    pub fn dump_memory(&self, filename: &str) {
+        fs::write(filename, self.memory_image() + "\n").expect("Could not write memory dump");
+    }
+
+    // Records all inputs and outputs (with step indices) into self.transcript
+    pub fn enable_transcript(&mut self) {
+        self.transcript = Some(Transcript::new());
+    }
+
+    // Counts executed instructions per opcode and address and prints a report on halt
+    pub fn enable_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    // Steps, I/O counts and instructions per second, printed when the program halts
+    pub fn enable_metrics(&mut self) {
+        self.metrics = Some(Metrics::new());
+    }
+
+    // Records executed addresses. Call this before running, so the original program
+    // extent is known.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new(self.memory.len()));
+    }
+
+    // Tracks reads of memory past the program that was never written, see ReadAudit.
+    // With strict the vm faults on the first one.
+    pub fn enable_read_audit(&mut self, strict: bool) {
+        self.read_audit = Some(ReadAudit::new(self.memory.len(), strict));
+    }
+
+    // The callers of run() and co. don't check for faults, so they get a panic with
+    // the whole description instead
+    fn panic_on_fault(&self) -> ! {
+        panic!("{}", self.fault.as_ref().expect("Faulted without a fault"));
+    }
+
+    // Every run() and co. is timed, if metrics are enabled
+    fn start_run(&self) -> RunTimer {
+        return RunTimer::start(self.metrics.is_some(), self.steps);
+    }
+
+    fn stop_run(&mut self, timer: RunTimer) {
+        timer.stop(self.metrics.as_mut(), self.steps);
+        if self.state == VmState::Terminated {
+            if let Some(ref metrics) = self.metrics {
+                metrics.print_summary();
+            }
+        }
+    }
+
+    pub fn run(&mut self) -> VmState {
+        let timer = self.start_run();
+        loop {
+            match self.step() {
+                VmState::NotStarted => panic!("Invalid state after step()"),
+                VmState::Running => (), // keep going
+                VmState::WaitForInput => break, // suspend
+                VmState::Paused => break, // interrupted
+                VmState::Terminated => break, // done
+                VmState::LimitExceeded => break,
+                VmState::Faulted => self.panic_on_fault(),
+            }
+        }
+        self.stop_run(timer);
+        return self.state;
+    }
+
+    // Like run(), but faults are returned instead of panicking
+    pub fn try_run(&mut self) -> RunResult {
+        let timer = self.start_run();
+        let result = loop {
+            match self.step() {
+                VmState::NotStarted => panic!("Invalid state after step()"),
+                VmState::Running => (),
+                VmState::WaitForInput => break RunResult::NeedsInput,
+                VmState::Paused => break RunResult::Paused,
+                VmState::Terminated => break RunResult::Halted,
+                VmState::LimitExceeded => break RunResult::LimitExceeded(self.limit_exceeded.unwrap()),
+                VmState::Faulted => break RunResult::Faulted(self.fault.clone().unwrap()),
+            }
+        };
+        self.stop_run(timer);
+        return result;
+    }
+
+    // Writes the fault and the memory to the file when the program faults
+    pub fn enable_crash_dump(&mut self, filename: &str) {
+        self.crash_dump = Some(filename.to_string());
+    }
+
+    // Like run(), but pauses right after the next output instruction and returns the
+    // value instead of writing it to the output sink. Returns None if the VM had to
+    // wait for input or terminated before outputting anything.
+    pub fn run_until_output(&mut self) -> Option<i64> {
+        let timer = self.start_run();
+        self.capture_output = true;
+        self.captured_output = None;
+        loop {
+            match self.step() {
+                VmState::NotStarted => panic!("Invalid state after step()"),
+                VmState::Running => if self.captured_output.is_some() { break; },
+                VmState::WaitForInput => break,
+                VmState::Paused => break,
+                VmState::Terminated => break,
+                VmState::LimitExceeded => break,
+                VmState::Faulted => self.panic_on_fault(),
+            }
+        }
+        self.capture_output = false;
+        self.stop_run(timer);
+        return self.captured_output.take();
+    }
+
+    // Like run(), but executes at most max_steps instructions. Returns how many were
+    // executed and whether the budget ran out (i.e. the VM could keep going).
+    pub fn run_for(&mut self, max_steps: usize) -> (usize, bool) {
+        if self.state == VmState::Terminated {
+            return (0, false);
+        }
+        let timer = self.start_run();
+        let mut steps = 0;
+        let result = loop {
+            if steps >= max_steps {
+                break (steps, true);
+            }
+            match self.step() {
+                VmState::NotStarted => panic!("Invalid state after step()"),
+                VmState::Running => steps += 1,
+                VmState::WaitForInput => break (steps, false),
+                VmState::Paused => break (steps + 1, false),
+                VmState::Terminated => break (steps + 1, false),
+                VmState::LimitExceeded => break (steps, false),
+                VmState::Faulted => self.panic_on_fault(),
+            }
+        };
+        self.stop_run(timer);
+        return result;
+    }
+}
+
+// Only the registers and sizes, the sinks and the memory can be huge (and don't need to be Debug)
+impl<I: InputSource, O: OutputSink, M: MemoryBackend> fmt::Debug for Vm<I, O, M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.debug_struct("Vm")
+            .field("instruction_pointer", &self.instruction_pointer)
+            .field("relative_base", &self.relative_base)
+            .field("state", &self.state)
+            .field("steps", &self.steps)
+            .field("queued_inputs", &self.input_source.len())
+            .field("buffered_outputs", &self.output_sink.buffered())
+            .field("memory_len", &self.mem_len())
+            .field("limit_exceeded", &self.limit_exceeded)
+            .field("fault", &self.fault)
+            .finish();
+    }
+}
+
+// e.g. "waiting for input at ip 12 (rb 40, 1234 steps, 0 inputs queued, 3 outputs)"
+impl<I: InputSource, O: OutputSink, M: MemoryBackend> fmt::Display for Vm<I, O, M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at ip {} (rb {}, {} steps, {} inputs queued", self.state, self.instruction_pointer,
+            self.relative_base, self.steps, self.input_source.len())?;
+        if let Some(outputs) = self.output_sink.buffered() {
+            write!(f, ", {} outputs", outputs)?;
+        }
+        return write!(f, ")");
+    }
+}
+
+// Forks the machine, e.g. to branch at decision points in a search. Interrupt, observer and
+// memory-mapped callbacks can't be cloned, so the clone doesn't have any. Custom op codes
+// and the host call are kept, the clone shares their handlers.
+impl<I: InputSource + Clone, O: OutputSink + Clone, M: MemoryBackend + Clone> Clone for Vm<I, O, M> {
+    fn clone(&self) -> Vm<I, O, M> {
+        return Vm {
+            memory: self.memory.clone(),
+            instruction_pointer: self.instruction_pointer,
+            input_source: self.input_source.clone(),
+            output_sink: self.output_sink.clone(),
+            state: self.state,
+            relative_base: self.relative_base,
+            capture_output: self.capture_output,
+            captured_output: self.captured_output,
+            interrupt: None,
+            profiler: self.profiler.clone(),
+            metrics: self.metrics.clone(),
+            coverage: self.coverage.clone(),
+            read_audit: self.read_audit.clone(),
+            memory_map: MemoryMap::new(),
+            read_only: self.read_only.clone(),
+            decode_cache: self.decode_cache.clone(),
+            history: self.history.clone(),
+            transcript: self.transcript.clone(),
+            custom_ops: self.custom_ops.clone(),
+            limits: self.limits,
+            limit_exceeded: self.limit_exceeded,
+            fault: self.fault.clone(),
+            trace: self.trace.clone(),
+            crash_dump: self.crash_dump.clone(),
+            steps: self.steps,
+            output_count: self.output_count,
+            observer: None,
+        };
+    }
+}
+
+// For the days that just feed some inputs and want the outputs. Err is how the program
+// stopped instead of halting (faulted, ran out of input, ...).
+pub fn run(program: &[Cell], inputs: &[i64]) -> Result<Vec<i64>, RunResult> {
+    return run_vm(program, inputs).1;
+}
+
+// Same as run(), but also returns the vm to look at (or dump) its memory afterwards
+pub fn run_vm(program: &[Cell], inputs: &[i64]) -> (Vm<VecDeque<i64>, VecDeque<i64>>, Result<Vec<i64>, RunResult>) {
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program.to_vec());
+    vm.input_source.extend(inputs);
+    let result = match vm.try_run() {
+        RunResult::Halted => Ok(vm.output_sink.iter().cloned().collect()),
+        result => Err(result),
+    };
+    return (vm, result);
+}
+
+// The file given with `--dump <file>` on the command line of a day binary, if any
+pub fn dump_arg() -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    return args.iter().position(|arg| arg == "--dump").and_then(|i| args.get(i + 1).cloned());
+}
+
+// All patch files given with `--patch <file>` (can be repeated)
+pub fn patch_args() -> Vec<Patch> {
+    let args: Vec<String> = env::args().collect();
+    return args.windows(2).filter(|pair| pair[0] == "--patch")
+        .map(|pair| Patch::load(&pair[1]).unwrap_or_else(|err| panic!("{}", err)))
+        .collect();
+}
+
+// read_program() with the `--patch` files applied
+pub fn read_patched_program(filename: &str) -> Vec<Cell> {
+    let mut program = read_program(filename);
+    for patch in patch_args() {
+        patch.apply(&mut program);
+    }
+    return program;
+}
+
+pub fn read_program(filename: &str) -> Vec<Cell> {
+    return read_program_as::<Cell>(filename);
+}
+
+// For vms with a different cell type, e.g. Vm<_, _, InfiniteTape<i32>>
+pub fn read_program_as<T: IntcodeCell>(filename: &str) -> Vec<T> {
+    let program_str = fs::read_to_string(&filename)
+        .unwrap_or_else(|err| panic!("Could not read program '{}': {}", filename, err));
+    return try_parse_program_as::<T>(&program_str)
+        .unwrap_or_else(|err| panic!("Could not parse program '{}': {}", filename, err));
+}
+
+pub fn parse_program(program_str: &str) -> Vec<Cell> {
+    return parse_program_as::<Cell>(program_str);
+}
+
+// Panics on malformed programs, use try_parse_program_as to handle that
+pub fn parse_program_as<T: IntcodeCell>(program_str: &str) -> Vec<T> {
+    return try_parse_program_as::<T>(program_str).unwrap_or_else(|err| panic!("Could not parse program: {}", err));
+}
@@ -0,0 +1,790 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Stderr, Stdout, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub trait InputSource {
+    fn read(&mut self) -> i64;
+    fn len(&self) -> usize;
+
+    // Puts a value back, so it is read next (used to step backwards).
+    // Returns false if the source doesn't support that.
+    fn unread(&mut self, _value: i64) -> bool {
+        return false;
+    }
+}
+
+pub trait OutputSink {
+    fn write(&mut self, value: i64);
+
+    // How many written values the sink still holds, if it keeps them at all
+    fn buffered(&self) -> Option<usize> {
+        return None;
+    }
+
+    // Takes back the last written value. Returns false if the sink doesn't support that.
+    fn unwrite(&mut self) -> bool {
+        return false;
+    }
+}
+
+impl InputSource for VecDeque<i64> {
+    fn read(&mut self) -> i64 {
+        if self.len() == 0 {
+            panic!("InputSource VecDeque is empty!");
+        }
+        return self.pop_front().unwrap();
+    }
+
+    fn len(&self) -> usize {
+        return self.len();
+    }
+
+    fn unread(&mut self, value: i64) -> bool {
+        self.push_front(value);
+        return true;
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn write(&mut self, value: i64) {
+        self.push_back(value);
+    }
+
+    fn buffered(&self) -> Option<usize> {
+        return Some(self.len());
+    }
+
+    fn unwrite(&mut self) -> bool {
+        return self.pop_back().is_some();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlushPolicy {
+    EveryValue, // for watching the output live
+    Manual, // call flush(), or rely on the writer flushing itself on drop (BufWriter does)
+}
+
+// Writes values as text to anything that implements io::Write (stdout, stderr, files,
+// Vec<u8>, ...), each one followed by the separator
+#[derive(Clone)]
+pub struct WriteSink<W: Write> {
+    writer: W,
+    pub separator: String,
+    pub flush_policy: FlushPolicy,
+}
+
+impl<W: Write> WriteSink<W> {
+    // One value per line, flushed right away
+    pub fn new(writer: W) -> WriteSink<W> {
+        return WriteSink { writer: writer, separator: "\n".to_string(), flush_policy: FlushPolicy::EveryValue };
+    }
+
+    pub fn with_separator(mut self, separator: &str) -> WriteSink<W> {
+        self.separator = separator.to_string();
+        return self;
+    }
+
+    pub fn with_flush_policy(mut self, flush_policy: FlushPolicy) -> WriteSink<W> {
+        self.flush_policy = flush_policy;
+        return self;
+    }
+
+    pub fn flush(&mut self) {
+        self.writer.flush().expect("Could not flush output");
+    }
+
+    pub fn get_ref(&self) -> &W {
+        return &self.writer;
+    }
+
+    pub fn into_inner(mut self) -> W {
+        self.flush();
+        return self.writer;
+    }
+}
+
+impl<W: Write> OutputSink for WriteSink<W> {
+    fn write(&mut self, value: i64) {
+        write!(self.writer, "{}{}", value, self.separator).expect("Could not write output");
+        if self.flush_policy == FlushPolicy::EveryValue {
+            self.flush();
+        }
+    }
+}
+
+// println!() for every value
+pub type ConsoleOutputSink = WriteSink<Stdout>;
+
+impl Default for WriteSink<Stdout> {
+    fn default() -> Self {
+        return WriteSink::new(io::stdout());
+    }
+}
+
+impl Default for WriteSink<Stderr> {
+    fn default() -> Self {
+        return WriteSink::new(io::stderr());
+    }
+}
+
+impl Default for WriteSink<Vec<u8>> {
+    fn default() -> Self {
+        return WriteSink::new(Vec::new());
+    }
+}
+
+// Prints values as characters instead of numbers
+#[derive(Clone)]
+pub struct AsciiConsoleOutputSink {
+}
+
+impl OutputSink for AsciiConsoleOutputSink {
+    fn write(&mut self, value: i64) {
+        print!("{}", value as u8 as char);
+    }
+}
+
+impl Default for AsciiConsoleOutputSink {
+    fn default() -> Self {
+        return AsciiConsoleOutputSink {};
+    }
+}
+
+// Collects the output as text, for ASCII programs and for comparing output in tests.
+// Values 0-255 become chars, everything else (usually the final numeric result) goes to
+// non_ascii, together with the byte offset in text it was written at.
+#[derive(Default, Clone, Debug)]
+pub struct AsciiStringSink {
+    pub text: String,
+    pub non_ascii: Vec<(usize, i64)>,
+}
+
+impl AsciiStringSink {
+    pub fn new() -> AsciiStringSink {
+        return AsciiStringSink { text: String::new(), non_ascii: Vec::new() };
+    }
+
+    // The text so far, leaves the sink empty (e.g. after every prompt)
+    pub fn take(&mut self) -> String {
+        return std::mem::replace(&mut self.text, String::new());
+    }
+
+    pub fn non_ascii_values(&self) -> Vec<i64> {
+        return self.non_ascii.iter().map(|&(_, value)| value).collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.non_ascii.clear();
+    }
+}
+
+impl OutputSink for AsciiStringSink {
+    fn write(&mut self, value: i64) {
+        if value >= 0 && value < 256 {
+            self.text.push(value as u8 as char);
+        } else {
+            self.non_ascii.push((self.text.len(), value));
+        }
+    }
+
+    fn unwrite(&mut self) -> bool {
+        // a non-ASCII value at the end of the text was written after the last char
+        if let Some(&(offset, _)) = self.non_ascii.last() {
+            if offset == self.text.len() {
+                self.non_ascii.pop();
+                return true;
+            }
+        }
+        return self.text.pop().is_some();
+    }
+}
+
+pub fn is_ascii_output(value: i64) -> bool {
+    return (value >= 32 && value < 127) || value == '\n' as i64;
+}
+
+// Routes printable ASCII (and newlines) to one sink and everything else to another, e.g.
+// to print the picture an ASCII program draws but keep the final (big) result.
+// Small numeric results will end up in the ASCII sink, there is no way to tell.
+#[derive(Default, Clone)]
+pub struct AsciiDemux<A: OutputSink, N: OutputSink> {
+    pub ascii: A,
+    pub numeric: N,
+}
+
+impl<A: OutputSink, N: OutputSink> AsciiDemux<A, N> {
+    pub fn new(ascii: A, numeric: N) -> AsciiDemux<A, N> {
+        return AsciiDemux { ascii: ascii, numeric: numeric };
+    }
+}
+
+impl<A: OutputSink, N: OutputSink> OutputSink for AsciiDemux<A, N> {
+    fn write(&mut self, value: i64) {
+        if is_ascii_output(value) {
+            self.ascii.write(value);
+        } else {
+            self.numeric.write(value);
+        }
+    }
+}
+
+// Writes every value to both sinks, e.g. a VecDeque to work with and the console to watch.
+// Nest them for more: Tee<A, Tee<B, C>>
+#[derive(Default, Clone)]
+pub struct Tee<A: OutputSink, B: OutputSink> {
+    pub first: A,
+    pub second: B,
+}
+
+impl<A: OutputSink, B: OutputSink> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Tee<A, B> {
+        return Tee { first: first, second: second };
+    }
+}
+
+impl<A: OutputSink, B: OutputSink> OutputSink for Tee<A, B> {
+    fn write(&mut self, value: i64) {
+        self.first.write(value);
+        self.second.write(value);
+    }
+
+    fn unwrite(&mut self) -> bool {
+        let first = self.first.unwrite();
+        let second = self.second.unwrite();
+        return first && second;
+    }
+}
+
+// Like Tee, but for any number of sinks (of different types) that are added at runtime
+#[derive(Default)]
+pub struct MultiSink {
+    pub sinks: Vec<Box<dyn OutputSink + Send>>,
+}
+
+impl MultiSink {
+    pub fn new() -> MultiSink {
+        return MultiSink { sinks: Vec::new() };
+    }
+
+    pub fn add<S: OutputSink + Send + 'static>(&mut self, sink: S) {
+        self.sinks.push(Box::new(sink));
+    }
+}
+
+impl OutputSink for MultiSink {
+    fn write(&mut self, value: i64) {
+        for sink in self.sinks.iter_mut() {
+            sink.write(value);
+        }
+    }
+
+    fn unwrite(&mut self) -> bool {
+        let mut all = true;
+        for sink in self.sinks.iter_mut() {
+            all = sink.unwrite() && all;
+        }
+        return all;
+    }
+}
+
+// Slows down forwarding to the inner sink for animations. Either every value_per_frame
+// values make up a frame, or frames are ended explicitly with end_frame() (e.g. after
+// every run() of a game loop). Every frame waits until 1/fps after the previous one.
+pub struct ThrottledSink<S: OutputSink> {
+    pub inner: S,
+    values_per_frame: Option<usize>,
+    frame_time: Duration,
+    count: usize,
+    last_frame: Option<Instant>,
+}
+
+impl<S: OutputSink> ThrottledSink<S> {
+    pub fn per_second(inner: S, values_per_second: f64) -> ThrottledSink<S> {
+        return ThrottledSink::per_frame(inner, 1, values_per_second);
+    }
+
+    pub fn per_frame(inner: S, values_per_frame: usize, fps: f64) -> ThrottledSink<S> {
+        let mut sink = ThrottledSink::frames(inner, fps);
+        sink.values_per_frame = Some(values_per_frame);
+        return sink;
+    }
+
+    pub fn frames(inner: S, fps: f64) -> ThrottledSink<S> {
+        assert!(fps > 0.0, "ThrottledSink needs a positive frame rate, got {}", fps);
+        return ThrottledSink {
+            inner: inner,
+            values_per_frame: None,
+            frame_time: Duration::from_secs_f64(1.0 / fps),
+            count: 0,
+            last_frame: None,
+        };
+    }
+
+    pub fn end_frame(&mut self) {
+        let now = Instant::now();
+        let mut frame = now;
+        if let Some(last_frame) = self.last_frame {
+            let next = last_frame + self.frame_time;
+            if next > now {
+                thread::sleep(next - now);
+                frame = next;
+            }
+        }
+        self.last_frame = Some(frame);
+        self.count = 0;
+    }
+}
+
+impl<S: OutputSink> OutputSink for ThrottledSink<S> {
+    fn write(&mut self, value: i64) {
+        self.inner.write(value);
+        self.count += 1;
+        if Some(self.count) == self.values_per_frame {
+            self.end_frame();
+        }
+    }
+
+    fn buffered(&self) -> Option<usize> {
+        return self.inner.buffered();
+    }
+
+    fn unwrite(&mut self) -> bool {
+        return self.inner.unwrite();
+    }
+}
+
+// Calls the closure whenever the VM wants input, so it never runs dry
+pub struct FnInputSource<F: FnMut() -> i64> {
+    func: F,
+}
+
+impl<F: FnMut() -> i64> FnInputSource<F> {
+    pub fn new(func: F) -> FnInputSource<F> {
+        return FnInputSource { func: func };
+    }
+}
+
+impl<F: FnMut() -> i64> InputSource for FnInputSource<F> {
+    fn read(&mut self) -> i64 {
+        return (self.func)();
+    }
+
+    fn len(&self) -> usize {
+        return usize::max_value();
+    }
+}
+
+// Combinators, e.g. a phase setting followed by whatever comes through a channel:
+// chain(once(phase), receiver)
+
+pub struct Once {
+    value: Option<i64>,
+}
+
+pub fn once(value: i64) -> Once {
+    return Once { value: Some(value) };
+}
+
+impl InputSource for Once {
+    fn read(&mut self) -> i64 {
+        return self.value.take().expect("InputSource Once was already read!");
+    }
+
+    fn len(&self) -> usize {
+        return if self.value.is_some() { 1 } else { 0 };
+    }
+}
+
+pub struct Repeat {
+    value: i64,
+}
+
+pub fn repeat(value: i64) -> Repeat {
+    return Repeat { value: value };
+}
+
+impl InputSource for Repeat {
+    fn read(&mut self) -> i64 {
+        return self.value;
+    }
+
+    fn len(&self) -> usize {
+        return usize::max_value();
+    }
+}
+
+// The next value is fetched ahead of time, so len() knows whether there is one
+pub struct FromIter<T: Iterator<Item = i64>> {
+    next: Option<i64>,
+    iter: T,
+}
+
+pub fn from_iter<V: IntoIterator<Item = i64>>(values: V) -> FromIter<V::IntoIter> {
+    let mut iter = values.into_iter();
+    return FromIter { next: iter.next(), iter: iter };
+}
+
+impl<T: Iterator<Item = i64>> InputSource for FromIter<T> {
+    fn read(&mut self) -> i64 {
+        let value = self.next.expect("InputSource FromIter is empty!");
+        self.next = self.iter.next();
+        return value;
+    }
+
+    fn len(&self) -> usize {
+        return match self.next {
+            Some(_) => self.iter.size_hint().0.saturating_add(1),
+            None => 0,
+        };
+    }
+}
+
+// Reads from the first source until it is empty, then from the second
+pub struct Chain<A: InputSource, B: InputSource> {
+    pub first: A,
+    pub second: B,
+}
+
+pub fn chain<A: InputSource, B: InputSource>(first: A, second: B) -> Chain<A, B> {
+    return Chain { first: first, second: second };
+}
+
+impl<A: InputSource, B: InputSource> InputSource for Chain<A, B> {
+    fn read(&mut self) -> i64 {
+        if self.first.len() > 0 {
+            return self.first.read();
+        }
+        return self.second.read();
+    }
+
+    fn len(&self) -> usize {
+        return self.first.len().saturating_add(self.second.len());
+    }
+
+    fn unread(&mut self, value: i64) -> bool {
+        return self.first.unread(value) || self.second.unread(value);
+    }
+}
+
+pub struct FnOutputSink<F: FnMut(i64)> {
+    func: F,
+}
+
+impl<F: FnMut(i64)> FnOutputSink<F> {
+    pub fn new(func: F) -> FnOutputSink<F> {
+        return FnOutputSink { func: func };
+    }
+}
+
+impl<F: FnMut(i64)> OutputSink for FnOutputSink<F> {
+    fn write(&mut self, value: i64) {
+        (self.func)(value);
+    }
+}
+
+// mpsc::Receiver can't tell how many values are pending, so values are moved into
+// a buffer to implement len(). len() only gets &self, hence the RefCell.
+pub struct ChannelInputSource {
+    receiver: Receiver<i64>,
+    buffer: RefCell<VecDeque<i64>>,
+}
+
+impl ChannelInputSource {
+    pub fn new(receiver: Receiver<i64>) -> ChannelInputSource {
+        return ChannelInputSource {
+            receiver: receiver,
+            buffer: RefCell::new(VecDeque::new()),
+        };
+    }
+
+    fn fill_buffer(&self) {
+        let mut buffer = self.buffer.borrow_mut();
+        while let Ok(value) = self.receiver.try_recv() {
+            buffer.push_back(value);
+        }
+    }
+
+    // Blocks until at least one value is available. Returns false if the sending
+    // side hung up and nothing is buffered anymore.
+    pub fn wait(&mut self) -> bool {
+        self.fill_buffer();
+        if self.buffer.borrow().len() > 0 {
+            return true;
+        }
+        match self.receiver.recv() {
+            Ok(value) => {
+                self.buffer.borrow_mut().push_back(value);
+                return true;
+            },
+            Err(_) => return false,
+        }
+    }
+}
+
+impl InputSource for ChannelInputSource {
+    fn read(&mut self) -> i64 {
+        if !self.wait() {
+            panic!("InputSource channel is disconnected!");
+        }
+        return self.buffer.borrow_mut().pop_front().unwrap();
+    }
+
+    fn len(&self) -> usize {
+        self.fill_buffer();
+        return self.buffer.borrow().len();
+    }
+}
+
+pub struct ChannelOutputSink {
+    sender: Sender<i64>,
+}
+
+impl ChannelOutputSink {
+    pub fn new(sender: Sender<i64>) -> ChannelOutputSink {
+        return ChannelOutputSink { sender: sender };
+    }
+}
+
+impl OutputSink for ChannelOutputSink {
+    fn write(&mut self, value: i64) {
+        // If the receiving machine is gone (e.g. it terminated), the value is dropped
+        let _ = self.sender.send(value);
+    }
+}
+
+// Like mpsc::channel(): whatever is written to the sink can be read from the source
+pub fn channel() -> (ChannelOutputSink, ChannelInputSource) {
+    let (sender, receiver) = mpsc::channel();
+    return (ChannelOutputSink::new(sender), ChannelInputSource::new(receiver));
+}
+
+// Reads all whitespace/comma separated integers of a file up front
+#[derive(Default, Clone)]
+pub struct FileInputSource {
+    pub values: VecDeque<i64>,
+}
+
+impl FileInputSource {
+    pub fn open(filename: &str) -> FileInputSource {
+        let text = fs::read_to_string(filename).expect("Could not read input file");
+        let values = text.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| s.len() > 0)
+            .map(|s| s.parse::<i64>().unwrap_or_else(|_| panic!("Invalid value in input file: '{}'", s)))
+            .collect();
+        return FileInputSource { values: values };
+    }
+}
+
+impl InputSource for FileInputSource {
+    fn read(&mut self) -> i64 {
+        return self.values.pop_front().expect("Input file has no more values");
+    }
+
+    fn len(&self) -> usize {
+        return self.values.len();
+    }
+
+    fn unread(&mut self, value: i64) -> bool {
+        self.values.push_front(value);
+        return true;
+    }
+}
+
+// Commands for ASCII programs, one per line, `#` starts a comment:
+//
+//     north
+//     take mutex # the one in the hallway
+//
+// When the script is used up, further lines are read from stdin (if interactive is set),
+// so a known-good prefix of a text adventure can be replayed before playing on by hand.
+pub struct ScriptInputSource {
+    pub values: VecDeque<i64>,
+    pub interactive: bool,
+    pub echo: bool, // print script lines when they are read, so the output reads like a session
+    line_start: bool,
+}
+
+impl ScriptInputSource {
+    pub fn open(filename: &str) -> ScriptInputSource {
+        let text = fs::read_to_string(filename)
+            .unwrap_or_else(|err| panic!("Could not read script '{}': {}", filename, err));
+        return ScriptInputSource::from_text(&text);
+    }
+
+    pub fn from_text(text: &str) -> ScriptInputSource {
+        let mut values = VecDeque::new();
+        for line in text.lines() {
+            let command = line.split('#').next().unwrap().trim();
+            if command.len() > 0 {
+                values.extend(command.bytes().map(|b| b as i64));
+                values.push_back('\n' as i64);
+            }
+        }
+        return ScriptInputSource { values: values, interactive: true, echo: true, line_start: true };
+    }
+
+    // Only stdin
+    pub fn stdin() -> ScriptInputSource {
+        return ScriptInputSource::from_text("");
+    }
+
+    fn read_stdin_line(&mut self) {
+        io::stdout().flush().expect("Could not flush stdout");
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line).expect("Could not read stdin") == 0 {
+            panic!("stdin was closed while the program waits for input");
+        }
+        self.values.extend(line.trim_end().bytes().map(|b| b as i64));
+        self.values.push_back('\n' as i64);
+    }
+}
+
+impl InputSource for ScriptInputSource {
+    fn read(&mut self) -> i64 {
+        if self.values.len() == 0 {
+            if !self.interactive {
+                panic!("Script has no more input");
+            }
+            self.read_stdin_line();
+        } else if self.echo && self.line_start {
+            let line: String = self.values.iter().take_while(|&&v| v != '\n' as i64).map(|&v| v as u8 as char).collect();
+            println!("{}", line);
+        }
+        let value = self.values.pop_front().unwrap();
+        self.line_start = value == '\n' as i64;
+        return value;
+    }
+
+    // Interactive sources never run dry, read() blocks on stdin instead
+    fn len(&self) -> usize {
+        return if self.interactive { usize::max_value() } else { self.values.len() };
+    }
+}
+
+// Writes one value per line. Buffered, the rest is written when the sink is dropped.
+pub type FileOutputSink = WriteSink<BufWriter<File>>;
+
+impl WriteSink<BufWriter<File>> {
+    pub fn create(filename: &str) -> FileOutputSink {
+        let file = File::create(filename).expect("Could not create output file");
+        return WriteSink::new(BufWriter::new(file)).with_flush_policy(FlushPolicy::Manual);
+    }
+
+    pub fn append(filename: &str) -> FileOutputSink {
+        let file = OpenOptions::new().create(true).append(true).open(filename)
+            .expect("Could not open output file");
+        return WriteSink::new(BufWriter::new(file)).with_flush_policy(FlushPolicy::Manual);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_combinators() {
+        let mut input = chain(once(1), chain(from_iter(vec![2, 3]), repeat(0)));
+        let values: Vec<i64> = (0..5).map(|_| input.read()).collect();
+        assert_eq!(values, vec![1, 2, 3, 0, 0]);
+        let mut input = chain(once(4), from_iter(5..7));
+        assert_eq!(input.len(), 3);
+        input.read();
+        input.read();
+        input.read();
+        assert_eq!(input.len(), 0);
+    }
+
+    #[test]
+    fn script_input() {
+        let mut script = ScriptInputSource::from_text("# walkthrough\nno\n\n  take x # comment\n");
+        script.interactive = false;
+        script.echo = false;
+        assert_eq!(script.len(), 10);
+        let text: String = (0..10).map(|_| script.read() as u8 as char).collect();
+        assert_eq!(text, "no\ntake x\n");
+        assert_eq!(script.len(), 0);
+    }
+
+    #[test]
+    fn ascii_string_sink() {
+        let mut sink = AsciiStringSink::new();
+        for &value in &[72, 105, 10, 1234, -1, 0xe9] {
+            sink.write(value);
+        }
+        assert_eq!(sink.text, "Hi\n\u{e9}");
+        assert_eq!(sink.non_ascii, vec![(3, 1234), (3, -1)]);
+        assert_eq!(sink.non_ascii_values(), vec![1234, -1]);
+        assert!(sink.unwrite() && sink.unwrite());
+        assert_eq!(sink.non_ascii_values(), vec![1234]);
+        assert_eq!(sink.take(), "Hi\n");
+        assert_eq!(sink.text, "");
+    }
+    #[test]
+    fn throttled_sink() {
+        let mut sink = ThrottledSink::per_frame(VecDeque::new(), 2, 1000.0);
+        for value in 0..5 {
+            sink.write(value);
+        }
+        assert_eq!(sink.inner, vec![0, 1, 2, 3, 4]);
+        assert_eq!(sink.count, 1);
+        for &fps in &[0.0, -30.0, std::f64::NAN] {
+            let result = std::panic::catch_unwind(|| ThrottledSink::frames(VecDeque::new(), fps));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn tee_and_multi_sink() {
+        let mut tee = Tee::new(VecDeque::new(), WriteSink::new(Vec::new()).with_separator(","));
+        tee.write(1);
+        tee.write(-2);
+        assert_eq!(tee.first, vec![1, -2]);
+        assert_eq!(tee.second.get_ref(), b"1,-2,");
+        // the text can't be taken back, but the VecDeque still is
+        assert!(!tee.unwrite());
+        assert_eq!(tee.first, vec![1]);
+
+        let (first, mut first_values) = channel();
+        let (second, mut second_values) = channel();
+        let mut multi = MultiSink::new();
+        multi.add(first);
+        multi.add(second);
+        multi.write(3);
+        multi.write(4);
+        assert_eq!((first_values.read(), first_values.read()), (3, 4));
+        assert_eq!((second_values.read(), second_values.read()), (3, 4));
+        assert_eq!(second_values.len(), 0);
+    }
+
+    #[test]
+    fn files() {
+        let dir = std::env::temp_dir();
+        let input_file = dir.join(format!("intcode_input_{}.txt", std::process::id()));
+        let input_file = input_file.to_str().unwrap();
+        fs::write(input_file, "1, 2\n3  -4,\n").unwrap();
+        let mut input = FileInputSource::open(input_file);
+        fs::remove_file(input_file).unwrap();
+        assert_eq!(input.len(), 4);
+        let values: Vec<i64> = (0..4).map(|_| input.read()).collect();
+        assert_eq!(values, vec![1, 2, 3, -4]);
+
+        let output_file = dir.join(format!("intcode_output_{}.txt", std::process::id()));
+        let output_file = output_file.to_str().unwrap();
+        let mut output = FileOutputSink::create(output_file);
+        output.write(5);
+        output.write(6);
+        drop(output);
+        let mut output = FileOutputSink::append(output_file);
+        output.write(7);
+        drop(output);
+        let text = fs::read_to_string(output_file).unwrap();
+        fs::remove_file(output_file).unwrap();
+        assert_eq!(text, "5\n6\n7\n");
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+// Limits for running untrusted programs. When one is exceeded, the vm stops with
+// VmState::LimitExceeded (instead of eating all RAM or running forever) and
+// Vm::limit_exceeded says which one it was.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Limits {
+    pub max_memory: Option<usize>, // in cells, i.e. writes to addresses >= this fail
+    pub max_steps: Option<u64>,
+    pub max_outputs: Option<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LimitExceeded {
+    Memory { address: usize, limit: usize },
+    Steps { limit: u64 },
+    Outputs { limit: u64 },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match *self {
+            LimitExceeded::Memory { address, limit } =>
+                write!(f, "Memory limit exceeded: write to address {} (limit is {} cells)", address, limit),
+            LimitExceeded::Steps { limit } =>
+                write!(f, "Step limit exceeded: program did not halt within {} instructions", limit),
+            LimitExceeded::Outputs { limit } =>
+                write!(f, "Output limit exceeded: program tried to output more than {} values", limit),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use super::*;
+    use super::super::{parse_program, RunResult, Vm, VmState};
+
+    type TestVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+    fn limited(program: &str, limits: Limits) -> TestVm {
+        let mut vm: TestVm = Vm::new(parse_program(program));
+        vm.set_limits(limits);
+        return vm;
+    }
+
+    #[test]
+    fn steps() {
+        let mut vm = limited("1105,1,0", Limits { max_steps: Some(10), ..Limits::default() });
+        assert_eq!(vm.try_run(), RunResult::LimitExceeded(LimitExceeded::Steps { limit: 10 }));
+        assert_eq!((vm.state, vm.limit_exceeded, vm.instruction_pointer, vm.steps),
+            (VmState::LimitExceeded, Some(LimitExceeded::Steps { limit: 10 }), 0, 10));
+        assert_eq!(vm.step(), VmState::LimitExceeded);
+        assert_eq!(vm.steps, 10);
+    }
+
+    #[test]
+    fn outputs() {
+        // outputs 1 forever
+        let mut vm = limited("104,1,1105,1,0", Limits { max_outputs: Some(3), ..Limits::default() });
+        assert_eq!(vm.run(), VmState::LimitExceeded);
+        assert_eq!((vm.limit_exceeded, vm.instruction_pointer), (Some(LimitExceeded::Outputs { limit: 3 }), 0));
+        assert_eq!(vm.output_sink, vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn memory() {
+        let limits = Limits { max_memory: Some(50), ..Limits::default() };
+        let mut vm = limited("1101,1,2,9,1101,1,2,100,99,0", limits);
+        assert_eq!(vm.run(), VmState::LimitExceeded);
+        assert_eq!((vm.limit_exceeded, vm.instruction_pointer), (Some(LimitExceeded::Memory { address: 100, limit: 50 }), 4));
+        assert_eq!((vm.read_mem(9), vm.read_mem(100), vm.mem_len()), (3, 0, 10));
+
+        // a host call block that reaches past the limit, the handler isn't called
+        let mut vm = limited("11150,1,45,10,99", limits);
+        vm.enable_host_calls(50, |_, block| block[0] = 1);
+        assert_eq!(vm.run(), VmState::LimitExceeded);
+        assert_eq!((vm.limit_exceeded, vm.instruction_pointer), (Some(LimitExceeded::Memory { address: 54, limit: 50 }), 0));
+        assert_eq!((vm.read_mem(45), vm.mem_len()), (0, 5));
+    }
+}
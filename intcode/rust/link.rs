@@ -0,0 +1,155 @@
+// Objects are assembled programs that can still be moved and refer to labels of other
+// objects (asm::assemble_object()). link() puts them one after another (the first one
+// at address 0, where execution starts) and fills in the addresses. Saved as text:
+//   code <cells, comma separated>
+//   export <label> <address>
+//   import <cell> <label>       (the cell gets the address of the label added)
+//   reloc <cell> <factor>       (the cell gets factor * where the object ends up added)
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use super::{try_parse_program_as, Cell};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Object {
+    pub name: String, // for error messages, usually the source file
+    pub code: Vec<Cell>,
+    pub exports: BTreeMap<String, usize>,
+    pub imports: Vec<(usize, String)>,
+    pub relocations: Vec<(usize, Cell)>,
+}
+
+impl Object {
+    pub fn new(name: &str) -> Object {
+        return Object {
+            name: name.to_string(),
+            code: Vec::new(),
+            exports: BTreeMap::new(),
+            imports: Vec::new(),
+            relocations: Vec::new(),
+        };
+    }
+
+    // Appends a cell, see asm::Value
+    pub fn add(&mut self, value: Cell, relocation: Cell, import: Option<String>) {
+        let address = self.code.len();
+        self.code.push(value);
+        if relocation != 0 {
+            self.relocations.push((address, relocation));
+        }
+        if let Some(import) = import {
+            self.imports.push((address, import));
+        }
+    }
+
+    pub fn save(&self, filename: &str) {
+        let code: Vec<String> = self.code.iter().map(|v| v.to_string()).collect();
+        let mut text = format!("code {}\n", code.join(","));
+        for (name, address) in self.exports.iter() {
+            text.push_str(&format!("export {} {}\n", name, address));
+        }
+        for &(address, ref name) in self.imports.iter() {
+            text.push_str(&format!("import {} {}\n", address, name));
+        }
+        for &(address, factor) in self.relocations.iter() {
+            text.push_str(&format!("reloc {} {}\n", address, factor));
+        }
+        fs::write(filename, text).expect("Could not write object file");
+    }
+
+    pub fn load(filename: &str) -> Result<Object, String> {
+        let text = fs::read_to_string(filename).map_err(|e| format!("Could not read {}: {}", filename, e))?;
+        let mut object = Object::new(filename);
+        for (i, line) in text.lines().enumerate() {
+            let error = || format!("{}:{}: invalid entry '{}'", filename, i + 1, line);
+            let parts: Vec<&str> = line.split(' ').collect();
+            let number = |index: usize| parts[index].parse::<usize>().map_err(|_| error());
+            match (parts[0], parts.len()) {
+                ("", 1) => (),
+                ("code", 2) => object.code = try_parse_program_as(parts[1]).map_err(|err| format!("{}:{}: {}", filename, i + 1, err))?,
+                ("export", 3) => { object.exports.insert(parts[1].to_string(), number(2)?); },
+                ("import", 3) => object.imports.push((number(1)?, parts[2].to_string())),
+                ("reloc", 3) => object.relocations.push((number(1)?, parts[2].parse::<Cell>().map_err(|_| error())?)),
+                _ => return Err(error()),
+            }
+        }
+        let referenced = object.imports.iter().map(|&(a, _)| a).chain(object.relocations.iter().map(|&(a, _)| a));
+        if let Some(address) = referenced.filter(|&a| a >= object.code.len()).next() {
+            return Err(format!("{}: reference to cell {} outside of the code", filename, address));
+        }
+        return Ok(object);
+    }
+}
+
+pub fn link(objects: &[Object]) -> Result<Vec<Cell>, String> {
+    let mut bases = Vec::new();
+    let mut exports: HashMap<&str, (usize, &str)> = HashMap::new(); // label -> (address, object)
+    let mut length = 0;
+    for object in objects {
+        bases.push(length);
+        for (name, &address) in object.exports.iter() {
+            if let Some(&(_, other)) = exports.get(name.as_str()) {
+                return Err(format!("'{}' is exported by {} and {}", name, other, object.name));
+            }
+            exports.insert(name, (length + address, &object.name));
+        }
+        length += object.code.len();
+    }
+
+    let mut program: Vec<Cell> = Vec::with_capacity(length);
+    for (object, &base) in objects.iter().zip(bases.iter()) {
+        let mut code = object.code.clone();
+        for &(address, factor) in object.relocations.iter() {
+            code[address] += factor * base as Cell;
+        }
+        for &(address, ref name) in object.imports.iter() {
+            match exports.get(name.as_str()) {
+                Some(&(target, _)) => code[address] += target as Cell,
+                None => return Err(format!("{}: '{}' is not exported by any object", object.name, name)),
+            }
+        }
+        program.extend(code);
+    }
+    return Ok(program);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use super::super::asm::assemble_object;
+    use super::super::run;
+
+    #[test]
+    fn link_two_objects() {
+        let main = assemble_object("
+%include <stack.asm>
+%import print_number, stack
+        init_stack stack
+        push [value]
+        call print_number
+        hlt
+value:  data 1234
+", "main.asm").unwrap();
+        let library = assemble_object("
+%export print_number, stack
+%include <print_number.asm>
+stack:  data 0
+", "print.asm").unwrap();
+        assert!(main.imports.len() == 2 && main.relocations.len() > 0);
+
+        let path = env::temp_dir().join(format!("intcode_link_test_{}.o", process::id()));
+        let path = path.to_str().unwrap();
+        library.save(path);
+        let loaded = Object::load(path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.map(|object| object.code), Ok(library.code.clone()));
+
+        let program = link(&[main.clone(), library.clone()]).unwrap();
+        let output: String = run(&program, &[]).unwrap().iter().map(|&v| v as u8 as char).collect();
+        assert_eq!(output, "1234");
+        assert_eq!(link(&[main]).unwrap_err(), "main.asm: 'stack' is not exported by any object");
+        assert!(link(&[library.clone(), library]).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+// Command line tool for working with Intcode programs. Build with `rustc main.rs -o intcode`.
+#[path = "intcode.rs"]
+mod intcode;
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process;
+
+fn usage() -> ! {
+    eprintln!("Usage:");
+    eprintln!("  intcode asm <source> <output>");
+    eprintln!("  intcode object <source> <output.o>");
+    eprintln!("  intcode link <output> <objects...>");
+    eprintln!("  intcode disasm <program>");
+    eprintln!("  intcode list <program> [--run [inputs...]]");
+    eprintln!("  intcode debug <program> [inputs...]");
+    eprintln!("  intcode audit <program> [inputs...]");
+    eprintln!("  intcode record <program> <transcript>");
+    eprintln!("  intcode replay <program> <transcript>");
+    eprintln!("  intcode play <program> [script]");
+    eprintln!("  intcode transpile <program> <output.rs> [function name]");
+    eprintln!("  intcode wasm <program> <output.wasm>");
+    eprintln!("  intcode solve <program> <output>");
+    eprintln!("  intcode generate <output> [length] [seed]");
+    eprintln!("  intcode selftest");
+    eprintln!("  intcode test [directory]");
+    process::exit(1);
+}
+
+fn transpile(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let program = intcode::read_program(&args[0]);
+    let fn_name = if args.len() > 2 { args[2].as_str() } else { "run_program" };
+    match intcode::transpile::transpile(&program, fn_name) {
+        Ok(source) => fs::write(&args[1], source).expect("Could not write output file"),
+        Err(err) => {
+            eprintln!("Could not transpile program: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn wasm(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let program = intcode::read_program(&args[0]);
+    match intcode::wasm::compile(&program) {
+        Ok(bytes) => fs::write(&args[1], bytes).expect("Could not write output file"),
+        Err(err) => {
+            eprintln!("Could not compile program: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+// Also writes a source map to <output>.map
+fn asm(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let source = fs::read_to_string(&args[0]).expect("Could not read source file");
+    match intcode::asm::assemble(&source, &args[0]) {
+        Ok(assembled) => {
+            let text: Vec<String> = assembled.program.iter().map(|v| v.to_string()).collect();
+            fs::write(&args[1], text.join(",")).expect("Could not write output file");
+            assembled.source_map.save(&intcode::sourcemap::SourceMap::map_filename(&args[1]));
+        },
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn object(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let source = fs::read_to_string(&args[0]).expect("Could not read source file");
+    match intcode::asm::assemble_object(&source, &args[0]) {
+        Ok(object) => object.save(&args[1]),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+// The first object is put at address 0, so that's where the program starts
+fn link(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let mut objects = Vec::new();
+    for filename in &args[1..] {
+        match intcode::link::Object::load(filename) {
+            Ok(object) => objects.push(object),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+    }
+    match intcode::link::link(&objects) {
+        Ok(program) => {
+            let text: Vec<String> = program.iter().map(|v| v.to_string()).collect();
+            fs::write(&args[0], text.join(",")).expect("Could not write output file");
+        },
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn disasm(args: &[String]) {
+    if args.len() < 1 {
+        usage();
+    }
+    let program = intcode::read_program(&args[0]);
+    let lines = match intcode::sourcemap::SourceMap::load_for_program(&args[0]) {
+        Some(map) => intcode::disasm::disassemble_with_map(&program, &map),
+        None => intcode::disasm::disassemble(&program),
+    };
+    for (address, text) in lines {
+        if text.ends_with(':') {
+            println!("{}", text);
+        } else {
+            println!("{:>6}:   {}", address, text);
+        }
+    }
+}
+
+// Annotated listing. Code is found by following jumps, or with --run by running the
+// program with the given inputs and taking whatever was executed. Functions are found
+// by looking for the usual call/return pattern.
+fn list(args: &[String]) {
+    if args.len() < 1 {
+        usage();
+    }
+    let program = intcode::read_program(&args[0]);
+    let code = if args.len() > 1 && args[1] == "--run" {
+        let mut vm: intcode::Vm<VecDeque<i64>, VecDeque<i64>> = intcode::Vm::new(program.clone());
+        for arg in &args[2..] {
+            vm.input_source.push_back(arg.parse::<i64>().expect("Inputs must be integers"));
+        }
+        vm.enable_coverage();
+        vm.run();
+        vm.coverage.unwrap().covered
+    } else {
+        intcode::transpile::code_cells(&program)
+    };
+    let calls = intcode::calls::detect(&program, &code);
+    for line in intcode::disasm::annotated_listing(&program, &code, Some(&calls)) {
+        println!("{}", line);
+    }
+}
+
+// Runs the program and lists the reads of memory past the program that was never written
+fn audit(args: &[String]) {
+    if args.len() < 1 {
+        usage();
+    }
+    let mut vm: intcode::Vm<VecDeque<i64>, VecDeque<i64>> = intcode::Vm::new(intcode::read_program(&args[0]));
+    for arg in &args[1..] {
+        vm.input_source.push_back(arg.parse::<i64>().expect("Inputs must be integers"));
+    }
+    vm.enable_read_audit(false);
+    let state = vm.run();
+    println!("{:?} after {} steps", state, vm.steps);
+    vm.read_audit.unwrap().print_summary();
+}
+
+fn debug(args: &[String]) {
+    if args.len() < 1 {
+        usage();
+    }
+    let program = intcode::read_program(&args[0]);
+    let mut debugger = intcode::debugger::Debugger::new(program, 100000);
+    debugger.source_map = intcode::sourcemap::SourceMap::load_for_program(&args[0]);
+    debugger.notes = intcode::annotations::Annotations::load_for_program(&args[0]);
+    debugger.notes_file = Some(intcode::annotations::Annotations::notes_filename(&args[0]));
+    for arg in &args[1..] {
+        let value = arg.parse::<i64>().expect("Inputs must be integers");
+        debugger.vm.input_source.push_back(value);
+    }
+    debugger.repl();
+}
+
+// Runs a program interactively (inputs are read from stdin when needed) and saves a transcript
+fn record(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let mut vm: intcode::Vm<VecDeque<i64>, VecDeque<i64>> = intcode::Vm::new(intcode::read_program(&args[0]));
+    vm.enable_transcript();
+    let stdin = io::stdin();
+    loop {
+        let state = vm.run();
+        for value in vm.output_sink.drain(..) {
+            println!("{}", value);
+        }
+        if state != intcode::VmState::WaitForInput {
+            break;
+        }
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        for part in line.split(|c| c == ',' || char::is_whitespace(c)).filter(|p| p.len() > 0) {
+            match part.parse::<i64>() {
+                Ok(value) => vm.input_source.push_back(value),
+                Err(_) => eprintln!("Ignoring invalid input '{}'", part),
+            }
+        }
+    }
+    vm.transcript.unwrap().save(&args[1]);
+}
+
+// For ASCII programs: feeds the script (if given), then what is typed on stdin
+fn play(args: &[String]) {
+    if args.len() < 1 {
+        usage();
+    }
+    let input = match args.get(1) {
+        Some(script) => intcode::ScriptInputSource::open(script),
+        None => intcode::ScriptInputSource::stdin(),
+    };
+    let output = intcode::AsciiDemux::new(intcode::AsciiConsoleOutputSink::default(), intcode::ConsoleOutputSink::default());
+    let mut vm: intcode::Vm<_, _> = intcode::Vm::with_io(intcode::read_program(&args[0]), input, output);
+    vm.run();
+}
+
+// Feeds the inputs of a transcript back into the program and checks that the outputs match
+fn replay(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let recorded = intcode::Transcript::load(&args[1]);
+    let input = intcode::ReplayInputSource::new(&recorded);
+    let mut vm: intcode::Vm<intcode::ReplayInputSource, VecDeque<i64>> =
+        intcode::Vm::with_io(intcode::read_program(&args[0]), input, VecDeque::new());
+    vm.enable_transcript();
+    vm.run();
+    let replayed = vm.transcript.unwrap();
+    for (i, (a, b)) in recorded.events.iter().zip(replayed.events.iter()).enumerate() {
+        if a != b {
+            eprintln!("Mismatch at event {}: recorded {:?}, replayed {:?}", i, a, b);
+            process::exit(1);
+        }
+    }
+    if recorded.events.len() != replayed.events.len() {
+        eprintln!("Recorded {} events, replayed {}", recorded.events.len(), replayed.events.len());
+        process::exit(1);
+    }
+    println!("Replayed {} events, all match", replayed.events.len());
+}
+
+// Searches for inputs that make the program output the given value
+fn solve(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let program = intcode::read_program(&args[0]);
+    let target = args[1].parse::<i64>().expect("Output must be an integer");
+    let config = intcode::symbolic::SymbolicConfig::default();
+    match intcode::symbolic::find_inputs_for_output(&program, target, &config) {
+        Some(inputs) => {
+            let text: Vec<String> = inputs.iter().map(|v| v.to_string()).collect();
+            println!("{}", text.join(","));
+        },
+        None => {
+            eprintln!("No inputs found");
+            process::exit(1);
+        }
+    }
+}
+
+// Writes a random program that always halts and prints the inputs it reads
+fn generate(args: &[String]) {
+    if args.len() < 1 {
+        usage();
+    }
+    let mut config = intcode::generator::GeneratorConfig::default();
+    if args.len() > 1 {
+        config.length = args[1].parse::<usize>().expect("Length must be a number");
+    }
+    let seed = if args.len() > 2 { args[2].parse::<u64>().expect("Seed must be a number") } else { 1 };
+    let generated = intcode::generator::Generator::new(config, seed).generate();
+    let text: Vec<String> = generated.program.iter().map(|v| v.to_string()).collect();
+    fs::write(&args[0], text.join(",")).expect("Could not write output file");
+    let inputs: Vec<String> = generated.inputs.iter().map(|v| v.to_string()).collect();
+    println!("{}", inputs.join(" "));
+    eprintln!("{} steps, {} outputs", generated.steps, generated.outputs.len());
+}
+
+// Runs the built-in conformance tests against the VM
+fn selftest() {
+    let results = intcode::selftest::run_all();
+    let mut failed = 0;
+    for &(name, ref result) in results.iter() {
+        match *result {
+            Ok(()) => println!("ok    {}", name),
+            Err(ref err) => {
+                println!("FAIL  {}: {}", name, err);
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed, {} failed", results.len() - failed, failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+// Runs every *.ic test file below the directory (tests/intcode by default)
+fn test(args: &[String]) {
+    let dir = if args.len() > 0 { args[0].as_str() } else { "tests/intcode" };
+    let files = intcode::testfile::discover(Path::new(dir));
+    if files.len() == 0 {
+        eprintln!("No test files found in {}", dir);
+        process::exit(1);
+    }
+    let mut passed = 0;
+    let mut failed = 0;
+    for file in files {
+        for (name, result) in intcode::testfile::run_file(&file) {
+            match result {
+                Ok(()) => passed += 1,
+                Err(err) => {
+                    println!("FAIL  {}: {}", name, err);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        usage();
+    }
+    match args[1].as_str() {
+        "asm" => asm(&args[2..]),
+        "object" => object(&args[2..]),
+        "link" => link(&args[2..]),
+        "disasm" => disasm(&args[2..]),
+        "list" => list(&args[2..]),
+        "debug" => debug(&args[2..]),
+        "audit" => audit(&args[2..]),
+        "record" => record(&args[2..]),
+        "replay" => replay(&args[2..]),
+        "play" => play(&args[2..]),
+        "transpile" => transpile(&args[2..]),
+        "wasm" => wasm(&args[2..]),
+        "solve" => solve(&args[2..]),
+        "generate" => generate(&args[2..]),
+        "selftest" => selftest(),
+        "test" => test(&args[2..]),
+        _ => usage(),
+    }
+}
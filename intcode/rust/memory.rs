@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{Cell, IntcodeCell};
+
+pub trait MemoryBackend {
+    type Cell: IntcodeCell;
+
+    fn from_program(program: Vec<Self::Cell>) -> Self where Self: Sized;
+    // Replaces the whole memory with the program, backends override this to keep their
+    // allocations
+    fn load(&mut self, program: &[Self::Cell]) where Self: Sized {
+        *self = Self::from_program(program.to_vec());
+    }
+    fn get(&self, index: usize) -> Self::Cell;
+    fn set(&mut self, index: usize, value: Self::Cell);
+    // One past the highest address that was initialized or written
+    fn len(&self) -> usize;
+}
+
+#[derive(Clone)]
+pub struct InfiniteTape<T: IntcodeCell = Cell> {
+    pub data: Vec<T>,
+}
+
+impl<T: IntcodeCell> MemoryBackend for InfiniteTape<T> {
+    type Cell = T;
+
+    fn from_program(program: Vec<T>) -> InfiniteTape<T> {
+        return InfiniteTape { data: program };
+    }
+
+    fn load(&mut self, program: &[T]) {
+        self.data.clear();
+        self.data.extend_from_slice(program);
+    }
+
+    fn set(&mut self, index: usize, value: T) {
+        if index >= self.data.len() {
+            self.data.resize(index + 1, T::ZERO);
+        }
+        self.data[index] = value;
+    }
+
+    fn get(&self, index: usize) -> T {
+        if index >= self.data.len() {
+            return T::ZERO;
+        } else {
+            return self.data[index];
+        }
+    }
+
+    fn len(&self) -> usize {
+        return self.data.len();
+    }
+}
+
+// Only stores the cells that were actually written, so programs can use huge addresses
+// without allocating everything in between. Slower than InfiniteTape for normal programs.
+#[derive(Clone)]
+pub struct SparseTape<T: IntcodeCell = Cell> {
+    pub data: HashMap<usize, T>,
+    len: usize,
+}
+
+impl<T: IntcodeCell> MemoryBackend for SparseTape<T> {
+    type Cell = T;
+
+    fn from_program(program: Vec<T>) -> SparseTape<T> {
+        let len = program.len();
+        return SparseTape {
+            data: program.into_iter().enumerate().filter(|&(_, v)| v != T::ZERO).collect(),
+            len: len,
+        };
+    }
+
+    fn load(&mut self, program: &[T]) {
+        self.data.clear();
+        self.data.extend(program.iter().cloned().enumerate().filter(|&(_, v)| v != T::ZERO));
+        self.len = program.len();
+    }
+
+    fn set(&mut self, index: usize, value: T) {
+        if index >= self.len {
+            self.len = index + 1;
+        }
+        self.data.insert(index, value);
+    }
+
+    fn get(&self, index: usize) -> T {
+        return *self.data.get(&index).unwrap_or(&T::ZERO);
+    }
+
+    fn len(&self) -> usize {
+        return self.len;
+    }
+}
+
+const PAGE_SIZE: usize = 1024;
+// Pages below this index are looked up in a Vec, the ones above in a HashMap, so the
+// page table itself can't explode either
+const DENSE_PAGE_COUNT: usize = 1 << 18;
+
+type Page<T> = Arc<[T; PAGE_SIZE]>;
+
+// Memory split into fixed-size pages that are allocated on first write. Normal programs
+// live in a few contiguous pages, while a write to a huge address only costs one page.
+// Pages are shared between clones and only copied when one of them writes to it, so
+// forking a Vm with this backend is cheap.
+#[derive(Clone)]
+pub struct PagedTape<T: IntcodeCell = Cell> {
+    pages: Vec<Option<Page<T>>>,
+    far_pages: HashMap<usize, Page<T>>,
+    len: usize,
+}
+
+impl<T: IntcodeCell> PagedTape<T> {
+    fn page(&self, page_index: usize) -> Option<&Page<T>> {
+        if page_index < DENSE_PAGE_COUNT {
+            return match self.pages.get(page_index) {
+                Some(page) => page.as_ref(),
+                None => None,
+            };
+        }
+        return self.far_pages.get(&page_index);
+    }
+
+    // Copies the page first, if it is shared with a clone
+    fn page_mut(&mut self, page_index: usize) -> &mut [T; PAGE_SIZE] {
+        let page = if page_index < DENSE_PAGE_COUNT {
+            if page_index >= self.pages.len() {
+                self.pages.resize(page_index + 1, None);
+            }
+            self.pages[page_index].get_or_insert_with(|| Arc::new([T::ZERO; PAGE_SIZE]))
+        } else {
+            self.far_pages.entry(page_index).or_insert_with(|| Arc::new([T::ZERO; PAGE_SIZE]))
+        };
+        return Arc::make_mut(page);
+    }
+
+    // Number of pages this tape shares with clones
+    pub fn shared_page_count(&self) -> usize {
+        return self.pages.iter().filter_map(|p| p.as_ref()).chain(self.far_pages.values())
+            .filter(|p| Arc::strong_count(p) > 1).count();
+    }
+}
+
+impl<T: IntcodeCell> MemoryBackend for PagedTape<T> {
+    type Cell = T;
+
+    fn from_program(program: Vec<T>) -> PagedTape<T> {
+        let mut tape = PagedTape {
+            pages: Vec::new(),
+            far_pages: HashMap::new(),
+            len: 0,
+        };
+        for (index, chunk) in program.chunks(PAGE_SIZE).enumerate() {
+            tape.page_mut(index)[..chunk.len()].copy_from_slice(chunk);
+        }
+        tape.len = program.len();
+        return tape;
+    }
+
+    fn set(&mut self, index: usize, value: T) {
+        if index >= self.len {
+            self.len = index + 1;
+        }
+        self.page_mut(index / PAGE_SIZE)[index % PAGE_SIZE] = value;
+    }
+
+    fn get(&self, index: usize) -> T {
+        return match self.page(index / PAGE_SIZE) {
+            Some(page) => page[index % PAGE_SIZE],
+            None => T::ZERO,
+        };
+    }
+
+    fn len(&self) -> usize {
+        return self.len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use super::*;
+    use super::super::{parse_program, Vm, VmState};
+
+    #[test]
+    fn sparse_tape() {
+        let mut tape: SparseTape = SparseTape::from_program(vec![1, 0, 2]);
+        assert_eq!((tape.len(), tape.data.len()), (3, 2)); // zeros aren't stored
+        assert_eq!((tape.get(0), tape.get(1), tape.get(2), tape.get(3)), (1, 0, 2, 0));
+        tape.set(1000000000, 5);
+        assert_eq!(tape.len(), 1000000001);
+        assert_eq!((tape.get(1000000000), tape.get(999999999)), (5, 0));
+        tape.set(1, 7); // below len, len stays
+        assert_eq!((tape.get(1), tape.len()), (7, 1000000001));
+        tape.load(&[0, 0, 4, 0]);
+        assert_eq!((tape.len(), tape.data.len()), (4, 1));
+        assert_eq!((tape.get(1), tape.get(2), tape.get(1000000000)), (0, 4, 0));
+    }
+
+    #[test]
+    fn paged_tape() {
+        let program: Vec<Cell> = (1..=PAGE_SIZE as Cell + 1).collect(); // one cell into the second page
+        let mut tape: PagedTape = PagedTape::from_program(program);
+        assert_eq!((tape.len(), tape.pages.len()), (PAGE_SIZE + 1, 2));
+        assert_eq!((tape.get(0), tape.get(PAGE_SIZE - 1), tape.get(PAGE_SIZE)), (1, PAGE_SIZE as Cell, PAGE_SIZE as Cell + 1));
+        assert_eq!((tape.get(PAGE_SIZE + 1), tape.get(3 * PAGE_SIZE)), (0, 0));
+        tape.set(2 * PAGE_SIZE - 1, -1);
+        tape.set(2 * PAGE_SIZE, -2);
+        assert_eq!((tape.get(2 * PAGE_SIZE - 1), tape.get(2 * PAGE_SIZE)), (-1, -2));
+        assert_eq!((tape.len(), tape.pages.len()), (2 * PAGE_SIZE + 1, 3));
+        // past the dense page table
+        let far = DENSE_PAGE_COUNT * PAGE_SIZE + 5;
+        tape.set(far, 9);
+        assert_eq!((tape.get(far), tape.get(far - 1), tape.len()), (9, 0, far + 1));
+        assert_eq!((tape.pages.len(), tape.far_pages.len()), (3, 1));
+        tape.load(&[4, 0, 6]);
+        assert_eq!((tape.len(), tape.pages.len(), tape.far_pages.len()), (3, 1, 0));
+        assert_eq!((tape.get(0), tape.get(2), tape.get(PAGE_SIZE), tape.get(far)), (4, 6, 0, 0));
+    }
+
+    #[test]
+    fn copy_on_write() {
+        // reads a value into 5
+        let mut parent: Vm<VecDeque<i64>, VecDeque<i64>, PagedTape> = Vm::new(parse_program("3,5,99,0,0,7"));
+        assert_eq!(parent.run(), VmState::WaitForInput);
+        let mut child = parent.clone();
+        assert_eq!((parent.memory.shared_page_count(), child.memory.shared_page_count()), (1, 1));
+        child.input_source.push_back(42);
+        assert_eq!(child.run(), VmState::Terminated);
+        assert_eq!((child.read_mem(5), parent.read_mem(5)), (42, 7));
+        assert_eq!((parent.memory.shared_page_count(), child.memory.shared_page_count()), (0, 0));
+        parent.input_source.push_back(1);
+        assert_eq!(parent.run(), VmState::Terminated);
+        assert_eq!((child.read_mem(5), parent.read_mem(5)), (42, 1));
+    }
+
+    #[test]
+    fn code_at_far_address() {
+        // writes a halt to 1000000000 and jumps there
+        let program = parse_program("1101,0,99,1000000000,1105,1,1000000000");
+        let mut sparse: Vm<VecDeque<i64>, VecDeque<i64>, SparseTape> = Vm::new(program.clone());
+        assert_eq!(sparse.run(), VmState::Terminated);
+        assert_eq!(sparse.mem_len(), 1000000001);
+        let mut paged: Vm<VecDeque<i64>, VecDeque<i64>, PagedTape> = Vm::new(program);
+        assert_eq!(paged.run(), VmState::Terminated);
+    }
+}
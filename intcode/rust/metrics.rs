@@ -0,0 +1,51 @@
+// Counts and timing for Vm::enable_metrics(), printed when the program halts.
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    pub runs: u64, // how often the vm was (re)started, e.g. after waiting for input
+    pub steps: u64,
+    pub inputs: u64,
+    pub outputs: u64,
+    pub elapsed: Duration, // inside run() and co. only
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        return Metrics { runs: 0, steps: 0, inputs: 0, outputs: 0, elapsed: Duration::from_secs(0) };
+    }
+
+    pub fn instructions_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        return if seconds > 0.0 { self.steps as f64 / seconds } else { 0.0 };
+    }
+
+    pub fn print_summary(&self) {
+        eprintln!("Metrics: {} steps in {:.3}s ({:.0} instructions/s), {} runs, {} inputs, {} outputs",
+            self.steps, self.elapsed.as_secs_f64(), self.instructions_per_second(), self.runs, self.inputs, self.outputs);
+    }
+}
+
+// Lives for the duration of one run() call
+pub struct RunTimer {
+    started: Option<Instant>, // only measured if metrics are enabled
+    steps: u64,
+}
+
+impl RunTimer {
+    pub fn start(metrics_enabled: bool, steps: u64) -> RunTimer {
+        return RunTimer {
+            started: if metrics_enabled { Some(Instant::now()) } else { None },
+            steps: steps,
+        };
+    }
+
+    pub fn stop(self, metrics: Option<&mut Metrics>, steps: u64) {
+        if let (Some(metrics), Some(started)) = (metrics, self.started) {
+            metrics.runs += 1;
+            metrics.steps += steps - self.steps;
+            metrics.elapsed += started.elapsed();
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use super::IntcodeCell;
+
+// Callbacks attached to single addresses, so devices can be modelled as memory-mapped
+// peripherals. Only the data accesses of instructions go through here.
+pub struct MemoryMap<T: IntcodeCell> {
+    pub reads: HashMap<usize, Box<dyn FnMut() -> T + Send>>,
+    pub writes: HashMap<usize, Box<dyn FnMut(T) + Send>>,
+}
+
+impl<T: IntcodeCell> MemoryMap<T> {
+    pub fn new() -> MemoryMap<T> {
+        return MemoryMap {
+            reads: HashMap::new(),
+            writes: HashMap::new(),
+        };
+    }
+
+    pub fn read(&mut self, address: usize) -> Option<T> {
+        if self.reads.is_empty() {
+            return None;
+        }
+        return match self.reads.get_mut(&address) {
+            Some(callback) => Some(callback()),
+            None => None,
+        };
+    }
+
+    // Returns whether the write was handled by a callback
+    pub fn write(&mut self, address: usize, value: T) -> bool {
+        if self.writes.is_empty() {
+            return false;
+        }
+        return match self.writes.get_mut(&address) {
+            Some(callback) => {
+                callback(value);
+                true
+            },
+            None => false,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use super::super::{parse_program, Vm, VmState};
+
+    // out [100]; out [100]; add 5, 6, [101]; out [101]
+    const PROGRAM: &'static str = "4,100,4,100,1101,5,6,101,4,101,99";
+
+    #[test]
+    fn mapped_reads_and_writes() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program(PROGRAM));
+        let mut counter = 0;
+        vm.map_read(100, move || {
+            counter += 1;
+            return counter;
+        });
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let sink = written.clone();
+        vm.map_write(101, move |value| sink.lock().unwrap().push(value));
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink, vec![1, 2, 0]);
+        assert_eq!(*written.lock().unwrap(), vec![11]);
+        // the host sees the memory itself
+        assert_eq!((vm.read_mem(100), vm.read_mem(101)), (0, 0));
+
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program(PROGRAM));
+        vm.map_read(100, || 7);
+        vm.map_write(101, |_| ());
+        vm.unmap(101);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink, vec![7, 7, 11]);
+        assert_eq!(vm.read_mem(101), 11);
+    }
+}
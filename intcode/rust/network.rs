@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+
+use super::{Cell, Vm, VmState};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Packet {
+    pub address: i64,
+    pub x: i64,
+    pub y: i64,
+}
+
+pub enum NatAction {
+    Continue,
+    Send(Packet),
+    Stop,
+}
+
+// Gets every packet sent to an address without a machine behind it and is asked what
+// to do whenever the network went idle
+pub trait Nat {
+    fn on_packet(&mut self, packet: Packet) -> NatAction;
+    fn on_idle(&mut self) -> NatAction;
+}
+
+pub type NetworkVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+// Machines communicate by outputting packets as three values (destination address, x, y).
+// Every machine is booted with its address as the first input and reads -1 whenever it
+// asks for input and there are no packets queued for it.
+pub struct Network {
+    pub machines: Vec<NetworkVm>,
+    pub rounds: usize,
+}
+
+impl Network {
+    pub fn new(program: &Vec<Cell>, machine_count: usize) -> Network {
+        let mut machines: Vec<NetworkVm> = Vec::new();
+        for address in 0..machine_count {
+            let mut vm: NetworkVm = Vm::new(program.clone());
+            vm.input_source.push_back(address as i64);
+            machines.push(vm);
+        }
+        return Network {
+            machines: machines,
+            rounds: 0,
+        };
+    }
+
+    // Returns false if there is no machine with that address
+    pub fn deliver(&mut self, packet: Packet) -> bool {
+        if packet.address < 0 || packet.address as usize >= self.machines.len() {
+            return false;
+        }
+        let queue = &mut self.machines[packet.address as usize].input_source;
+        queue.push_back(packet.x);
+        queue.push_back(packet.y);
+        return true;
+    }
+
+    fn apply(&mut self, action: NatAction) -> bool {
+        match action {
+            NatAction::Continue => return true,
+            NatAction::Send(packet) => {
+                if !self.deliver(packet) {
+                    panic!("NAT sent packet to unknown address {}", packet.address);
+                }
+                return true;
+            },
+            NatAction::Stop => return false,
+        }
+    }
+
+    // Runs every machine until it waits for input once. Returns the packets sent and
+    // whether every machine was starved for input (i.e. got -1).
+    fn round(&mut self) -> (Vec<Packet>, bool) {
+        let mut packets: Vec<Packet> = Vec::new();
+        let mut all_starved = true;
+        for vm in &mut self.machines {
+            if vm.state == VmState::Terminated {
+                continue;
+            }
+            if vm.input_source.len() == 0 {
+                vm.input_source.push_back(-1);
+            } else {
+                all_starved = false;
+            }
+            vm.run();
+            while vm.output_sink.len() >= 3 {
+                packets.push(Packet {
+                    address: vm.output_sink.pop_front().unwrap(),
+                    x: vm.output_sink.pop_front().unwrap(),
+                    y: vm.output_sink.pop_front().unwrap(),
+                });
+            }
+        }
+        self.rounds += 1;
+        return (packets, all_starved);
+    }
+
+    // Round-robins all machines until the NAT stops the network or every machine terminated
+    pub fn run<N: Nat>(&mut self, nat: &mut N) {
+        loop {
+            if self.machines.iter().all(|vm| vm.state == VmState::Terminated) {
+                return;
+            }
+            let (packets, all_starved) = self.round();
+            let idle = all_starved && packets.is_empty();
+            for packet in packets {
+                if !self.deliver(packet) && !self.apply(nat.on_packet(packet)) {
+                    return;
+                }
+            }
+            if idle && !self.apply(nat.on_idle()) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_program;
+
+    // Keeps the packets for 255 and stops on the first idle network
+    struct TestNat {
+        packets: Vec<Packet>,
+        idle: usize,
+    }
+
+    impl Nat for TestNat {
+        fn on_packet(&mut self, packet: Packet) -> NatAction {
+            self.packets.push(packet);
+            return NatAction::Continue;
+        }
+
+        fn on_idle(&mut self) -> NatAction {
+            self.idle += 1;
+            return NatAction::Stop;
+        }
+    }
+
+    #[test]
+    fn exchange_packet() {
+        // Machine 0 sends (10, 20) to machine 1, which sends (x + y, y) to 255. Both wait
+        // for input forever afterwards.
+        let program = parse_program("3,100,1005,100,14,104,1,104,10,104,20,1105,1,38,\
+            3,101,1008,101,-1,102,1005,102,14,3,103,1,101,103,104,104,255,4,104,4,103,1105,1,38,\
+            3,105,1105,1,38");
+        let mut network = Network::new(&program, 2);
+        let mut nat = TestNat { packets: Vec::new(), idle: 0 };
+        network.run(&mut nat);
+        assert_eq!(nat.packets, vec![Packet { address: 255, x: 30, y: 20 }]);
+        assert_eq!((nat.idle, network.rounds), (1, 3));
+        assert!(!network.deliver(Packet { address: 2, x: 0, y: 0 }));
+    }
+}
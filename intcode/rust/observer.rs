@@ -0,0 +1,11 @@
+use super::OpCode;
+
+// Hooks into execution for visualizers, statistics and tests. All callbacks do nothing
+// by default, so only the interesting ones have to be implemented.
+pub trait VmObserver: Send {
+    // Called before the instruction at `address` is executed
+    fn on_step(&mut self, _address: usize, _op_code: OpCode) {}
+    fn on_input(&mut self, _value: i64) {}
+    fn on_output(&mut self, _value: i64) {}
+    fn on_halt(&mut self) {}
+}
@@ -0,0 +1,77 @@
+// Program text is comma separated integers. Whitespace (newlines included) around values is
+// ignored, `#` starts a comment that goes to the end of the line and a trailing comma is fine.
+use std::error::Error;
+use std::fmt;
+
+use super::IntcodeCell;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub token: String, // empty if a value is missing, like in "1,,2"
+    pub offset: usize, // in bytes, from the start of the text
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.token.len() == 0 {
+            return write!(f, "missing value at offset {}", self.offset);
+        }
+        return write!(f, "invalid integer '{}' at offset {}", self.token, self.offset);
+    }
+}
+
+impl Error for ParseError {}
+
+// Comments are replaced by spaces, so offsets still point into the original text
+fn strip_comments(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_comment = false;
+    for c in text.chars() {
+        if c == '#' {
+            in_comment = true;
+        } else if c == '\n' {
+            in_comment = false;
+        }
+        if in_comment {
+            stripped.extend((0..c.len_utf8()).map(|_| ' '));
+        } else {
+            stripped.push(c);
+        }
+    }
+    return stripped;
+}
+
+pub fn try_parse_program_as<T: IntcodeCell>(text: &str) -> Result<Vec<T>, ParseError> {
+    let stripped = strip_comments(text);
+    let parts: Vec<&str> = stripped.split(',').collect();
+    let mut program = Vec::with_capacity(parts.len());
+    let mut offset = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let token = part.trim();
+        let token_offset = offset + (part.len() - part.trim_start().len());
+        offset += part.len() + 1;
+        if token.len() == 0 && i == parts.len() - 1 {
+            break; // trailing comma or empty text
+        }
+        match token.parse::<T>() {
+            Ok(value) => program.push(value),
+            Err(_) => return Err(ParseError { token: token.to_string(), offset: token_offset }),
+        }
+    }
+    return Ok(program);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_whitespace_and_errors() {
+        let program = try_parse_program_as::<i64>("# header\n1, 2,\n  -3 # three\n,4,\n\n").unwrap();
+        assert_eq!(program, vec![1, 2, -3, 4]);
+        assert_eq!(try_parse_program_as::<i64>("").unwrap(), vec![]);
+        assert_eq!(try_parse_program_as::<i64>("1,2,abc,4").unwrap_err(),
+                   ParseError { token: "abc".to_string(), offset: 4 });
+        assert_eq!(try_parse_program_as::<i64>("1,,2").unwrap_err().to_string(), "missing value at offset 2");
+    }
+}
@@ -0,0 +1,77 @@
+// Changes to a program that are applied when it is loaded, one `address = value` per line.
+// Several values after the address go to consecutive cells, `#` starts a comment:
+//
+//     # free play (day 13)
+//     0 = 2
+//
+// Day binaries apply the patch files given with `--patch <file>` (see read_patched_program).
+use std::fs;
+
+use super::IntcodeCell;
+
+// Puzzle programs are a few thousand cells long, an address past this is a typo and
+// would make apply() allocate the whole range
+const MAX_ADDRESS: usize = 1 << 20;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Patch {
+    pub writes: Vec<(usize, i64)>,
+}
+
+impl Patch {
+    pub fn parse(text: &str) -> Result<Patch, String> {
+        let mut writes = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.len() == 0 {
+                continue;
+            }
+            let error = |msg: String| format!("line {}: {}", i + 1, msg);
+            let equals = line.find('=').ok_or_else(|| error(format!("Expected 'address = value', got '{}'", line)))?;
+            let address_str = line[..equals].trim();
+            let address = address_str.parse::<usize>().map_err(|_| error(format!("Invalid address '{}'", address_str)))?;
+            let values = line[equals + 1..].split(',').map(|s| s.trim());
+            for (offset, value) in values.enumerate() {
+                let value = value.parse::<i64>().map_err(|_| error(format!("Invalid value '{}'", value)))?;
+                let target = address.checked_add(offset).filter(|&a| a < MAX_ADDRESS)
+                    .ok_or_else(|| error(format!("Address {} + {} is too large (max. {})", address, offset, MAX_ADDRESS - 1)))?;
+                writes.push((target, value));
+            }
+        }
+        return Ok(Patch { writes: writes });
+    }
+
+    pub fn load(filename: &str) -> Result<Patch, String> {
+        let text = fs::read_to_string(filename).map_err(|err| format!("Could not read patch '{}': {}", filename, err))?;
+        return Patch::parse(&text).map_err(|err| format!("{}: {}", filename, err));
+    }
+
+    // Writes past the end make the program longer
+    pub fn apply<T: IntcodeCell>(&self, program: &mut Vec<T>) {
+        for &(address, value) in &self.writes {
+            if address >= program.len() {
+                program.resize(address + 1, T::ZERO);
+            }
+            program[address] = T::from_i64(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_apply() {
+        let patch = Patch::parse("# free play\n0 = 2\n\n5=1, -1 # two cells\n").unwrap();
+        assert_eq!(patch.writes, vec![(0, 2), (5, 1), (6, -1)]);
+        let mut program: Vec<i64> = vec![1, 0, 0, 0, 99];
+        patch.apply(&mut program);
+        assert_eq!(program, vec![2, 0, 0, 0, 99, 1, -1]);
+        assert_eq!(Patch::parse("0 = x").unwrap_err(), "line 1: Invalid value 'x'");
+        assert!(Patch::parse("0 2").is_err());
+        assert_eq!(Patch::parse("1000000000000 = 5").unwrap_err(), "line 1: Address 1000000000000 + 0 is too large (max. 1048575)");
+        assert!(Patch::parse("1048575 = 1, 2").is_err());
+        assert!(Patch::parse(&format!("{} = 1", usize::MAX)).is_err());
+    }
+}
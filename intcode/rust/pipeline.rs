@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+use super::{Cell, Vm, VmState};
+
+pub type PipelineVm = Vm<VecDeque<i64>, VecDeque<i64>>;
+
+// VMs in series, the outputs of every stage are the inputs of the next one. With feedback
+// the outputs of the last stage also go back into the first one (like the amplifiers
+// in day 7), so it runs until every stage terminated. Other wirings can be given as
+// connections, then every output goes to all stages its stage is connected to.
+pub struct Pipeline {
+    pub stages: Vec<PipelineVm>,
+    pub connections: Vec<(usize, usize)>, // (from, to)
+}
+
+impl Pipeline {
+    pub fn new(programs: Vec<Vec<Cell>>) -> Pipeline {
+        let count = programs.len();
+        return Pipeline {
+            stages: programs.into_iter().map(|program| Vm::new(program)).collect(),
+            connections: (1..count).map(|i| (i - 1, i)).collect(),
+        };
+    }
+
+    pub fn copies(program: &Vec<Cell>, count: usize) -> Pipeline {
+        return Pipeline::new(vec![program.clone(); count]);
+    }
+
+    pub fn with_feedback(mut self) -> Pipeline {
+        let last = self.stages.len() - 1;
+        self.connections.push((last, 0));
+        return self;
+    }
+
+    pub fn with_connections(mut self, connections: &[(usize, usize)]) -> Pipeline {
+        let count = self.stages.len();
+        assert!(connections.iter().all(|&(from, to)| from < count && to < count), "Connection to a stage that doesn't exist");
+        self.connections = connections.to_vec();
+        return self;
+    }
+
+    // One value per stage that it reads before anything else, e.g. phase settings
+    pub fn with_first_inputs(mut self, values: &[i64]) -> Pipeline {
+        self.push_first_inputs(values);
+        return self;
+    }
+
+    pub fn push_first_inputs(&mut self, values: &[i64]) {
+        assert!(values.len() == self.stages.len(), "Expected {} first inputs, got {}", self.stages.len(), values.len());
+        for (stage, &value) in self.stages.iter_mut().zip(values) {
+            stage.input_source.push_back(value);
+        }
+    }
+
+    // Loads the program into every stage again and drops pending inputs and outputs,
+    // so the same pipeline can be run many times (e.g. for every phase setting)
+    pub fn reset(&mut self, program: &[Cell]) {
+        for stage in self.stages.iter_mut() {
+            stage.reset(program);
+            stage.input_source.clear();
+            stage.output_sink.clear();
+        }
+    }
+
+    // Feeds the inputs to the first stage and runs the stages in turn until all of them
+    // terminated. Returns everything the last stage output (in feedback mode these are
+    // the values that went around again, the last one is usually the answer).
+    // Values sent to a stage that already terminated are never read.
+    // Panics if the stages are all waiting for input that never comes.
+    pub fn run(&mut self, inputs: &[i64]) -> Vec<i64> {
+        self.stages[0].input_source.extend(inputs);
+        let count = self.stages.len();
+        let mut outputs = Vec::new();
+        loop {
+            let mut progress = false;
+            for i in 0..count {
+                if self.stages[i].state == VmState::Terminated {
+                    continue;
+                }
+                let steps = self.stages[i].steps;
+                self.stages[i].run();
+                progress = progress || self.stages[i].steps != steps;
+                let values: Vec<i64> = self.stages[i].output_sink.drain(..).collect();
+                if i == count - 1 {
+                    outputs.extend(values.iter().cloned());
+                }
+                for &(_, to) in self.connections.iter().filter(|&&(from, _)| from == i) {
+                    self.stages[to].input_source.extend(values.iter().cloned());
+                }
+            }
+            if self.stages.iter().all(|stage| stage.state == VmState::Terminated) {
+                return outputs;
+            }
+            if !progress {
+                let states: Vec<String> = self.stages.iter().map(|stage| stage.to_string()).collect();
+                panic!("Pipeline is stuck:\n  {}", states.join("\n  "));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_program;
+
+    #[test]
+    fn series_and_feedback() {
+        let program = parse_program("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        let mut series = Pipeline::copies(&program, 5).with_first_inputs(&[4, 3, 2, 1, 0]);
+        assert_eq!(series.run(&[0]), vec![43210]);
+
+        let program = parse_program("3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5");
+        let mut feedback = Pipeline::copies(&program, 5).with_feedback().with_first_inputs(&[9, 8, 7, 6, 5]);
+        assert_eq!(feedback.run(&[0]).last(), Some(&139629729));
+        feedback.reset(&program);
+        feedback.push_first_inputs(&[9, 8, 7, 6, 5]);
+        assert_eq!(feedback.run(&[0]).last(), Some(&139629729));
+    }
+
+    #[test]
+    fn connections() {
+        // every stage outputs 10 * signal + 5 - phase
+        let program = parse_program("3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0");
+        // the first stage feeds both others, the last one only reads the signal from the first
+        let mut fork = Pipeline::copies(&program, 3).with_connections(&[(0, 1), (0, 2), (1, 2)]).with_first_inputs(&[4, 2, 3]);
+        assert_eq!(fork.run(&[0]), vec![12]);
+        assert_eq!(fork.stages[2].input_source, vec![13]);
+    }
+
+    #[test]
+    fn stuck() {
+        // the first stage halts without output, the second one waits for it forever
+        let mut pipeline = Pipeline::new(vec![parse_program("99"), parse_program("3,0,99")]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pipeline.run(&[])));
+        assert!(result.is_err());
+        assert_eq!(pipeline.stages[0].steps, 1);
+    }
+}
@@ -0,0 +1,252 @@
+// Directives for the assembler, expanded before it sees the source:
+//
+//   %include "util.asm"     ; relative to the including file, every file only once
+//   %include <stack.asm>    ; from the standard library, see STDLIB
+//   %define SCREEN 1000     ; constant, can be used wherever a label can
+//   %macro inc addr         ; parameters are replaced by the arguments of an invocation
+//   @again: add addr, 1, addr ; labels starting with @ are local to one expansion
+//   %endmacro
+//           inc [counter]   ; invoked like an instruction
+//   %export print_number    ; labels other objects can use when linking (see link.rs)
+//   %import print_number    ; label that comes from another object
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+// Shipped with the assembler, so programs can include it from anywhere
+pub const STDLIB: [(&'static str, &'static str); 4] = [
+    ("stack.asm", include_str!("stdlib/stack.asm")),
+    ("math.asm", include_str!("stdlib/math.asm")),
+    ("memcpy.asm", include_str!("stdlib/memcpy.asm")),
+    ("print_number.asm", include_str!("stdlib/print_number.asm")),
+];
+
+const MAX_DEPTH: usize = 64; // of nested includes and macro expansions
+
+// A line as the assembler sees it
+pub struct SourceLine {
+    pub file: String,
+    pub number: usize, // in file
+    pub origin: usize, // line in the main file that caused it (e.g. the %include or macro invocation)
+    pub text: String, // without the comment
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<SourceLine>,
+}
+
+struct Preprocessor {
+    lines: Vec<SourceLine>,
+    macros: HashMap<String, Macro>,
+    constants: HashMap<String, String>,
+    included: HashSet<String>,
+    defining: Option<(String, Macro)>,
+    expansions: usize,
+    exports: Vec<String>,
+    imports: HashSet<String>,
+}
+
+pub struct Preprocessed {
+    pub lines: Vec<SourceLine>,
+    pub constants: HashMap<String, String>,
+    pub exports: Vec<String>,
+    pub imports: HashSet<String>,
+}
+
+fn is_identifier_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_' || c == '.';
+}
+
+// Replaces whole identifiers and turns @label into a name unique to the expansion
+fn substitute(text: &str, args: &HashMap<&str, &str>, local_prefix: &str) -> String {
+    let mut result = String::new();
+    let mut word = String::new();
+    let mut local = false;
+    for c in text.chars().chain(Some(' ')) {
+        if is_identifier_char(c) {
+            word.push(c);
+            continue;
+        }
+        if word.len() > 0 {
+            if local {
+                result.push_str(local_prefix);
+                result.push_str(&word);
+            } else {
+                result.push_str(args.get(word.as_str()).cloned().unwrap_or(&word));
+            }
+            word.clear();
+        } else if local {
+            result.push('@');
+        }
+        local = c == '@';
+        if !local {
+            result.push(c);
+        }
+    }
+    result.pop(); // the space that was added
+    return result;
+}
+
+// "a:  b: rest" -> (["a:", "b:"], "rest")
+fn split_labels(text: &str) -> (Vec<&str>, &str) {
+    let mut labels = Vec::new();
+    let mut rest = text;
+    while let Some(colon) = rest.find(':') {
+        labels.push(&rest[..colon + 1]);
+        rest = rest[colon + 1..].trim();
+    }
+    return (labels, rest);
+}
+
+impl Preprocessor {
+    fn process_file(&mut self, source: &str, file: &str, origin: Option<usize>, depth: usize) -> Result<(), String> {
+        for (i, raw_line) in source.lines().enumerate() {
+            let text = match raw_line.find(';') {
+                Some(end) => &raw_line[..end],
+                None => raw_line,
+            }.trim();
+            let line = SourceLine { file: file.to_string(), number: i + 1, origin: origin.unwrap_or(i + 1), text: text.to_string() };
+            self.process_line(line, depth)?;
+        }
+        if let Some((ref name, _)) = self.defining {
+            return Err(format!("{}: %macro {} is missing its %endmacro", file, name));
+        }
+        return Ok(());
+    }
+
+    fn process_line(&mut self, line: SourceLine, depth: usize) -> Result<(), String> {
+        let error = |msg: String| format!("{}:{}: {}", line.file, line.number, msg);
+        let (directive, rest) = match line.text.find(char::is_whitespace) {
+            Some(i) => (&line.text[..i], line.text[i..].trim()),
+            None => (&line.text[..], ""),
+        };
+        if self.defining.is_some() {
+            if directive == "%endmacro" {
+                let (name, definition) = self.defining.take().unwrap();
+                self.macros.insert(name, definition);
+            } else if directive == "%macro" {
+                return Err(error("Macros can't be defined inside macros".to_string()));
+            } else {
+                self.defining.as_mut().unwrap().1.body.push(line);
+            }
+            return Ok(());
+        }
+        match directive {
+            "%macro" => {
+                let (name, params) = match rest.find(char::is_whitespace) {
+                    Some(i) => (&rest[..i], rest[i..].trim()),
+                    None => (rest, ""),
+                };
+                if name.len() == 0 || !name.chars().all(is_identifier_char) {
+                    return Err(error(format!("Invalid macro name '{}'", name)));
+                }
+                if self.macros.contains_key(name) {
+                    return Err(error(format!("Macro '{}' defined twice", name)));
+                }
+                let params: Vec<String> = params.split(',').map(|p| p.trim().to_string()).filter(|p| p.len() > 0).collect();
+                self.defining = Some((name.to_string(), Macro { params: params, body: Vec::new() }));
+            },
+            "%endmacro" => return Err(error("%endmacro without %macro".to_string())),
+            "%define" => {
+                let (name, value) = match rest.find(char::is_whitespace) {
+                    Some(i) => (&rest[..i], rest[i..].trim()),
+                    None => return Err(error("Expected '%define NAME value'".to_string())),
+                };
+                if self.constants.insert(name.to_string(), value.to_string()).is_some() {
+                    return Err(error(format!("Constant '{}' defined twice", name)));
+                }
+            },
+            "%export" | "%import" => {
+                for name in rest.split(',').map(|n| n.trim()) {
+                    if name.len() == 0 || !name.chars().all(is_identifier_char) {
+                        return Err(error(format!("Invalid label '{}'", name)));
+                    }
+                    if directive == "%export" {
+                        self.exports.push(name.to_string());
+                    } else {
+                        self.imports.insert(name.to_string());
+                    }
+                }
+            },
+            "%include" => {
+                if depth >= MAX_DEPTH {
+                    return Err(error("Includes are nested too deep".to_string()));
+                }
+                let (key, source) = if rest.starts_with('<') && rest.ends_with('>') {
+                    let name = &rest[1..rest.len() - 1];
+                    match STDLIB.iter().find(|&&(file, _)| file == name) {
+                        Some(&(_, source)) => (format!("<{}>", name), source.to_string()),
+                        None => return Err(error(format!("No '{}' in the standard library", name))),
+                    }
+                } else if rest.len() >= 2 && rest.starts_with('"') && rest.ends_with('"') {
+                    let dir = Path::new(&line.file).parent().unwrap_or(Path::new(""));
+                    let path = dir.join(&rest[1..rest.len() - 1]).to_string_lossy().to_string();
+                    let source = fs::read_to_string(&path).map_err(|err| error(format!("Could not read '{}': {}", path, err)))?;
+                    (path, source)
+                } else {
+                    return Err(error("Expected %include \"file\" or %include <file>".to_string()));
+                };
+                if self.included.insert(key.clone()) {
+                    self.process_file(&source, &key, Some(line.origin), depth + 1)?;
+                }
+            },
+            _ if directive.starts_with('%') => return Err(error(format!("Unknown directive '{}'", directive))),
+            _ => {
+                let (labels, rest) = split_labels(&line.text);
+                let name = rest.split(char::is_whitespace).next().unwrap();
+                if !self.macros.contains_key(name) {
+                    self.lines.push(line);
+                    return Ok(());
+                }
+                if depth >= MAX_DEPTH {
+                    return Err(error(format!("Macro '{}' is expanded recursively", name)));
+                }
+                let args: Vec<&str> = rest[name.len()..].split(',').map(|a| a.trim()).filter(|a| a.len() > 0).collect();
+                let expanded: Vec<SourceLine> = {
+                    let definition = &self.macros[name];
+                    if args.len() != definition.params.len() {
+                        return Err(error(format!("{} takes {} arguments, got {}", name, definition.params.len(), args.len())));
+                    }
+                    let arg_map: HashMap<&str, &str> = definition.params.iter().map(|p| p.as_str()).zip(args.iter().cloned()).collect();
+                    let local_prefix = format!("{}.{}.", name, self.expansions);
+                    definition.body.iter().map(|body_line| SourceLine {
+                        file: body_line.file.clone(),
+                        number: body_line.number,
+                        origin: line.origin,
+                        text: substitute(&body_line.text, &arg_map, &local_prefix),
+                    }).collect()
+                };
+                self.expansions += 1;
+                if labels.len() > 0 {
+                    self.lines.push(SourceLine { file: line.file.clone(), number: line.number, origin: line.origin, text: labels.join(" ") });
+                }
+                for expanded_line in expanded {
+                    self.process_line(expanded_line, depth + 1)?;
+                }
+            },
+        }
+        return Ok(());
+    }
+}
+
+pub fn preprocess(source: &str, filename: &str) -> Result<Preprocessed, String> {
+    let mut preprocessor = Preprocessor {
+        lines: Vec::new(),
+        macros: HashMap::new(),
+        constants: HashMap::new(),
+        included: HashSet::new(),
+        defining: None,
+        expansions: 0,
+        exports: Vec::new(),
+        imports: HashSet::new(),
+    };
+    preprocessor.included.insert(filename.to_string());
+    preprocessor.process_file(source, filename, None, 0)?;
+    return Ok(Preprocessed {
+        lines: preprocessor.lines,
+        constants: preprocessor.constants,
+        exports: preprocessor.exports,
+        imports: preprocessor.imports,
+    });
+}
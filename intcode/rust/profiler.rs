@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use super::OpCode;
+
+const HOT_ADDRESS_COUNT: usize = 10;
+// Addresses above this are counted in a HashMap, so code at a huge address (which the
+// sparse and paged tapes allow) doesn't allocate everything below it
+const DENSE_ADDRESS_COUNT: usize = 1 << 16;
+
+#[derive(Clone)]
+pub struct Profiler {
+    pub op_counts: HashMap<OpCode, u64>,
+    pub address_counts: Vec<u64>, // address -> number of instructions executed there
+    pub far_address_counts: HashMap<usize, u64>,
+    pub total: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        return Profiler {
+            op_counts: HashMap::new(),
+            address_counts: Vec::new(),
+            far_address_counts: HashMap::new(),
+            total: 0,
+        };
+    }
+
+    pub fn record(&mut self, address: usize, op_code: OpCode) {
+        *self.op_counts.entry(op_code).or_insert(0) += 1;
+        if address < DENSE_ADDRESS_COUNT {
+            if address >= self.address_counts.len() {
+                self.address_counts.resize(address + 1, 0);
+            }
+            self.address_counts[address] += 1;
+        } else {
+            *self.far_address_counts.entry(address).or_insert(0) += 1;
+        }
+        self.total += 1;
+    }
+
+    fn percent(&self, count: u64) -> f64 {
+        return count as f64 / self.total as f64 * 100.0;
+    }
+
+    pub fn hot_addresses(&self, count: usize) -> Vec<(usize, u64)> {
+        let mut addresses: Vec<(usize, u64)> = self.address_counts.iter().cloned().enumerate()
+            .filter(|&(_, n)| n > 0).chain(self.far_address_counts.iter().map(|(&a, &n)| (a, n))).collect();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addresses.truncate(count);
+        return addresses;
+    }
+
+    pub fn print_report(&self) {
+        eprintln!("Profile: {} instructions executed", self.total);
+        if self.total == 0 {
+            return;
+        }
+
+        let mut ops: Vec<(&OpCode, &u64)> = self.op_counts.iter().collect();
+        ops.sort_by(|a, b| b.1.cmp(a.1).then(format!("{:?}", a.0).cmp(&format!("{:?}", b.0))));
+        eprintln!("Opcodes:");
+        for (op_code, count) in ops {
+            eprintln!("  {:<20} {:>12} {:>6.2}%", format!("{:?}", op_code), count, self.percent(*count));
+        }
+
+        eprintln!("Hot addresses:");
+        for (address, count) in self.hot_addresses(HOT_ADDRESS_COUNT) {
+            eprintln!("  {:>8} {:>12} {:>6.2}%", address, count, self.percent(count));
+        }
+    }
+}
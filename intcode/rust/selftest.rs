@@ -0,0 +1,341 @@
+// Conformance tests: the examples from the puzzle descriptions of days 2, 5 and 9 plus the
+// relative mode tests that went around on reddit (those catch most relative base bugs).
+// Run them with `intcode selftest` or as unit tests with `rustc --test main.rs`.
+use std::collections::VecDeque;
+use std::panic;
+
+use super::{cell_to_i64, parse_program, Cell, Limits, MemoryBackend, Vm, VmState};
+
+pub struct TestCase {
+    pub name: &'static str,
+    pub program: &'static str,
+    pub inputs: &'static [i64],
+    pub outputs: &'static [i64],
+    pub memory: &'static [i64], // expected start of the memory after halting, empty to skip
+}
+
+const QUINE: &'static str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+const DAY5_LARGER: &'static str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,\
+    1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+pub const TEST_CASES: &'static [TestCase] = &[
+    TestCase { name: "day 2 add", program: "1,0,0,0,99", inputs: &[], outputs: &[], memory: &[2, 0, 0, 0, 99] },
+    TestCase { name: "day 2 mul", program: "2,3,0,3,99", inputs: &[], outputs: &[], memory: &[2, 3, 0, 6, 99] },
+    TestCase { name: "day 2 mul past end", program: "2,4,4,5,99,0", inputs: &[], outputs: &[],
+        memory: &[2, 4, 4, 5, 99, 9801] },
+    TestCase { name: "day 2 self-modifying", program: "1,1,1,4,99,5,6,0,99", inputs: &[], outputs: &[],
+        memory: &[30, 1, 1, 4, 2, 5, 6, 0, 99] },
+    TestCase { name: "day 2 example", program: "1,9,10,3,2,3,11,0,99,30,40,50", inputs: &[], outputs: &[],
+        memory: &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50] },
+    TestCase { name: "day 5 echo", program: "3,0,4,0,99", inputs: &[1234], outputs: &[1234], memory: &[] },
+    TestCase { name: "day 5 immediate mode", program: "1002,4,3,4,33", inputs: &[], outputs: &[],
+        memory: &[1002, 4, 3, 4, 99] },
+    TestCase { name: "day 5 negative immediate", program: "1101,100,-1,4,0", inputs: &[], outputs: &[],
+        memory: &[1101, 100, -1, 4, 99] },
+    TestCase { name: "day 5 equal (position), 8", program: "3,9,8,9,10,9,4,9,99,-1,8", inputs: &[8], outputs: &[1], memory: &[] },
+    TestCase { name: "day 5 equal (position), 7", program: "3,9,8,9,10,9,4,9,99,-1,8", inputs: &[7], outputs: &[0], memory: &[] },
+    TestCase { name: "day 5 less (position), 5", program: "3,9,7,9,10,9,4,9,99,-1,8", inputs: &[5], outputs: &[1], memory: &[] },
+    TestCase { name: "day 5 less (position), 8", program: "3,9,7,9,10,9,4,9,99,-1,8", inputs: &[8], outputs: &[0], memory: &[] },
+    TestCase { name: "day 5 equal (immediate), 8", program: "3,3,1108,-1,8,3,4,3,99", inputs: &[8], outputs: &[1], memory: &[] },
+    TestCase { name: "day 5 equal (immediate), 9", program: "3,3,1108,-1,8,3,4,3,99", inputs: &[9], outputs: &[0], memory: &[] },
+    TestCase { name: "day 5 less (immediate), -3", program: "3,3,1107,-1,8,3,4,3,99", inputs: &[-3], outputs: &[1], memory: &[] },
+    TestCase { name: "day 5 less (immediate), 10", program: "3,3,1107,-1,8,3,4,3,99", inputs: &[10], outputs: &[0], memory: &[] },
+    TestCase { name: "day 5 jump (position), 0", program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", inputs: &[0], outputs: &[0], memory: &[] },
+    TestCase { name: "day 5 jump (position), 5", program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", inputs: &[5], outputs: &[1], memory: &[] },
+    TestCase { name: "day 5 jump (immediate), 0", program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1", inputs: &[0], outputs: &[0], memory: &[] },
+    TestCase { name: "day 5 jump (immediate), 3", program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1", inputs: &[3], outputs: &[1], memory: &[] },
+    TestCase { name: "day 5 larger example, 7", program: DAY5_LARGER, inputs: &[7], outputs: &[999], memory: &[] },
+    TestCase { name: "day 5 larger example, 8", program: DAY5_LARGER, inputs: &[8], outputs: &[1000], memory: &[] },
+    TestCase { name: "day 5 larger example, 9", program: DAY5_LARGER, inputs: &[9], outputs: &[1001], memory: &[] },
+    TestCase { name: "day 9 quine", program: QUINE, inputs: &[],
+        outputs: &[109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99], memory: &[] },
+    TestCase { name: "day 9 16-digit output", program: "1102,34915192,34915192,7,4,7,99,0", inputs: &[],
+        outputs: &[1219070632396864], memory: &[] },
+    TestCase { name: "day 9 large number", program: "104,1125899906842624,99", inputs: &[],
+        outputs: &[1125899906842624], memory: &[] },
+    TestCase { name: "relative base, position", program: "109,1,9,2,204,-6,99", inputs: &[], outputs: &[204], memory: &[] },
+    TestCase { name: "relative base, immediate", program: "109,1,109,9,204,-6,99", inputs: &[], outputs: &[204], memory: &[] },
+    TestCase { name: "relative base, relative", program: "109,1,209,-1,204,-106,99", inputs: &[], outputs: &[204], memory: &[] },
+    TestCase { name: "relative input, position", program: "109,1,3,3,204,2,99", inputs: &[42], outputs: &[42], memory: &[] },
+    TestCase { name: "relative input, relative", program: "109,1,203,2,204,2,99", inputs: &[42], outputs: &[42], memory: &[] },
+    TestCase { name: "negative relative base, position", program: "109,-1,4,1,99", inputs: &[], outputs: &[-1], memory: &[] },
+    TestCase { name: "negative relative base, immediate", program: "109,-1,104,1,99", inputs: &[], outputs: &[1], memory: &[] },
+    TestCase { name: "negative relative base, relative", program: "109,-1,204,1,99", inputs: &[], outputs: &[109], memory: &[] },
+];
+
+// Runs the program until it halts (or gives up after max_steps) and compares the outputs
+// and the start of the memory
+pub fn check(program: Vec<Cell>, inputs: &[i64], expected_outputs: &[i64], expected_memory: &[i64],
+             max_steps: u64) -> Result<(), String> {
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program);
+    vm.set_limits(Limits { max_steps: Some(max_steps), ..Limits::default() });
+    vm.input_source.extend(inputs.iter());
+    let state = vm.run();
+    if state != VmState::Terminated {
+        return Err(format!("Program did not halt: {:?}", state));
+    }
+    let outputs: Vec<i64> = vm.output_sink.iter().cloned().collect();
+    if outputs != expected_outputs {
+        return Err(format!("Expected outputs {:?}, got {:?}", expected_outputs, outputs));
+    }
+    let memory: Vec<i64> = (0..expected_memory.len()).map(|i| cell_to_i64(vm.read_mem(i))).collect();
+    if memory != expected_memory {
+        return Err(format!("Expected memory {:?}, got {:?}", expected_memory, memory));
+    }
+    return Ok(());
+}
+
+pub fn run_case(case: &TestCase) -> Result<(), String> {
+    return check(parse_program(case.program), case.inputs, case.outputs, case.memory, 100000);
+}
+
+// Panics count as failures too, so one broken case doesn't hide the others
+pub fn run_all() -> Vec<(&'static str, Result<(), String>)> {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| ())); // the message ends up in the result instead
+    let results = TEST_CASES.iter().map(|case| {
+        let result = match panic::catch_unwind(|| run_case(case)) {
+            Ok(result) => result,
+            Err(payload) => {
+                let message = payload.downcast_ref::<String>().cloned()
+                    .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                Err(format!("Panicked: {}", message))
+            }
+        };
+        return (case.name, result);
+    }).collect();
+    panic::set_hook(default_hook);
+    return results;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use super::*;
+    use super::super::{run, OpCode, RunResult, UninitializedRead, VmError, VmObserver};
+
+    #[test]
+    fn all_cases_pass() {
+        for case in TEST_CASES {
+            if let Err(err) = run_case(case) {
+                panic!("{}: {}", case.name, err);
+            }
+        }
+    }
+
+    #[test]
+    fn quine_outputs_itself() {
+        let quine = parse_program(QUINE);
+        let outputs = run(&quine, &[]).unwrap();
+        assert_eq!(outputs, quine.iter().map(|&v| cell_to_i64(v)).collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn run_stops_without_input() {
+        assert_eq!(run(&parse_program("3,0,4,0,99"), &[42]), Ok(vec![42]));
+        assert_eq!(run(&parse_program("3,0,4,0,99"), &[]), Err(RunResult::NeedsInput));
+    }
+
+    #[test]
+    fn faults_are_reported() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("1101,1,2,7,1105,1,9,0,0,77"));
+        match vm.try_run() {
+            RunResult::Faulted(fault) => {
+                assert_eq!(fault.error, VmError::UnknownOpCode("77".to_string()));
+                assert_eq!(fault.ip, 9);
+                assert_eq!(fault.recent_trace, vec![0, 4]);
+            },
+            result => panic!("Expected a fault, got {:?}", result),
+        }
+        assert_eq!(vm.state, VmState::Faulted);
+    }
+
+    #[test]
+    fn negative_relative_address_faults() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("109,-5,204,1,99"));
+        match vm.try_run() {
+            RunResult::Faulted(fault) => assert_eq!(fault.error, VmError::InvalidAddress(-4)),
+            result => panic!("Expected a fault, got {:?}", result),
+        }
+        assert_eq!(vm.relative_base, -5);
+    }
+
+    #[test]
+    fn waits_for_input() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("3,0,4,0,99"));
+        assert_eq!(vm.run(), VmState::WaitForInput);
+        assert_eq!(vm.to_string(), "waiting for input at ip 0 (rb 0, 0 steps, 0 inputs queued, 0 outputs)");
+        vm.input_source.push_back(5);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink.pop_front(), Some(5));
+    }
+
+    #[test]
+    fn run_for_budget() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("1105,1,0"));
+        assert_eq!(vm.run_for(10), (10, true));
+        assert_eq!((vm.state, vm.steps), (VmState::Running, 10));
+        assert_eq!(vm.run_for(5), (5, true));
+
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("1101,1,2,5,99,0"));
+        assert_eq!(vm.run_for(100), (2, false));
+        assert_eq!((vm.state, vm.read_mem(5)), (VmState::Terminated, 3));
+        assert_eq!(vm.run_for(100), (0, false));
+
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("1101,1,2,7,3,7,99,0"));
+        assert_eq!(vm.run_for(100), (1, false));
+        assert_eq!(vm.state, VmState::WaitForInput);
+    }
+
+    #[test]
+    fn reset() {
+        // arb 10; add 2, 3, [rb+0]; out [rb+0]
+        let program = parse_program("109,10,21101,2,3,0,204,0,99,0,0");
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program.clone());
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!((vm.read_mem(10), vm.relative_base, vm.steps), (5, 10, 4));
+        vm.reset(&program);
+        assert_eq!((vm.instruction_pointer, vm.relative_base, vm.state, vm.steps), (0, 0, VmState::NotStarted, 0));
+        assert_eq!(vm.memory_image(), "109,10,21101,2,3,0,204,0,99,0,0");
+        assert_eq!(vm.output_sink, vec![5]); // sinks are kept
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!((vm.output_sink.clone(), vm.steps), (VecDeque::from(vec![5, 5]), 4));
+
+        // the output count starts over, a limit that was hit is cleared
+        vm.set_limits(Limits { max_outputs: Some(1), ..Limits::default() });
+        vm.reset(&program);
+        assert_eq!(vm.run(), VmState::Terminated);
+        vm.set_limits(Limits { max_outputs: Some(0), ..Limits::default() });
+        vm.reset(&program);
+        assert_eq!(vm.run(), VmState::LimitExceeded);
+        vm.set_limits(Limits { max_outputs: Some(1), ..Limits::default() });
+        vm.reset(&program);
+        assert_eq!((vm.state, vm.limit_exceeded), (VmState::NotStarted, None));
+        assert_eq!(vm.run(), VmState::Terminated);
+    }
+
+    #[test]
+    fn run_until_output() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("104,1,104,2,3,9,4,9,99,0"));
+        assert_eq!(vm.run_until_output(), Some(1));
+        assert_eq!((vm.instruction_pointer, vm.state), (2, VmState::Running));
+        assert_eq!(vm.run_until_output(), Some(2));
+        assert_eq!(vm.run_until_output(), None);
+        assert_eq!(vm.state, VmState::WaitForInput);
+        vm.input_source.push_back(7);
+        assert_eq!(vm.run_until_output(), Some(7));
+        assert_eq!(vm.run_until_output(), None);
+        assert_eq!(vm.state, VmState::Terminated);
+        assert!(vm.output_sink.is_empty()); // returned instead of written
+    }
+
+    #[test]
+    fn interrupt() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("1105,1,0"));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        // pauses on every third call
+        vm.set_interrupt(10, move || counter.fetch_add(1, Ordering::SeqCst) % 3 == 2);
+        assert_eq!(vm.run(), VmState::Paused);
+        assert_eq!((vm.steps, calls.load(Ordering::SeqCst)), (30, 3));
+        assert_eq!(vm.run(), VmState::Paused);
+        assert_eq!((vm.steps, calls.load(Ordering::SeqCst)), (60, 6));
+        vm.clear_interrupt();
+        assert_eq!(vm.run_for(100), (100, true));
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+    }
+
+    #[test]
+    fn clones_are_independent() {
+        // outputs 1, then the input plus 1
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("104,1,3,11,1001,11,1,11,4,11,99,0"));
+        assert_eq!(vm.run(), VmState::WaitForInput);
+        let mut fork = vm.clone();
+        assert_eq!((fork.instruction_pointer, fork.steps, fork.output_sink.clone()), (2, 1, VecDeque::from(vec![1])));
+        fork.input_source.push_back(10);
+        fork.output_sink.clear();
+        assert_eq!(fork.run(), VmState::Terminated);
+        assert_eq!((fork.output_sink.clone(), fork.read_mem(11)), (VecDeque::from(vec![11]), 11));
+        // the original is still waiting, with its own memory and output
+        assert_eq!((vm.state, vm.steps, vm.read_mem(11), vm.output_sink.clone()), (VmState::WaitForInput, 1, 0, VecDeque::from(vec![1])));
+        vm.input_source.push_back(20);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink, vec![1, 21]);
+        assert_eq!(fork.read_mem(11), 11);
+    }
+
+    // Logs every callback, shared with the test since the vm owns the observer
+    struct LogObserver {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl VmObserver for LogObserver {
+        fn on_step(&mut self, address: usize, op_code: OpCode) {
+            self.log.lock().unwrap().push(format!("step {} {:?}", address, op_code));
+        }
+
+        fn on_input(&mut self, value: i64) {
+            self.log.lock().unwrap().push(format!("in {}", value));
+        }
+
+        fn on_output(&mut self, value: i64) {
+            self.log.lock().unwrap().push(format!("out {}", value));
+        }
+
+        fn on_halt(&mut self) {
+            self.log.lock().unwrap().push("halt".to_string());
+        }
+    }
+
+    #[test]
+    fn observer() {
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("3,7,4,7,99,0,0,0"));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        vm.set_observer(LogObserver { log: log.clone() });
+        assert_eq!(vm.run(), VmState::WaitForInput);
+        assert!(log.lock().unwrap().is_empty()); // nothing happened yet
+        vm.input_source.push_back(5);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(*log.lock().unwrap(), vec!["step 0 Input", "in 5", "step 2 Output", "out 5", "step 4 Terminate", "halt"]);
+        vm.clear_observer();
+        vm.reset(&parse_program("99"));
+        vm.run();
+        assert_eq!(log.lock().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn read_audit() {
+        // out [20]; add 1, 2, [21]; out [21]; out [20]
+        let program = parse_program("4,20,1101,1,2,21,4,21,4,20,99");
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program.clone());
+        vm.enable_read_audit(false);
+        assert_eq!(vm.run(), VmState::Terminated);
+        assert_eq!(vm.output_sink, vec![0, 3, 0]);
+        let reads = &vm.read_audit.as_ref().unwrap().reads;
+        assert_eq!(reads, &vec![UninitializedRead { ip: 0, address: 20 }, UninitializedRead { ip: 8, address: 20 }]);
+
+        let mut strict: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program);
+        strict.enable_read_audit(true);
+        match strict.try_run() {
+            RunResult::Faulted(fault) => assert_eq!((fault.error, fault.ip), (VmError::UninitializedRead(20), 0)),
+            result => panic!("Expected a fault, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn metrics() {
+        // echoes two inputs
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(parse_program("3,9,4,9,3,9,4,9,99,0"));
+        vm.enable_metrics();
+        for &value in &[7, 8] {
+            assert_eq!(vm.run(), VmState::WaitForInput);
+            vm.input_source.push_back(value);
+        }
+        assert_eq!(vm.run(), VmState::Terminated);
+        let metrics = vm.metrics.as_ref().unwrap();
+        assert_eq!((metrics.runs, metrics.steps, metrics.inputs, metrics.outputs), (3, 5, 2, 2));
+        assert!(metrics.elapsed > std::time::Duration::from_secs(0));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use super::annotations::Annotations;
+
+// Where the cells of an assembled program came from. Saved next to the program as
+// <program>.map, one entry per line:
+//   file <source file>
+//   label <address> <name>
+//   line <address> <line number>   (first cell of an instruction or data item)
+//   code <address>                 (instruction start, everything else is data)
+// Without any code entries (a map made only from notes) all cells that decode are code.
+#[derive(Clone, Default, Debug)]
+pub struct SourceMap {
+    pub file: String,
+    pub labels: BTreeMap<usize, String>,
+    pub lines: BTreeMap<usize, usize>,
+    pub code: BTreeSet<usize>,
+    pub comments: BTreeMap<usize, String>, // from the notes, not saved in the map
+}
+
+impl SourceMap {
+    pub fn new(file: &str) -> SourceMap {
+        return SourceMap {
+            file: file.to_string(),
+            labels: BTreeMap::new(),
+            lines: BTreeMap::new(),
+            code: BTreeSet::new(),
+            comments: BTreeMap::new(),
+        };
+    }
+
+    pub fn map_filename(program_filename: &str) -> String {
+        return format!("{}.map", program_filename);
+    }
+
+    pub fn save(&self, filename: &str) {
+        let mut text = format!("file {}\n", self.file);
+        for (address, name) in self.labels.iter() {
+            text.push_str(&format!("label {} {}\n", address, name));
+        }
+        for (address, line) in self.lines.iter() {
+            text.push_str(&format!("line {} {}\n", address, line));
+        }
+        for address in self.code.iter() {
+            text.push_str(&format!("code {}\n", address));
+        }
+        fs::write(filename, text).expect("Could not write source map");
+    }
+
+    pub fn load(filename: &str) -> Result<SourceMap, String> {
+        let text = fs::read_to_string(filename).map_err(|e| format!("Could not read {}: {}", filename, e))?;
+        let mut map = SourceMap::new("");
+        for (i, line) in text.lines().enumerate() {
+            let parts: Vec<&str> = line.splitn(3, ' ').collect();
+            let address = || -> Result<usize, String> {
+                return parts.get(1).and_then(|a| a.parse::<usize>().ok())
+                    .ok_or(format!("{}:{}: invalid address", filename, i + 1));
+            };
+            match (parts[0], parts.len()) {
+                ("", 1) => (),
+                ("file", _) => map.file = line[5..].to_string(),
+                ("label", 3) => { map.labels.insert(address()?, parts[2].to_string()); },
+                ("line", 3) => {
+                    let number = parts[2].parse::<usize>().map_err(|_| format!("{}:{}: invalid line number", filename, i + 1))?;
+                    map.lines.insert(address()?, number);
+                },
+                ("code", 2) => { map.code.insert(address()?); },
+                _ => return Err(format!("{}:{}: invalid entry '{}'", filename, i + 1, line)),
+            }
+        }
+        return Ok(map);
+    }
+
+    // Looks for <program>.map and merges <program>.notes (see annotations.rs) into it
+    pub fn load_for_program(program_filename: &str) -> Option<SourceMap> {
+        let filename = SourceMap::map_filename(program_filename);
+        let map = if fs::metadata(&filename).is_ok() {
+            match SourceMap::load(&filename) {
+                Ok(map) => Some(map),
+                Err(err) => {
+                    eprintln!("Ignoring source map: {}", err);
+                    None
+                },
+            }
+        } else {
+            None
+        };
+        let notes = Annotations::load_for_program(program_filename);
+        if notes.is_empty() {
+            return map;
+        }
+        let mut map = map.unwrap_or(SourceMap::new(""));
+        notes.apply(&mut map);
+        return Some(map);
+    }
+
+    pub fn address_of(&self, label: &str) -> Option<usize> {
+        return self.labels.iter().find(|&(_, name)| name == label).map(|(&address, _)| address);
+    }
+
+    // Address relative to the closest label before it, e.g. "loop+3"
+    pub fn symbolize(&self, address: usize) -> Option<String> {
+        return match self.labels.range(..address + 1).next_back() {
+            Some((&label_address, name)) if label_address == address => Some(name.clone()),
+            Some((&label_address, name)) => Some(format!("{}+{}", name, address - label_address)),
+            None => None,
+        };
+    }
+
+    // e.g. "loop+3 (game.asm:12) ; decrements the lives"
+    pub fn describe(&self, address: usize) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(symbol) = self.symbolize(address) {
+            parts.push(symbol);
+        }
+        if let Some(line) = self.lines.get(&address) {
+            parts.push(format!("({}:{})", self.file, line));
+        }
+        if let Some(comment) = self.comments.get(&address) {
+            parts.push(format!("; {}", comment));
+        }
+        return parts.join(" ");
+    }
+}
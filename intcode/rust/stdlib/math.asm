@@ -0,0 +1,25 @@
+; Small helpers that are missing from the instruction set
+
+%macro mov source, dest
+        add source, 0, dest
+%endmacro
+
+%macro jmp target
+        jt 1, target
+%endmacro
+
+%macro neg value, dest
+        mul value, -1, dest
+%endmacro
+
+; dest = value * 2^count, by doubling count times (count can be a runtime value)
+%macro shl value, count, dest
+        add value, 0, dest
+        add count, 0, [@count]
+@loop:  jf [@count], @done
+        mul dest, 2, dest
+        add [@count], -1, [@count]
+        jt 1, @loop
+@count: data 0
+@done:
+%endmacro
@@ -0,0 +1,17 @@
+; memcpy dest, source, length copies length cells. dest and source are addresses, so
+; `memcpy buffer, [pointer], 3` copies from where pointer points to.
+; Works by modifying its own copy instruction, so it can't be used from read-only code.
+
+%macro memcpy dest, source, length
+        add length, 0, [@count]
+        add source, 0, [@copy+1]
+        add dest, 0, [@copy+3]
+@loop:  jf [@count], @done
+@copy:  add [0], 0, [0]
+        add [@copy+1], 1, [@copy+1]
+        add [@copy+3], 1, [@copy+3]
+        add [@count], -1, [@count]
+        jt 1, @loop
+@count: data 0
+@done:
+%endmacro
@@ -0,0 +1,56 @@
+; Outputs a number as decimal ASCII digits. Include it where it isn't executed (e.g. after
+; the hlt) and call it with the stack from stack.asm, the argument is popped:
+;
+;           push [score]
+;           call print_number
+;
+; Intcode can't divide, so the digits are counted by subtracting powers of ten.
+; The most negative number can't be printed (its negation overflows).
+%include <stack.asm>
+
+print_number:
+        add [rb-2], 0, [pn.value]
+        lt [pn.value], 0, [pn.tmp]
+        jf [pn.tmp], pn.positive
+        out 45                          ; '-'
+        mul [pn.value], -1, [pn.value]
+pn.positive:
+        add pn.powers, 0, [pn.load+1]
+        add 0, 0, [pn.started]
+pn.next_power:
+pn.load:
+        add [0], 0, [pn.power]          ; the address is patched to the current power
+        jf [pn.power], pn.end
+        add 0, 0, [pn.digit]
+pn.count:
+        lt [pn.value], [pn.power], [pn.tmp]
+        jt [pn.tmp], pn.emit
+        mul [pn.power], -1, [pn.tmp]
+        add [pn.value], [pn.tmp], [pn.value]
+        add [pn.digit], 1, [pn.digit]
+        jt 1, pn.count
+pn.emit:
+        add [pn.started], [pn.digit], [pn.tmp]
+        jf [pn.tmp], pn.skip            ; leading zero
+        add 1, 0, [pn.started]
+        add [pn.digit], 48, [pn.tmp]
+        out [pn.tmp]
+pn.skip:
+        add [pn.load+1], 1, [pn.load+1]
+        jt 1, pn.next_power
+pn.end:
+        jt [pn.started], pn.return
+        out 48                          ; the number was 0
+pn.return:
+        add [rb-1], 0, [rb-2]           ; the return address replaces the argument
+        arb -1
+        ret
+
+pn.value:   data 0
+pn.power:   data 0
+pn.digit:   data 0
+pn.started: data 0
+pn.tmp:     data 0
+pn.powers:  data 1000000000000000000, 100000000000000000, 10000000000000000, 1000000000000000
+            data 100000000000000, 10000000000000, 1000000000000, 100000000000, 10000000000
+            data 1000000000, 100000000, 10000000, 1000000, 100000, 10000, 1000, 100, 10, 1, 0
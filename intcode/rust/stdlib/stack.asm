@@ -0,0 +1,39 @@
+; A stack for subroutines, growing upwards from wherever init_stack puts it. The relative
+; base points at the first free cell, so [rb-1] is the top.
+;
+;           init_stack stack
+;           push 42
+;           call print_number
+;           hlt
+;   stack:  data 0
+
+%macro init_stack address
+        arb address
+%endmacro
+
+%macro push value
+        add value, 0, [rb]
+        arb 1
+%endmacro
+
+%macro pop dest
+        arb -1
+        add [rb], 0, dest
+%endmacro
+
+%macro drop count
+        arb -count
+%endmacro
+
+; The return address is pushed, so the arguments are below it: [rb-2] is the last one
+%macro call target
+        add @return, 0, [rb]
+        arb 1
+        jt 1, target
+@return:
+%endmacro
+
+%macro ret
+        arb -1
+        jt 1, [rb]
+%endmacro
@@ -0,0 +1,636 @@
+// Bounded symbolic execution to answer "which inputs make the program output X".
+//
+// Inputs are variables, every cell computed from them holds an expression tree instead
+// of a number. When a jump depends on an input, both ways are explored (breadth first) and
+// the condition is added to the path constraints. At every output the solver tries to
+// find inputs that satisfy the constraints and make the output equal to the target.
+//
+// The solver assigns the inputs in order. Equations that are affine in the input being
+// assigned are solved directly, everything else is enumerated within `input_range`.
+// Paths that use inputs as addresses, instructions or relative base offsets are given up,
+// just like the ones that would fault on the Vm (the program may still output the target
+// on another path, or before the fault).
+use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use super::{Cell, Limits, OpCode, ParamMode, Vm};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SymbolicConfig {
+    pub max_steps: usize, // per path
+    pub max_paths: usize,
+    pub max_inputs: usize,
+    pub input_range: (i64, i64), // inclusive, for inputs that can't be solved for directly
+    pub solver_budget: usize, // evaluations per solver call
+}
+
+impl Default for SymbolicConfig {
+    fn default() -> SymbolicConfig {
+        return SymbolicConfig {
+            max_steps: 100000,
+            max_paths: 2000,
+            max_inputs: 16,
+            input_range: (-1000, 1000),
+            solver_budget: 1000000,
+        };
+    }
+}
+
+enum Kind {
+    Const(Cell),
+    Input(usize),
+    Add(Value, Value),
+    Mul(Value, Value),
+    LessThan(Value, Value),
+    Equals(Value, Value),
+}
+
+struct Node {
+    kind: Kind,
+    max_input: Option<usize>, // highest input index the expression depends on
+}
+
+type Value = Rc<Node>;
+
+fn constant(value: Cell) -> Value {
+    return Rc::new(Node { kind: Kind::Const(value), max_input: None });
+}
+
+fn input(index: usize) -> Value {
+    return Rc::new(Node { kind: Kind::Input(index), max_input: Some(index) });
+}
+
+fn as_const(value: &Value) -> Option<Cell> {
+    return match value.kind {
+        Kind::Const(v) => Some(v),
+        _ => None,
+    };
+}
+
+fn binary(op: OpCode, a: Value, b: Value) -> Value {
+    // Fold constants, so programs that don't touch the inputs stay concrete
+    if let (Some(x), Some(y)) = (as_const(&a), as_const(&b)) {
+        return constant(match op {
+            OpCode::Add => x.wrapping_add(y),
+            OpCode::Mul => x.wrapping_mul(y),
+            OpCode::LessThan => if x < y { 1 } else { 0 },
+            OpCode::Equals => if x == y { 1 } else { 0 },
+            _ => unreachable!(),
+        });
+    }
+    match (op, as_const(&a), as_const(&b)) {
+        (OpCode::Add, Some(0), _) => return b,
+        (OpCode::Add, _, Some(0)) => return a,
+        (OpCode::Mul, Some(1), _) => return b,
+        (OpCode::Mul, _, Some(1)) => return a,
+        (OpCode::Mul, Some(0), _) | (OpCode::Mul, _, Some(0)) => return constant(0),
+        _ => (),
+    }
+    // Constants go to the right of + and *, and "x + 1 + 1" becomes "x + 2" so that
+    // loop counters stay small
+    let (a, b) = if (op == OpCode::Add || op == OpCode::Mul) && as_const(&a).is_some() { (b, a) } else { (a, b) };
+    if let Some(c) = as_const(&b) {
+        let nested = match (op, &a.kind) {
+            (OpCode::Add, &Kind::Add(ref x, ref y)) => as_const(y).map(|d| (x.clone(), d.wrapping_add(c))),
+            (OpCode::Mul, &Kind::Mul(ref x, ref y)) => as_const(y).map(|d| (x.clone(), d.wrapping_mul(c))),
+            _ => None,
+        };
+        if let Some((x, c)) = nested {
+            return binary(op, x, constant(c));
+        }
+    }
+    let max_input = a.max_input.max(b.max_input);
+    let kind = match op {
+        OpCode::Add => Kind::Add(a, b),
+        OpCode::Mul => Kind::Mul(a, b),
+        OpCode::LessThan => Kind::LessThan(a, b),
+        OpCode::Equals => Kind::Equals(a, b),
+        _ => unreachable!(),
+    };
+    return Rc::new(Node { kind: kind, max_input: max_input });
+}
+
+// Expressions are DAGs (x = x + x shares nodes), so results of shared nodes are memoized
+fn eval(value: &Value, inputs: &[Cell], memo: &mut HashMap<usize, Cell>) -> Cell {
+    let result = match value.kind {
+        Kind::Const(v) => return v,
+        Kind::Input(i) => return inputs[i],
+        _ if Rc::strong_count(value) == 1 => None,
+        _ => memo.get(&(&**value as *const Node as usize)).cloned(),
+    };
+    if let Some(result) = result {
+        return result;
+    }
+    let result = match value.kind {
+        Kind::Add(ref a, ref b) => eval(a, inputs, memo).wrapping_add(eval(b, inputs, memo)),
+        Kind::Mul(ref a, ref b) => eval(a, inputs, memo).wrapping_mul(eval(b, inputs, memo)),
+        Kind::LessThan(ref a, ref b) => if eval(a, inputs, memo) < eval(b, inputs, memo) { 1 } else { 0 },
+        Kind::Equals(ref a, ref b) => if eval(a, inputs, memo) == eval(b, inputs, memo) { 1 } else { 0 },
+        Kind::Const(_) | Kind::Input(_) => unreachable!(),
+    };
+    if Rc::strong_count(value) > 1 {
+        memo.insert(&**value as *const Node as usize, result);
+    }
+    return result;
+}
+
+fn evaluate(value: &Value, inputs: &[Cell]) -> Cell {
+    return eval(value, inputs, &mut HashMap::new());
+}
+
+fn collect_inputs(value: &Value, visited: &mut HashSet<usize>, used: &mut Vec<bool>) {
+    if !visited.insert(&**value as *const Node as usize) {
+        return;
+    }
+    match value.kind {
+        Kind::Const(_) => (),
+        Kind::Input(i) => used[i] = true,
+        Kind::Add(ref a, ref b) | Kind::Mul(ref a, ref b) |
+        Kind::LessThan(ref a, ref b) | Kind::Equals(ref a, ref b) => {
+            collect_inputs(a, visited, used);
+            collect_inputs(b, visited, used);
+        },
+    }
+}
+
+// value != 0 if `nonzero`, value == 0 otherwise
+#[derive(Clone)]
+struct Constraint {
+    value: Value,
+    nonzero: bool,
+}
+
+impl Constraint {
+    fn new(value: Value, nonzero: bool) -> Constraint {
+        // "(a < b) == 0" is just "a < b" negated, which keeps it solvable
+        if let Kind::Equals(ref a, ref b) = value.kind {
+            let is_comparison = match a.kind {
+                Kind::LessThan(_, _) | Kind::Equals(_, _) => true,
+                _ => false,
+            };
+            if is_comparison && as_const(b) == Some(0) {
+                return Constraint::new(a.clone(), !nonzero);
+            }
+        }
+        return Constraint { value: value, nonzero: nonzero };
+    }
+
+    fn holds(&self, inputs: &[Cell]) -> bool {
+        return (evaluate(&self.value, inputs) != 0) == self.nonzero;
+    }
+}
+
+enum Solution {
+    Found(Vec<Cell>),
+    Impossible, // at least within input_range
+    Unknown, // ran out of budget
+}
+
+// What a single constraint says about the input that is being assigned
+enum Bound {
+    Exactly(Cell),
+    AtLeast(Cell),
+    AtMost(Cell),
+    Empty,
+    Unknown,
+}
+
+// None on overflow, like the checked_ functions
+fn div_floor(a: Cell, b: Cell) -> Option<Cell> {
+    let q = a.checked_div(b)?;
+    return if (a % b != 0) && ((a < 0) != (b < 0)) { q.checked_sub(1) } else { Some(q) };
+}
+
+fn div_ceil(a: Cell, b: Cell) -> Option<Cell> {
+    let q = a.checked_div(b)?;
+    return if (a % b != 0) && ((a < 0) == (b < 0)) { q.checked_add(1) } else { Some(q) };
+}
+
+// Small values first: 0, 1, -1, 2, -2, ... The values are generated as they are needed,
+// since the range can be much larger than the solver budget.
+struct Candidates {
+    lo: Cell,
+    hi: Cell,
+    magnitude: Option<Cell>, // None when there are no values left
+    negative: bool, // whether -magnitude is next
+}
+
+impl Iterator for Candidates {
+    type Item = Cell;
+
+    fn next(&mut self) -> Option<Cell> {
+        loop {
+            let magnitude = self.magnitude?;
+            if magnitude > self.hi && -magnitude < self.lo {
+                self.magnitude = None;
+                return None;
+            }
+            let value = if self.negative { -magnitude } else { magnitude };
+            if self.negative || magnitude == 0 {
+                self.magnitude = magnitude.checked_add(1);
+                self.negative = false;
+            } else {
+                self.negative = true;
+            }
+            if value >= self.lo && value <= self.hi {
+                return Some(value);
+            }
+        }
+    }
+}
+
+// None if the range can't be enumerated without overflowing
+fn candidates_in(lo: Cell, hi: Cell) -> Option<Candidates> {
+    let start = if lo > 0 { lo } else if hi < 0 { hi.checked_neg()? } else { 0 };
+    return Some(Candidates { lo: lo, hi: hi, magnitude: Some(start), negative: false });
+}
+
+struct Solver<'a> {
+    constraints: Vec<&'a Constraint>,
+    input_count: usize,
+    used: Vec<bool>, // inputs that appear in any constraint, the others are just 0
+    range: (i64, i64),
+    budget: usize,
+    exhausted: bool,
+}
+
+impl<'a> Solver<'a> {
+    // Comparisons between expressions that are affine in input `index` (with the inputs
+    // before it fixed) can be solved directly instead of trying every value
+    fn bound(&mut self, constraint: &Constraint, inputs: &mut Vec<Cell>, index: usize) -> Bound {
+        let (a, b, less_than) = match constraint.value.kind {
+            Kind::Equals(ref a, ref b) if constraint.nonzero => (a, b, false),
+            Kind::LessThan(ref a, ref b) => (a, b, true),
+            _ => return Bound::Unknown,
+        };
+        let mut f = |x: Cell| -> Cell {
+            inputs[index] = x;
+            return evaluate(a, inputs).wrapping_sub(evaluate(b, inputs));
+        };
+        let f0 = f(0);
+        let slope = f(1).wrapping_sub(f0);
+        self.budget = self.budget.saturating_sub(2);
+        // Comparisons are constant almost everywhere, so a flat function tells us nothing
+        if slope == 0 {
+            return Bound::Unknown;
+        }
+        for &x in [2, -1, 1000, -7919].iter() {
+            self.budget = self.budget.saturating_sub(1);
+            if f(x) != f0.wrapping_add(slope.wrapping_mul(x)) {
+                return Bound::Unknown;
+            }
+        }
+        // f(x) = f0 + slope * x, anything that overflows is left to enumeration
+        let neg_f0 = match f0.checked_neg() {
+            Some(neg_f0) => neg_f0,
+            None => return Bound::Unknown,
+        };
+        let bound = if !less_than {
+            match f0.checked_rem(slope) {
+                Some(0) => neg_f0.checked_div(slope).map(Bound::Exactly),
+                Some(_) => Some(Bound::Empty),
+                None => None,
+            }
+        } else if constraint.nonzero {
+            // slope * x <= -f0 - 1
+            neg_f0.checked_sub(1).and_then(|limit| if slope > 0 {
+                div_floor(limit, slope).map(Bound::AtMost)
+            } else {
+                div_ceil(limit, slope).map(Bound::AtLeast)
+            })
+        } else {
+            // slope * x >= -f0
+            if slope > 0 {
+                div_ceil(neg_f0, slope).map(Bound::AtLeast)
+            } else {
+                div_floor(neg_f0, slope).map(Bound::AtMost)
+            }
+        };
+        return bound.unwrap_or(Bound::Unknown);
+    }
+
+    // Sets `exhausted` if the candidates can't be computed without overflowing
+    fn candidates(&mut self, level: &[&Constraint], inputs: &mut Vec<Cell>, index: usize) -> Candidates {
+        let none = Candidates { lo: 0, hi: 0, magnitude: None, negative: false };
+        if !self.used[index] {
+            return candidates_in(0, 0).unwrap();
+        }
+        let mut lo: Option<Cell> = None;
+        let mut hi: Option<Cell> = None;
+        for constraint in level {
+            match self.bound(constraint, inputs, index) {
+                Bound::Exactly(value) => {
+                    lo = Some(value);
+                    hi = Some(value);
+                    break;
+                },
+                Bound::AtLeast(value) => lo = Some(lo.map_or(value, |lo| lo.max(value))),
+                Bound::AtMost(value) => hi = Some(hi.map_or(value, |hi| hi.min(value))),
+                Bound::Empty => return none,
+                Bound::Unknown => (),
+            }
+        }
+        // Open sides are limited to a window as wide as input_range
+        let (range_lo, range_hi) = (self.range.0 as Cell, self.range.1 as Cell);
+        let range = match range_hi.checked_sub(range_lo) {
+            Some(width) => match (lo, hi) {
+                (Some(lo), Some(hi)) => Some((lo, hi)),
+                (Some(lo), None) => lo.checked_add(width).map(|end| (lo, range_hi.max(end))),
+                (None, Some(hi)) => hi.checked_sub(width).map(|start| (range_lo.min(start), hi)),
+                (None, None) => Some((range_lo, range_hi)),
+            },
+            None => None,
+        };
+        match range.and_then(|(lo, hi)| candidates_in(lo, hi)) {
+            Some(candidates) => return candidates,
+            None => {
+                self.exhausted = true;
+                return none;
+            },
+        }
+    }
+
+    fn search(&mut self, inputs: &mut Vec<Cell>, index: usize) -> Option<Vec<Cell>> {
+        if index == self.input_count {
+            return Some(inputs.clone());
+        }
+        // Constraints that can be checked once this input is known
+        let level: Vec<&Constraint> = self.constraints.iter().cloned()
+            .filter(|c| c.value.max_input == Some(index)).collect();
+        for value in self.candidates(&level, inputs, index) {
+            if self.budget == 0 {
+                self.exhausted = true;
+                return None;
+            }
+            self.budget = self.budget.saturating_sub(level.len().max(1));
+            inputs[index] = value;
+            if level.iter().all(|c| c.holds(inputs)) {
+                if let Some(solution) = self.search(inputs, index + 1) {
+                    return Some(solution);
+                }
+                if self.exhausted {
+                    return None;
+                }
+            }
+        }
+        return None;
+    }
+}
+
+fn solve(constraints: &[Constraint], input_count: usize, config: &SymbolicConfig) -> Solution {
+    // Constraints without inputs were decided by constant folding already
+    if constraints.iter().any(|c| c.value.max_input.is_none() && !c.holds(&[])) {
+        return Solution::Impossible;
+    }
+    let mut used = vec![false; input_count];
+    let mut visited = HashSet::new();
+    for constraint in constraints {
+        collect_inputs(&constraint.value, &mut visited, &mut used);
+    }
+    let mut solver = Solver {
+        constraints: constraints.iter().filter(|c| c.value.max_input.is_some()).collect(),
+        input_count: input_count,
+        used: used,
+        range: config.input_range,
+        budget: config.solver_budget,
+        exhausted: false,
+    };
+    let mut inputs: Vec<Cell> = vec![0; input_count];
+    return match solver.search(&mut inputs, 0) {
+        Some(solution) => Solution::Found(solution),
+        None if solver.exhausted => Solution::Unknown,
+        None => Solution::Impossible,
+    };
+}
+
+#[derive(Clone)]
+struct State {
+    program: Rc<Vec<Cell>>,
+    memory: HashMap<usize, Value>, // cells that were written
+    instruction_pointer: usize,
+    relative_base: Cell,
+    input_count: usize,
+    constraints: Vec<Constraint>,
+    steps: usize,
+}
+
+enum StepResult {
+    Continue,
+    Output(Value),
+    Fork(Value, usize), // condition, jump target if it is nonzero
+    Halt,
+    GiveUp, // depends on inputs in a way we can't handle, or faults
+}
+
+impl State {
+    fn get(&self, address: usize) -> Value {
+        if let Some(value) = self.memory.get(&address) {
+            return value.clone();
+        }
+        return constant(if address < self.program.len() { self.program[address] } else { 0 });
+    }
+
+    fn concrete(&self, address: usize) -> Option<Cell> {
+        return as_const(&self.get(address));
+    }
+
+    fn param_address(&self, modes: &[ParamMode], n: usize) -> Option<usize> {
+        let pointer = self.instruction_pointer + n;
+        let address = match modes[n - 1] {
+            ParamMode::Position => self.concrete(pointer)?,
+            ParamMode::Immediate => return Some(pointer),
+            ParamMode::Relative => self.concrete(pointer)?.checked_add(self.relative_base)?,
+        };
+        if address < 0 {
+            return None; // invalid address
+        }
+        return Some(address as usize);
+    }
+
+    fn param(&self, modes: &[ParamMode], n: usize) -> Option<Value> {
+        return self.param_address(modes, n).map(|address| self.get(address));
+    }
+
+    fn step(&mut self, config: &SymbolicConfig) -> StepResult {
+        let raw = match self.concrete(self.instruction_pointer) {
+            Some(raw) => raw,
+            None => return StepResult::GiveUp,
+        };
+        let digits = (raw % 100000) as i64;
+        let op_code = match OpCode::try_read(digits) {
+            Some(op_code) => op_code,
+            None => return StepResult::GiveUp, // unknown opcode
+        };
+        let param_count = op_code.get_param_count();
+        let mut modes = [ParamMode::Position; 3];
+        for i in 0..param_count {
+            modes[i] = match ParamMode::try_read(digits, i + 1) {
+                Some(mode) => mode,
+                None => return StepResult::GiveUp,
+            };
+        }
+        let next = self.instruction_pointer + 1 + param_count;
+        self.steps += 1;
+        macro_rules! try_param {
+            ($e:expr) => { match $e { Some(v) => v, None => return StepResult::GiveUp } };
+        }
+        match op_code {
+            OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals => {
+                let a = try_param!(self.param(&modes, 1));
+                let b = try_param!(self.param(&modes, 2));
+                let address = try_param!(self.param_address(&modes, 3));
+                self.memory.insert(address, binary(op_code, a, b));
+            },
+            OpCode::Input => {
+                if self.input_count == config.max_inputs {
+                    return StepResult::GiveUp;
+                }
+                let address = try_param!(self.param_address(&modes, 1));
+                self.memory.insert(address, input(self.input_count));
+                self.input_count += 1;
+            },
+            OpCode::Output => {
+                let value = try_param!(self.param(&modes, 1));
+                self.instruction_pointer = next;
+                return StepResult::Output(value);
+            },
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let condition = try_param!(self.param(&modes, 1));
+                let target = match as_const(&try_param!(self.param(&modes, 2))) {
+                    Some(target) if target >= 0 => target as usize,
+                    _ => return StepResult::GiveUp, // negative or depends on the inputs
+                };
+                // Normalize to "jump if condition is nonzero"
+                let condition = if op_code == OpCode::JumpIfTrue {
+                    condition
+                } else {
+                    binary(OpCode::Equals, condition, constant(0))
+                };
+                match as_const(&condition) {
+                    Some(0) => (),
+                    Some(_) => {
+                        self.instruction_pointer = target;
+                        return StepResult::Continue;
+                    },
+                    None => {
+                        self.instruction_pointer = next;
+                        return StepResult::Fork(condition, target);
+                    },
+                }
+            },
+            OpCode::AdjustRelativeBase => {
+                let offset = try_param!(as_const(&try_param!(self.param(&modes, 1))));
+                self.relative_base = try_param!(self.relative_base.checked_add(offset));
+            },
+            OpCode::Terminate => return StepResult::Halt,
+            OpCode::Custom(_) | OpCode::HostCall => unreachable!(),
+        }
+        self.instruction_pointer = next;
+        return StepResult::Continue;
+    }
+}
+
+// The output counts even if the program faults afterwards
+fn produces(program: &[Cell], inputs: &[i64], target: i64, max_steps: usize) -> bool {
+    let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program.to_vec());
+    vm.input_source.extend(inputs.iter());
+    vm.set_limits(Limits { max_memory: None, max_steps: Some(max_steps as u64), max_outputs: None });
+    vm.try_run();
+    return vm.output_sink.contains(&target);
+}
+
+// Searches for inputs that make the program output `target` (at any point). Paths are
+// explored breadth first, so loops that depend on the inputs are unrolled one iteration
+// at a time and short executions are found first. The result is checked by running the
+// program on the regular Vm before it is returned.
+pub fn find_inputs_for_output(program: &[Cell], target: i64, config: &SymbolicConfig) -> Option<Vec<i64>> {
+    let mut queue: VecDeque<State> = VecDeque::new();
+    queue.push_back(State {
+        program: Rc::new(program.to_vec()),
+        memory: HashMap::new(),
+        instruction_pointer: 0,
+        relative_base: 0,
+        input_count: 0,
+        constraints: Vec::new(),
+        steps: 0,
+    });
+    let mut paths = 0;
+    while let Some(mut state) = queue.pop_front() {
+        paths += 1;
+        if paths > config.max_paths {
+            break;
+        }
+        while state.steps < config.max_steps {
+            match state.step(config) {
+                StepResult::Continue => (),
+                StepResult::Output(value) => {
+                    let mut constraints = state.constraints.clone();
+                    constraints.push(Constraint::new(binary(OpCode::Equals, value, constant(target as Cell)), true));
+                    if let Solution::Found(inputs) = solve(&constraints, state.input_count, config) {
+                        let inputs: Vec<i64> = inputs.iter().map(|&v| v as i64).collect();
+                        if produces(program, &inputs, target, config.max_steps) {
+                            return Some(inputs);
+                        }
+                    }
+                },
+                StepResult::Fork(condition, jump) => {
+                    let mut taken = state.clone();
+                    taken.instruction_pointer = jump;
+                    taken.constraints.push(Constraint::new(condition.clone(), true));
+                    state.constraints.push(Constraint::new(condition, false));
+                    // Drop branches that can't happen, keep the ones we don't know about
+                    for branch in vec![taken, state] {
+                        match solve(&branch.constraints, branch.input_count, config) {
+                            Solution::Impossible => (),
+                            _ => queue.push_back(branch),
+                        }
+                    }
+                    break;
+                },
+                StepResult::Halt | StepResult::GiveUp => break,
+            }
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_program;
+
+    #[test]
+    fn solutions() {
+        // outputs the input minus 5
+        let program = parse_program("3,9,1001,9,-5,9,4,9,99,0");
+        assert_eq!(find_inputs_for_output(&program, 37, &SymbolicConfig::default()), Some(vec![42]));
+        // outputs 1 if the input is 5, 0 otherwise
+        let program = parse_program("3,11,1008,11,5,11,4,11,99");
+        assert_eq!(find_inputs_for_output(&program, 1, &SymbolicConfig::default()), Some(vec![5]));
+        assert_eq!(find_inputs_for_output(&program, 2, &SymbolicConfig::default()), None);
+    }
+
+    #[test]
+    fn large_ranges() {
+        // outputs 7 if the input is less than 10^12, 8 otherwise
+        let program = parse_program("3,20,1007,20,1000000000000,21,1005,21,13,104,8,99,0,104,7,99,0,0,0,0,0,0");
+        assert_eq!(find_inputs_for_output(&program, 7, &SymbolicConfig::default()), Some(vec![0]));
+        assert_eq!(find_inputs_for_output(&program, 8, &SymbolicConfig::default()), Some(vec![1000000000000]));
+        // the window above the largest cell would overflow, so the solver gives up
+        let program = parse_program(&format!("3,20,1007,20,{},21,1005,21,13,104,8,99,0,104,7,99,0,0,0,0,0,0", Cell::max_value()));
+        assert_eq!(find_inputs_for_output(&program, 8, &SymbolicConfig::default()), None);
+        assert_eq!(find_inputs_for_output(&program, 7, &SymbolicConfig::default()), Some(vec![0]));
+    }
+
+    #[test]
+    fn faulting_paths() {
+        // 0 jumps to the invalid opcode 77, anything else outputs 42 first
+        let program = parse_program("3,50,1006,50,7,104,42,77,99");
+        let inputs = find_inputs_for_output(&program, 42, &SymbolicConfig::default()).unwrap();
+        assert!(inputs[0] != 0);
+        // a negative address and jump target
+        let program = parse_program("3,20,1005,20,7,4,-1,1106,0,-1");
+        assert_eq!(find_inputs_for_output(&program, 42, &SymbolicConfig::default()), None);
+    }
+}
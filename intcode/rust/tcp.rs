@@ -0,0 +1,195 @@
+// Input and output over a TCP stream as newline separated integers, e.g. to run a
+// program on one machine and what it talks to on another.
+//
+// The socket stays blocking: a reader thread parses incoming lines into a channel, so
+// len() can look for values without touching the socket (switching it to nonblocking
+// would affect the output half as well, they share the same socket).
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use super::{InputSource, OutputSink};
+
+// Err if something invalid arrived or reading failed, the reader stops after that
+fn spawn_reader(stream: TcpStream) -> Receiver<Result<i64, String>> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let value = match line {
+                Ok(ref line) if line.trim().len() == 0 => continue,
+                Ok(line) => line.trim().parse::<i64>().map_err(|_| format!("Invalid value received: '{}'", line.trim())),
+                Err(e) => Err(format!("Could not read from socket: {}", e)),
+            };
+            let stop = value.is_err();
+            if sender.send(value).is_err() || stop {
+                break;
+            }
+        }
+    });
+    return receiver;
+}
+
+// len() only gets &self, hence the RefCells (like ChannelInputSource)
+pub struct TcpInputSource {
+    receiver: Receiver<Result<i64, String>>,
+    buffer: RefCell<VecDeque<i64>>,
+    error: RefCell<Option<String>>, // raised once the values before it are used up
+}
+
+impl TcpInputSource {
+    pub fn new(stream: TcpStream) -> TcpInputSource {
+        return TcpInputSource {
+            receiver: spawn_reader(stream),
+            buffer: RefCell::new(VecDeque::new()),
+            error: RefCell::new(None),
+        };
+    }
+
+    fn push(&self, value: Result<i64, String>) {
+        match value {
+            Ok(value) => self.buffer.borrow_mut().push_back(value),
+            Err(err) => *self.error.borrow_mut() = Some(err),
+        }
+    }
+
+    fn fill_buffer(&self) {
+        while let Ok(value) = self.receiver.try_recv() {
+            self.push(value);
+        }
+    }
+
+    // Blocks until at least one value is available. Returns false if the connection
+    // was closed and nothing is buffered anymore.
+    pub fn wait(&mut self) -> bool {
+        self.fill_buffer();
+        if self.buffer.borrow().len() > 0 {
+            return true;
+        }
+        if let Some(ref err) = *self.error.borrow() {
+            panic!("{}", err);
+        }
+        match self.receiver.recv() {
+            Ok(value) => {
+                self.push(value);
+                return self.wait();
+            },
+            Err(_) => return false,
+        }
+    }
+}
+
+impl InputSource for TcpInputSource {
+    fn read(&mut self) -> i64 {
+        if !self.wait() {
+            panic!("InputSource connection is closed!");
+        }
+        return self.buffer.borrow_mut().pop_front().unwrap();
+    }
+
+    fn len(&self) -> usize {
+        self.fill_buffer();
+        return self.buffer.borrow().len();
+    }
+
+    fn unread(&mut self, value: i64) -> bool {
+        self.buffer.borrow_mut().push_front(value);
+        return true;
+    }
+}
+
+pub struct TcpOutputSink {
+    stream: TcpStream,
+}
+
+impl TcpOutputSink {
+    pub fn new(stream: TcpStream) -> TcpOutputSink {
+        return TcpOutputSink { stream: stream };
+    }
+}
+
+impl OutputSink for TcpOutputSink {
+    // Panics if the value can't be sent (e.g. the other side is gone), so nothing is lost silently
+    fn write(&mut self, value: i64) {
+        let line = format!("{}\n", value);
+        let mut bytes = line.as_bytes();
+        while bytes.len() > 0 {
+            match self.stream.write(bytes) {
+                Ok(0) => panic!("Could not write to socket: connection closed"),
+                Ok(n) => bytes = &bytes[n..],
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => thread::yield_now(),
+                Err(e) => panic!("Could not write to socket: {}", e),
+            }
+        }
+    }
+}
+
+// The reader thread keeps the socket open, so tell the other side that nothing more is coming
+impl Drop for TcpOutputSink {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Write);
+    }
+}
+
+fn split(stream: TcpStream) -> (TcpInputSource, TcpOutputSink) {
+    stream.set_nodelay(true).expect("Could not configure socket");
+    let write_stream = stream.try_clone().expect("Could not clone socket");
+    return (TcpInputSource::new(stream), TcpOutputSink::new(write_stream));
+}
+
+pub fn connect<A: ToSocketAddrs>(address: A) -> (TcpInputSource, TcpOutputSink) {
+    return split(TcpStream::connect(address).expect("Could not connect"));
+}
+
+// Waits for one connection
+pub fn accept<A: ToSocketAddrs>(address: A) -> (TcpInputSource, TcpOutputSink) {
+    let listener = TcpListener::bind(address).expect("Could not listen");
+    let (stream, _) = listener.accept().expect("Could not accept connection");
+    return split(stream);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_lost() {
+        const COUNT: i64 = 200000;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let receiving = thread::spawn(move || {
+            let (mut input, _output) = split(listener.accept().unwrap().0);
+            let mut sum = 0;
+            for _ in 0..COUNT {
+                sum += input.read();
+            }
+            return (sum, input.wait());
+        });
+        let (input, mut output) = connect(address);
+        for value in 0..COUNT {
+            output.write(value);
+            // polling the input used to make the shared socket nonblocking and drop writes
+            assert_eq!(input.len(), 0);
+        }
+        drop(output);
+        drop(input);
+        assert_eq!(receiving.join().unwrap(), (COUNT * (COUNT - 1) / 2, false));
+    }
+
+    #[test]
+    fn invalid_values() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let sending = thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            stream.write_all(b"1\n\n 2 \nthree\n").unwrap();
+        });
+        let (mut input, _output) = connect(address);
+        assert_eq!((input.read(), input.read()), (1, 2));
+        sending.join().unwrap();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| input.read()));
+        assert!(result.is_err());
+    }
+}
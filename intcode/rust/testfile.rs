@@ -0,0 +1,129 @@
+// Regression tests as plain text files (*.ic), run with `intcode test [directory]`:
+//
+//     # Day 5: is the input equal to 8?
+//     program: 3,9,8,9,10,9,4,9,99,-1,8
+//     run: 8 -> 1
+//     run: 7 -> 0
+//
+// Instead of `program:` the program can be loaded with `file: <path>`, relative to the
+// test file. Every `run:` line is one run of a fresh VM: inputs on the left, expected
+// outputs on the right (comma or space separated, either side may be empty).
+// `steps: <n>` changes the step limit for the runs after it.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{try_parse_program_as, Cell};
+use super::selftest;
+
+const DEFAULT_MAX_STEPS: u64 = 10000000;
+
+pub struct Run {
+    pub line: usize,
+    pub inputs: Vec<i64>,
+    pub outputs: Vec<i64>,
+    pub max_steps: u64,
+}
+
+pub struct TestFile {
+    pub program: Vec<Cell>,
+    pub runs: Vec<Run>,
+}
+
+fn parse_values(text: &str) -> Result<Vec<i64>, String> {
+    return text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| s.len() > 0)
+        .map(|s| s.parse::<i64>().map_err(|_| format!("Invalid value '{}'", s)))
+        .collect();
+}
+
+// `dir` is where `file:` paths are relative to
+pub fn parse(text: &str, dir: &Path) -> Result<TestFile, String> {
+    let mut program = None;
+    let mut runs = Vec::new();
+    let mut max_steps = DEFAULT_MAX_STEPS;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.len() == 0 || line.starts_with('#') {
+            continue;
+        }
+        let error = |msg: String| format!("line {}: {}", i + 1, msg);
+        let colon = line.find(':').ok_or_else(|| error(format!("Expected 'key: value', got '{}'", line)))?;
+        let (key, value) = (line[..colon].trim(), line[colon + 1..].trim());
+        match key {
+            "program" => program = Some(try_parse_program_as(value).map_err(|err| error(err.to_string()))?),
+            "file" => {
+                let text = fs::read_to_string(dir.join(value))
+                    .map_err(|err| error(format!("Could not read '{}': {}", value, err)))?;
+                program = Some(try_parse_program_as(&text).map_err(|err| error(format!("{}: {}", value, err)))?);
+            },
+            "steps" => max_steps = value.parse::<u64>().map_err(|_| error(format!("Invalid step limit '{}'", value)))?,
+            "run" => {
+                let arrow = value.find("->").ok_or_else(|| error("Expected 'inputs -> outputs'".to_string()))?;
+                runs.push(Run {
+                    line: i + 1,
+                    inputs: parse_values(&value[..arrow]).map_err(&error)?,
+                    outputs: parse_values(&value[arrow + 2..]).map_err(&error)?,
+                    max_steps: max_steps,
+                });
+            },
+            _ => return Err(error(format!("Unknown key '{}'", key))),
+        }
+    }
+    return match program {
+        Some(program) => Ok(TestFile { program: program, runs: runs }),
+        None => Err("No program given".to_string()),
+    };
+}
+
+// All *.ic files below dir, sorted so the output is stable
+pub fn discover(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => return files,
+    };
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            files.extend(discover(&path));
+        } else if path.extension().map_or(false, |ext| ext == "ic") {
+            files.push(path);
+        }
+    }
+    return files;
+}
+
+// One result per run, labelled "path:line". A file that can't be parsed is a single failure.
+pub fn run_file(path: &Path) -> Vec<(String, Result<(), String>)> {
+    let name = path.display().to_string();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => return vec![(name, Err(format!("Could not read file: {}", err)))],
+    };
+    let test = match parse(&text, path.parent().unwrap_or(Path::new("."))) {
+        Ok(test) => test,
+        Err(err) => return vec![(name, Err(err))],
+    };
+    return test.runs.iter().map(|run| {
+        let result = selftest::check(test.program.clone(), &run.inputs, &run.outputs, &[], run.max_steps);
+        return (format!("{}:{}", name, run.line), result);
+    }).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_run() {
+        let test = parse("# echo\nprogram: 3,0,4,0,99\n\nrun: 5 -> 5\nsteps: 10\nrun: -7 -> -7\n", Path::new(".")).unwrap();
+        assert_eq!(test.runs.len(), 2);
+        assert_eq!(test.runs[1].line, 6);
+        assert_eq!(test.runs[1].max_steps, 10);
+        for run in &test.runs {
+            assert!(selftest::check(test.program.clone(), &run.inputs, &run.outputs, &[], run.max_steps).is_ok());
+        }
+        assert!(parse("run: 1 -> 1\n", Path::new(".")).is_err());
+        assert!(parse("program: 99\nrun: 1\n", Path::new(".")).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use super::InputSource;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EventKind {
+    Input,
+    Output,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Event {
+    pub step: u64, // number of instructions executed before this one
+    pub kind: EventKind,
+    pub value: i64,
+}
+
+// Every input consumed and output produced by a vm. Saved as one event per line: "<step> in|out <value>"
+#[derive(Clone)]
+pub struct Transcript {
+    pub events: Vec<Event>,
+    pub steps: u64,
+}
+
+impl Transcript {
+    pub fn new() -> Transcript {
+        return Transcript {
+            events: Vec::new(),
+            steps: 0,
+        };
+    }
+
+    pub fn record(&mut self, kind: EventKind, value: i64) {
+        self.events.push(Event {
+            step: self.steps,
+            kind: kind,
+            value: value,
+        });
+    }
+
+    fn values(&self, kind: EventKind) -> Vec<i64> {
+        return self.events.iter().filter(|e| e.kind == kind).map(|e| e.value).collect();
+    }
+
+    pub fn inputs(&self) -> Vec<i64> {
+        return self.values(EventKind::Input);
+    }
+
+    pub fn outputs(&self) -> Vec<i64> {
+        return self.values(EventKind::Output);
+    }
+
+    pub fn save(&self, filename: &str) {
+        let mut text = String::new();
+        for event in &self.events {
+            let kind = match event.kind {
+                EventKind::Input => "in",
+                EventKind::Output => "out",
+            };
+            text.push_str(&format!("{} {} {}\n", event.step, kind, event.value));
+        }
+        fs::write(filename, text).expect("Could not write transcript");
+    }
+
+    pub fn load(filename: &str) -> Transcript {
+        let text = fs::read_to_string(filename).expect("Could not read transcript");
+        let mut transcript = Transcript::new();
+        for (i, line) in text.lines().enumerate() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() == 0 {
+                continue;
+            }
+            if parts.len() != 3 {
+                panic!("Invalid transcript line {}: '{}'", i + 1, line);
+            }
+            let kind = match parts[1] {
+                "in" => EventKind::Input,
+                "out" => EventKind::Output,
+                _ => panic!("Invalid event kind in transcript line {}: '{}'", i + 1, parts[1]),
+            };
+            let step = parts[0].parse::<u64>().expect("Invalid step in transcript");
+            transcript.events.push(Event {
+                step: step,
+                kind: kind,
+                value: parts[2].parse::<i64>().expect("Invalid value in transcript"),
+            });
+        }
+        return transcript;
+    }
+}
+
+// Feeds the inputs of a recorded transcript back into a vm
+#[derive(Default, Clone)]
+pub struct ReplayInputSource {
+    pub values: VecDeque<i64>,
+}
+
+impl ReplayInputSource {
+    pub fn new(transcript: &Transcript) -> ReplayInputSource {
+        return ReplayInputSource {
+            values: transcript.inputs().into_iter().collect(),
+        };
+    }
+
+    pub fn from_file(filename: &str) -> ReplayInputSource {
+        return ReplayInputSource::new(&Transcript::load(filename));
+    }
+}
+
+impl InputSource for ReplayInputSource {
+    fn read(&mut self) -> i64 {
+        return self.values.pop_front().expect("Transcript has no more inputs");
+    }
+
+    fn len(&self) -> usize {
+        return self.values.len();
+    }
+
+    fn unread(&mut self, value: i64) -> bool {
+        self.values.push_front(value);
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use super::*;
+    use super::super::{parse_program, Vm, VmState};
+
+    #[test]
+    fn record_and_replay() {
+        // outputs the sum and the product of two inputs
+        let program = parse_program("3,20,3,21,1,20,21,22,4,22,2,20,21,22,4,22,99");
+        let mut vm: Vm<VecDeque<i64>, VecDeque<i64>> = Vm::new(program.clone());
+        vm.enable_transcript();
+        vm.input_source.extend(&[3, 4]);
+        assert_eq!(vm.run(), VmState::Terminated);
+        let transcript = vm.transcript.take().unwrap();
+        assert_eq!(transcript.events, vec![
+            Event { step: 0, kind: EventKind::Input, value: 3 },
+            Event { step: 1, kind: EventKind::Input, value: 4 },
+            Event { step: 3, kind: EventKind::Output, value: 7 },
+            Event { step: 5, kind: EventKind::Output, value: 12 },
+        ]);
+        assert_eq!(transcript.steps, 7);
+
+        let filename = env::temp_dir().join(format!("intcode_transcript_{}.txt", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        transcript.save(filename);
+        let loaded = Transcript::load(filename);
+        fs::remove_file(filename).unwrap();
+        assert_eq!(loaded.events, transcript.events);
+
+        let mut replay: Vm<ReplayInputSource, VecDeque<i64>> = Vm::with_io(program, ReplayInputSource::new(&loaded), VecDeque::new());
+        replay.enable_transcript();
+        assert_eq!(replay.run(), VmState::Terminated);
+        let replayed = replay.transcript.unwrap();
+        assert_eq!((replayed.inputs(), replayed.outputs()), (vec![3, 4], vec![7, 12]));
+        assert_eq!((replayed.events, replayed.steps), (transcript.events, transcript.steps));
+        assert_eq!(replay.steps, vm.steps);
+    }
+}
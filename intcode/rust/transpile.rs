@@ -0,0 +1,368 @@
+// Ahead-of-time translation of Intcode programs into Rust source code.
+//
+// Code is found by following the control flow from address 0 ("strong" code). Jumps to
+// computed addresses can't be followed, so immediate operands that point at valid
+// instructions are used as additional entry points ("weak" code), which covers return
+// addresses pushed by the usual calling conventions. Every basic block becomes one arm
+// of a big match on the instruction pointer.
+//
+// Self-modifying programs can't be translated: stores with a fixed address into strong
+// code are rejected right away, the generated code bails out if the program writes into
+// strong code at runtime or tries to execute weak code after it was written to.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::{Cell, OpCode, ParamMode};
+use super::disasm::{self, Instruction};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CodeKind {
+    None,
+    Strong,
+    Weak,
+}
+
+pub struct Analysis {
+    pub instructions: BTreeMap<usize, Instruction>,
+    pub code: Vec<CodeKind>, // per cell of the program
+    pub leaders: BTreeSet<usize>, // basic block starts
+}
+
+fn immediate_target(instruction: &Instruction) -> Option<Cell> {
+    if instruction.modes[1] == ParamMode::Immediate {
+        return Some(instruction.params[1]);
+    }
+    return None;
+}
+
+// Returns (falls through, static jump target)
+pub fn successors(instruction: &Instruction) -> (bool, Option<Cell>) {
+    let cond_always = |taken_if_nonzero: bool| {
+        if instruction.modes[0] != ParamMode::Immediate {
+            return None;
+        }
+        return Some((instruction.params[0] != 0) == taken_if_nonzero);
+    };
+    return match instruction.op_code {
+        OpCode::Terminate => (false, None),
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let taken = cond_always(instruction.op_code == OpCode::JumpIfTrue);
+            match taken {
+                Some(true) => (false, immediate_target(instruction)),
+                Some(false) => (true, None),
+                None => (true, immediate_target(instruction)),
+            }
+        },
+        _ => (true, None),
+    };
+}
+
+impl Analysis {
+    fn new(program: &[Cell]) -> Analysis {
+        return Analysis {
+            instructions: BTreeMap::new(),
+            code: vec![CodeKind::None; program.len()],
+            leaders: BTreeSet::new(),
+        };
+    }
+
+    fn overlaps_code(&self, instruction: &Instruction) -> bool {
+        return (instruction.address..instruction.next_address())
+            .any(|a| a >= self.code.len() || self.code[a] != CodeKind::None);
+    }
+
+    fn explore(&mut self, program: &[Cell], entry: usize, kind: CodeKind) {
+        let mut worklist: Vec<usize> = vec![entry];
+        self.leaders.insert(entry);
+        while let Some(address) = worklist.pop() {
+            if self.instructions.contains_key(&address) {
+                continue;
+            }
+            let instruction = match disasm::decode(program, address) {
+                Some(instruction) => instruction,
+                None => continue,
+            };
+            if self.overlaps_code(&instruction) {
+                continue;
+            }
+            for a in instruction.address..instruction.next_address() {
+                self.code[a] = kind;
+            }
+            let (falls_through, target) = successors(&instruction);
+            if falls_through {
+                if instruction.op_code == OpCode::JumpIfTrue || instruction.op_code == OpCode::JumpIfFalse {
+                    self.leaders.insert(instruction.next_address());
+                }
+                worklist.push(instruction.next_address());
+            }
+            if let Some(target) = target {
+                if target >= 0 {
+                    self.leaders.insert(target as usize);
+                    worklist.push(target as usize);
+                }
+            }
+            self.instructions.insert(address, instruction);
+        }
+    }
+
+    pub fn analyze(program: &[Cell]) -> Analysis {
+        let mut analysis = Analysis::new(program);
+        analysis.explore(program, 0, CodeKind::Strong);
+
+        // Immediate operands as entry points for computed jumps, until nothing new shows up
+        let mut tried: BTreeSet<usize> = BTreeSet::new();
+        loop {
+            let seeds: Vec<usize> = analysis.instructions.values()
+                .flat_map(|i| i.modes.iter().zip(i.params.iter())
+                    .filter(|&(&mode, _)| mode == ParamMode::Immediate)
+                    .map(|(_, &value)| value).collect::<Vec<Cell>>())
+                .filter(|&v| v >= 0 && (v as usize) < program.len())
+                .map(|v| v as usize)
+                .filter(|a| !tried.contains(a))
+                .collect();
+            if seeds.is_empty() {
+                break;
+            }
+            for seed in seeds {
+                tried.insert(seed);
+                if analysis.instructions.contains_key(&seed) {
+                    // already code, but a computed jump might land in the middle of its block
+                    analysis.leaders.insert(seed);
+                } else if analysis.code[seed] == CodeKind::None && disasm::decode(program, seed).is_some() {
+                    analysis.explore(program, seed, CodeKind::Weak);
+                }
+            }
+        }
+        analysis.leaders = analysis.leaders.iter().cloned()
+            .filter(|a| analysis.instructions.contains_key(a)).collect();
+        return analysis;
+    }
+}
+
+fn operand(mode: ParamMode, value: Cell) -> String {
+    return match mode {
+        ParamMode::Position => format!("rd(&mem, {})?", value),
+        ParamMode::Immediate => format!("({})", value),
+        ParamMode::Relative => format!("rd(&mem, rb + ({}))?", value),
+    };
+}
+
+fn destination(instruction: &Instruction, param: usize, analysis: &Analysis) -> Result<String, String> {
+    let value = instruction.params[param];
+    return match instruction.modes[param] {
+        ParamMode::Position => {
+            if value >= 0 && (value as usize) < analysis.code.len() && analysis.code[value as usize] == CodeKind::Strong {
+                return Err(format!("Self-modifying store at {} writes to code at {}", instruction.address, value));
+            }
+            Ok(format!("{}", value))
+        },
+        ParamMode::Immediate => Err(format!("Write parameter in immediate mode at {}", instruction.address)),
+        ParamMode::Relative => Ok(format!("rb + ({})", value)),
+    };
+}
+
+// Returns the statements for one instruction and whether control can continue after it
+fn translate_instruction(instruction: &Instruction, analysis: &Analysis) -> Result<(String, bool), String> {
+    let op = |i: usize| operand(instruction.modes[i], instruction.params[i]);
+    let store = |param: usize, value: String| -> Result<String, String> {
+        return Ok(format!("let v = {}; wr(&mut mem, &mut weak_dirty, {}, v)?;", value, destination(instruction, param, analysis)?));
+    };
+    let jump = |taken_if_nonzero: bool| -> String {
+        let target = match instruction.modes[1] {
+            ParamMode::Immediate if instruction.params[1] >= 0 => format!("{}", instruction.params[1]),
+            _ => format!("to_ip({})?", op(1)),
+        };
+        if instruction.modes[0] == ParamMode::Immediate {
+            let taken = (instruction.params[0] != 0) == taken_if_nonzero;
+            return if taken { format!("ip = {}; continue;", target) } else { String::new() };
+        }
+        return format!("if {} {} 0 {{ ip = {}; continue; }}", op(0), if taken_if_nonzero { "!=" } else { "==" }, target);
+    };
+    let code = match instruction.op_code {
+        OpCode::Add => store(2, format!("{} + {}", op(0), op(1)))?,
+        OpCode::Mul => store(2, format!("{} * {}", op(0), op(1)))?,
+        OpCode::Input => store(0, "input()".to_string())?,
+        OpCode::Output => format!("output({});", op(0)),
+        OpCode::JumpIfTrue => jump(true),
+        OpCode::JumpIfFalse => jump(false),
+        OpCode::LessThan => store(2, format!("if {} < {} {{ 1 }} else {{ 0 }}", op(0), op(1)))?,
+        OpCode::Equals => store(2, format!("if {} == {} {{ 1 }} else {{ 0 }}", op(0), op(1)))?,
+        OpCode::AdjustRelativeBase => format!("rb += {};", op(0)),
+        OpCode::Terminate => "return Ok(());".to_string(),
+        OpCode::Custom(n) => return Err(format!("Custom op code {} can not be transpiled", n)),
+        OpCode::HostCall => return Err("Host calls can not be transpiled".to_string()),
+    };
+    return Ok((code, successors(instruction).0));
+}
+
+const PRELUDE: &'static str = "
+fn rd(mem: &Vec<i64>, address: i64) -> Result<i64, String> {
+    if address < 0 {
+        return Err(format!(\"Invalid address: {}\", address));
+    }
+    let address = address as usize;
+    return Ok(if address < mem.len() { mem[address] } else { 0 });
+}
+
+fn wr(mem: &mut Vec<i64>, weak_dirty: &mut bool, address: i64, value: i64) -> Result<(), String> {
+    if address < 0 {
+        return Err(format!(\"Invalid address: {}\", address));
+    }
+    let address = address as usize;
+    if address < CODE.len() {
+        match CODE[address] {
+            1 => return Err(format!(\"Self-modifying store to {}\", address)),
+            2 => *weak_dirty = true,
+            _ => (),
+        }
+    }
+    if address >= mem.len() {
+        mem.resize(address + 1, 0);
+    }
+    mem[address] = value;
+    return Ok(());
+}
+
+fn to_ip(value: i64) -> Result<usize, String> {
+    if value < 0 {
+        return Err(format!(\"Cannot jump to negative address\"));
+    }
+    return Ok(value as usize);
+}
+";
+
+// Cells that are reachable as code, following the control flow the same way as the
+// translation does. Used to tell code from data in listings.
+pub fn code_cells(program: &[Cell]) -> Vec<bool> {
+    let analysis = Analysis::analyze(program);
+    return analysis.code.iter().map(|&kind| kind != CodeKind::None).collect();
+}
+
+// Returns the source of a Rust module with a function `fn_name(input, output)` that
+// behaves like running the program on the Vm.
+pub fn transpile(program: &[Cell], fn_name: &str) -> Result<String, String> {
+    let analysis = Analysis::analyze(program);
+    let mut out = String::new();
+    writeln!(out, "// Generated from a {} cell Intcode program by intcode::transpile. Do not edit.", program.len()).unwrap();
+    writeln!(out, "#![allow(unused_parens, unreachable_code, unused_mut, unused_variables, dead_code)]").unwrap();
+    writeln!(out, "").unwrap();
+    writeln!(out, "static PROGRAM: [i64; {}] = {:?};", program.len(), program).unwrap();
+    let code: Vec<u8> = analysis.code.iter().map(|&k| match k {
+        CodeKind::None => 0,
+        CodeKind::Strong => 1,
+        CodeKind::Weak => 2,
+    }).collect();
+    writeln!(out, "static CODE: [u8; {}] = {:?}; // 1 = code, 2 = code only reachable by computed jumps", code.len(), code).unwrap();
+    out.push_str(PRELUDE);
+    writeln!(out, "").unwrap();
+    writeln!(out, "pub fn {}<I: FnMut() -> i64, O: FnMut(i64)>(mut input: I, mut output: O) -> Result<(), String> {{", fn_name).unwrap();
+    writeln!(out, "    let mut mem: Vec<i64> = PROGRAM.to_vec();").unwrap();
+    writeln!(out, "    let mut rb: i64 = 0;").unwrap();
+    writeln!(out, "    let mut ip: usize = 0;").unwrap();
+    writeln!(out, "    let mut weak_dirty = false;").unwrap();
+    writeln!(out, "    loop {{").unwrap();
+    writeln!(out, "        match ip {{").unwrap();
+    for &leader in &analysis.leaders {
+        writeln!(out, "            {} => {{", leader).unwrap();
+        if analysis.code[leader] == CodeKind::Weak {
+            writeln!(out, "                if weak_dirty {{ return Err(format!(\"Code at {} might have been modified\")); }}", leader).unwrap();
+        }
+        let mut address = leader;
+        loop {
+            let instruction = &analysis.instructions[&address];
+            let (code, falls_through) = translate_instruction(instruction, &analysis)?;
+            if !code.is_empty() {
+                writeln!(out, "                {} // {}", code, disasm::disassemble_instruction(program, address).unwrap().0).unwrap();
+            }
+            address = instruction.next_address();
+            if !falls_through {
+                break;
+            }
+            if analysis.leaders.contains(&address) || !analysis.instructions.contains_key(&address) {
+                writeln!(out, "                ip = {};", address).unwrap();
+                break;
+            }
+        }
+        writeln!(out, "            }},").unwrap();
+    }
+    writeln!(out, "            _ => return Err(format!(\"Jump to untranslated address {{}}\", ip)),").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    return Ok(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process::Command;
+    use super::super::{parse_program, run};
+
+    // Compiles the generated code with rustc and runs it. Ok(outputs) or Err(outputs so far, error).
+    fn run_transpiled(name: &str, program: &[Cell], inputs: &[i64]) -> Result<Vec<i64>, (Vec<i64>, String)> {
+        let mut source = transpile(program, "run_program").expect("Could not transpile");
+        source.push_str("
+fn main() {
+    let mut inputs = std::env::args().skip(1).map(|arg| arg.parse::<i64>().unwrap());
+    if let Err(err) = run_program(|| inputs.next().expect(\"Out of input\"), |value| println!(\"{}\", value)) {
+        println!(\"error {}\", err);
+    }
+}
+");
+        let base = env::temp_dir().join(format!("intcode_transpile_{}_{}", name, std::process::id()));
+        let source_file = base.with_extension("rs");
+        fs::write(&source_file, source).unwrap();
+        let compiled = Command::new("rustc").arg("-o").arg(&base).arg(&source_file).output().expect("Could not run rustc");
+        assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+        let ran = Command::new(&base).args(inputs.iter().map(|v| v.to_string())).output().unwrap();
+        let _ = fs::remove_file(&source_file);
+        let _ = fs::remove_file(&base);
+        let mut outputs = Vec::new();
+        for line in String::from_utf8(ran.stdout).unwrap().lines() {
+            if line.starts_with("error ") {
+                return Err((outputs, line[6..].to_string()));
+            }
+            outputs.push(line.parse::<i64>().unwrap());
+        }
+        return Ok(outputs);
+    }
+
+    // Outputs 1, 2 and then jumps back into the middle of the first block through [61]
+    fn jump_into_block() -> Vec<Cell> {
+        let mut program = parse_program("104,1,104,2,1001,62,1,62,1008,62,2,63,1005,63,19,106,0,61,0,99");
+        program.resize(64, 0);
+        program[61] = 2;
+        return program;
+    }
+
+    #[test]
+    fn computed_jump_into_block() {
+        let program = jump_into_block();
+        assert!(Analysis::analyze(&program).leaders.contains(&2));
+        assert_eq!(run(&program, &[]).unwrap(), vec![1, 2, 2]);
+        assert_eq!(run_transpiled("block", &program, &[]), Ok(vec![1, 2, 2]));
+    }
+
+    #[test]
+    fn same_as_vm() {
+        let quine = parse_program("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+        assert_eq!(run_transpiled("quine", &quine, &[]), Ok(run(&quine, &[]).unwrap()));
+        // outputs 999, 1000 or 1001 if the input is below, equal to or above 8
+        let compare_8 = parse_program("3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99");
+        for &input in [7, 8, 9].iter() {
+            assert_eq!(run_transpiled(&format!("compare_8_{}", input), &compare_8, &[input]), Ok(run(&compare_8, &[input]).unwrap()));
+        }
+    }
+
+    #[test]
+    fn self_modifying() {
+        // a fixed store into code is rejected right away
+        assert!(transpile(&parse_program("1101,0,99,6,104,1,104,2,99"), "f").is_err());
+        // overwrites the output at 11 (only reachable through the jump to [10]) with a halt
+        let program = parse_program("109,11,21101,0,99,0,106,0,10,99,11,104,7,99");
+        assert_eq!(run(&program, &[]).unwrap(), Vec::<i64>::new());
+        assert_eq!(run_transpiled("modified", &program, &[]), Err((vec![], "Code at 11 might have been modified".to_string())));
+    }
+}
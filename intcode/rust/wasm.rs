@@ -0,0 +1,570 @@
+// Compiles Intcode programs into WebAssembly modules (the binary format, so the result
+// can be loaded by a browser directly).
+//
+// The module imports env.input: () -> i64 and env.output: (i64) -> () and exports
+// run: () -> () and its memory. i64 crosses the JS boundary as BigInt:
+//
+//     const { instance } = await WebAssembly.instantiate(bytes, {
+//         env: { input: () => BigInt(nextInput()), output: value => console.log(value) },
+//     });
+//     instance.exports.run();
+//
+// Code is found and split into basic blocks like in transpile, and the same rules apply
+// to self-modifying programs, except that errors are traps (`unreachable`) instead of
+// messages. Blocks are dispatched with a br_table on the instruction pointer.
+//
+// Memory layout: one byte per program cell saying whether it is code (like CODE in the
+// transpiled source), then the cells as i64, starting at the next multiple of 8. Memory grows on writes
+// past the end, reads past the end return 0.
+use std::collections::BTreeMap;
+
+use super::transpile::{successors, Analysis, CodeKind};
+use super::disasm::Instruction;
+use super::{Cell, OpCode, ParamMode};
+
+// Function indices, the imports come first
+const INPUT: u32 = 0;
+const OUTPUT: u32 = 1;
+const READ: u32 = 2;
+const WRITE: u32 = 3;
+const RUN: u32 = 4;
+
+// Locals of run
+const IP: u32 = 0;
+const RB: u32 = 1;
+
+// Global: weak code has been written to
+const WEAK_DIRTY: u32 = 0;
+
+const PAGE_SIZE: u64 = 65536;
+
+// Instructions we need
+const UNREACHABLE: u8 = 0x00;
+const BLOCK: u8 = 0x02;
+const LOOP: u8 = 0x03;
+const IF: u8 = 0x04;
+const ELSE: u8 = 0x05;
+const END: u8 = 0x0b;
+const BR: u8 = 0x0c;
+const BR_IF: u8 = 0x0d;
+const BR_TABLE: u8 = 0x0e;
+const RETURN: u8 = 0x0f;
+const CALL: u8 = 0x10;
+const LOCAL_GET: u8 = 0x20;
+const LOCAL_SET: u8 = 0x21;
+const GLOBAL_GET: u8 = 0x23;
+const GLOBAL_SET: u8 = 0x24;
+const I64_LOAD: u8 = 0x29;
+const I32_LOAD8_U: u8 = 0x2d;
+const I64_STORE: u8 = 0x37;
+const MEMORY_SIZE: u8 = 0x3f;
+const MEMORY_GROW: u8 = 0x40;
+const I32_CONST: u8 = 0x41;
+const I64_CONST: u8 = 0x42;
+const I32_EQZ: u8 = 0x45;
+const I32_EQ: u8 = 0x46;
+const I32_LT_S: u8 = 0x48;
+const I64_EQZ: u8 = 0x50;
+const I64_EQ: u8 = 0x51;
+const I64_LT_S: u8 = 0x53;
+const I64_GE_U: u8 = 0x5a;
+const I64_ADD: u8 = 0x7c;
+const I64_SUB: u8 = 0x7d;
+const I64_MUL: u8 = 0x7e;
+const I64_DIV_U: u8 = 0x80;
+const I64_SHL: u8 = 0x86;
+const I64_SHR_U: u8 = 0x88;
+const I32_WRAP_I64: u8 = 0xa7;
+const I64_EXTEND_I32_U: u8 = 0xad;
+
+const VOID: u8 = 0x40;
+const TYPE_I32: u8 = 0x7f;
+const TYPE_I64: u8 = 0x7e;
+
+struct Code {
+    bytes: Vec<u8>,
+}
+
+impl Code {
+    fn new() -> Code {
+        return Code { bytes: Vec::new() };
+    }
+
+    fn op(&mut self, op: u8) -> &mut Code {
+        self.bytes.push(op);
+        return self;
+    }
+
+    fn uleb(&mut self, mut value: u64) -> &mut Code {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return self;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn sleb(&mut self, mut value: i64) -> &mut Code {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                self.bytes.push(byte);
+                return self;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn op_index(&mut self, op: u8, index: u32) -> &mut Code {
+        return self.op(op).uleb(index as u64);
+    }
+
+    fn i32_const(&mut self, value: i32) -> &mut Code {
+        return self.op(I32_CONST).sleb(value as i64);
+    }
+
+    fn i64_const(&mut self, value: i64) -> &mut Code {
+        return self.op(I64_CONST).sleb(value);
+    }
+
+    fn memarg(&mut self, align: u32) -> &mut Code {
+        return self.uleb(align as u64).uleb(0);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Code {
+        self.bytes.extend_from_slice(bytes);
+        return self;
+    }
+
+    fn name(&mut self, name: &str) -> &mut Code {
+        return self.uleb(name.len() as u64).bytes(name.as_bytes());
+    }
+
+    // Sections and function bodies are prefixed with their size
+    fn sized(&mut self, content: &Code) -> &mut Code {
+        return self.uleb(content.bytes.len() as u64).bytes(&content.bytes);
+    }
+
+    fn section(&mut self, id: u8, content: &Code) -> &mut Code {
+        return self.op(id).sized(content);
+    }
+}
+
+fn cells_offset(program_len: usize) -> u64 {
+    return (program_len as u64 + 7) / 8 * 8;
+}
+
+// Stack: address -> value
+fn read_function(cells: u64) -> Code {
+    let mut f = Code::new();
+    f.uleb(0); // no locals
+    f.op_index(LOCAL_GET, 0).i64_const(0).op(I64_LT_S).op(IF).op(VOID).op(UNREACHABLE).op(END);
+    // address >= (memory size - cells offset) / 8
+    f.op_index(LOCAL_GET, 0);
+    f.op(MEMORY_SIZE).op(0).op(I64_EXTEND_I32_U).i64_const(PAGE_SIZE as i64).op(I64_MUL);
+    f.i64_const(cells as i64).op(I64_SUB).i64_const(3).op(I64_SHR_U);
+    f.op(I64_GE_U).op(IF).op(TYPE_I64);
+    f.i64_const(0);
+    f.op(ELSE);
+    f.op_index(LOCAL_GET, 0).i64_const(3).op(I64_SHL).i64_const(cells as i64).op(I64_ADD).op(I32_WRAP_I64);
+    f.op(I64_LOAD).memarg(3);
+    f.op(END);
+    f.op(END);
+    return f;
+}
+
+// Stack: address, value -> nothing
+fn write_function(cells: u64, program_len: usize) -> Code {
+    let mut f = Code::new();
+    f.uleb(1).uleb(1).op(TYPE_I64); // local 2: pages needed
+    f.op_index(LOCAL_GET, 0).i64_const(0).op(I64_LT_S).op(IF).op(VOID).op(UNREACHABLE).op(END);
+    // Code map: 1 = code, 2 = weak code
+    f.op_index(LOCAL_GET, 0).i64_const(program_len as i64).op(I64_LT_S).op(IF).op(VOID);
+    f.op_index(LOCAL_GET, 0).op(I32_WRAP_I64).op(I32_LOAD8_U).memarg(0).i32_const(1).op(I32_EQ);
+    f.op(IF).op(VOID).op(UNREACHABLE).op(END);
+    f.op_index(LOCAL_GET, 0).op(I32_WRAP_I64).op(I32_LOAD8_U).memarg(0).i32_const(2).op(I32_EQ);
+    f.op(IF).op(VOID).i32_const(1).op_index(GLOBAL_SET, WEAK_DIRTY).op(END);
+    f.op(END);
+    // Grow to ceil((cells + (address + 1) * 8) / page size) pages
+    f.op_index(LOCAL_GET, 0).i64_const(1).op(I64_ADD).i64_const(3).op(I64_SHL).i64_const(cells as i64).op(I64_ADD);
+    f.i64_const(PAGE_SIZE as i64 - 1).op(I64_ADD).i64_const(PAGE_SIZE as i64).op(I64_DIV_U);
+    f.op(MEMORY_SIZE).op(0).op(I64_EXTEND_I32_U).op(I64_SUB).op_index(LOCAL_SET, 2);
+    f.op_index(LOCAL_GET, 2).i64_const(0).op(I64_LT_S).op(I32_EQZ).op(IF).op(VOID); // >= 0
+    f.op_index(LOCAL_GET, 2).op(I32_WRAP_I64).op(MEMORY_GROW).op(0).i32_const(0).op(I32_LT_S);
+    f.op(IF).op(VOID).op(UNREACHABLE).op(END);
+    f.op(END);
+    f.op_index(LOCAL_GET, 0).i64_const(3).op(I64_SHL).i64_const(cells as i64).op(I64_ADD).op(I32_WRAP_I64);
+    f.op_index(LOCAL_GET, 1).op(I64_STORE).memarg(3);
+    f.op(END);
+    return f;
+}
+
+fn operand(f: &mut Code, mode: ParamMode, value: Cell) {
+    match mode {
+        ParamMode::Position => {
+            f.i64_const(value as i64).op_index(CALL, READ);
+        },
+        ParamMode::Immediate => {
+            f.i64_const(value as i64);
+        },
+        ParamMode::Relative => {
+            f.op_index(LOCAL_GET, RB).i64_const(value as i64).op(I64_ADD).op_index(CALL, READ);
+        },
+    }
+}
+
+fn destination(f: &mut Code, instruction: &Instruction, param: usize, analysis: &Analysis) -> Result<(), String> {
+    let value = instruction.params[param];
+    match instruction.modes[param] {
+        ParamMode::Position => {
+            if value >= 0 && (value as usize) < analysis.code.len() && analysis.code[value as usize] == CodeKind::Strong {
+                return Err(format!("Self-modifying store at {} writes to code at {}", instruction.address, value));
+            }
+            f.i64_const(value as i64);
+        },
+        ParamMode::Immediate => return Err(format!("Write parameter in immediate mode at {}", instruction.address)),
+        ParamMode::Relative => {
+            f.op_index(LOCAL_GET, RB).i64_const(value as i64).op(I64_ADD);
+        },
+    }
+    return Ok(());
+}
+
+// Emits one instruction, `dispatch` is the branch depth of the dispatch loop
+fn compile_instruction(f: &mut Code, instruction: &Instruction, analysis: &Analysis, dispatch: u32) -> Result<bool, String> {
+    let op = |f: &mut Code, i: usize| operand(f, instruction.modes[i], instruction.params[i]);
+    match instruction.op_code {
+        OpCode::Add | OpCode::Mul | OpCode::LessThan | OpCode::Equals => {
+            destination(f, instruction, 2, analysis)?;
+            op(f, 0);
+            op(f, 1);
+            match instruction.op_code {
+                OpCode::Add => f.op(I64_ADD),
+                OpCode::Mul => f.op(I64_MUL),
+                OpCode::LessThan => f.op(I64_LT_S).op(I64_EXTEND_I32_U),
+                _ => f.op(I64_EQ).op(I64_EXTEND_I32_U),
+            };
+            f.op_index(CALL, WRITE);
+        },
+        OpCode::Input => {
+            destination(f, instruction, 0, analysis)?;
+            f.op_index(CALL, INPUT).op_index(CALL, WRITE);
+        },
+        OpCode::Output => {
+            op(f, 0);
+            f.op_index(CALL, OUTPUT);
+        },
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+            let taken_if_nonzero = instruction.op_code == OpCode::JumpIfTrue;
+            let always = instruction.modes[0] == ParamMode::Immediate;
+            if always && (instruction.params[0] != 0) != taken_if_nonzero {
+                return Ok(true); // never taken
+            }
+            let mut depth = dispatch;
+            if !always {
+                op(f, 0);
+                if taken_if_nonzero {
+                    f.op(I64_EQZ).op(I32_EQZ);
+                } else {
+                    f.op(I64_EQZ);
+                }
+                f.op(IF).op(VOID);
+                depth += 1;
+            }
+            op(f, 1);
+            f.op_index(LOCAL_SET, IP);
+            if instruction.modes[1] != ParamMode::Immediate || instruction.params[1] < 0 {
+                f.op_index(LOCAL_GET, IP).i64_const(0).op(I64_LT_S).op(IF).op(VOID).op(UNREACHABLE).op(END);
+            }
+            f.op_index(BR, depth);
+            if !always {
+                f.op(END);
+            }
+        },
+        OpCode::AdjustRelativeBase => {
+            f.op_index(LOCAL_GET, RB);
+            op(f, 0);
+            f.op(I64_ADD).op_index(LOCAL_SET, RB); // may go negative, read/write check the address
+        },
+        OpCode::Terminate => {
+            f.op(RETURN);
+        },
+        OpCode::Custom(n) => return Err(format!("Custom op code {} can not be compiled", n)),
+        OpCode::HostCall => return Err("Host calls can not be compiled".to_string()),
+    }
+    return Ok(successors(instruction).0);
+}
+
+fn run_function(program: &[Cell], analysis: &Analysis) -> Result<Code, String> {
+    let leaders: Vec<usize> = analysis.leaders.iter().cloned().collect();
+    let depths: BTreeMap<usize, u32> = leaders.iter().enumerate().map(|(k, &l)| (l, k as u32)).collect();
+    let n = leaders.len() as u32;
+    let mut f = Code::new();
+    f.uleb(1).uleb(2).op(TYPE_I64); // ip, rb
+
+    f.op(LOOP).op(VOID);
+    f.op(BLOCK).op(VOID); // trap
+    for _ in 0..n {
+        f.op(BLOCK).op(VOID);
+    }
+    // Innermost: jump to the block of the instruction pointer, depth k is leader k
+    f.op_index(LOCAL_GET, IP).i64_const(program.len() as i64).op(I64_GE_U).op_index(BR_IF, n);
+    f.op_index(LOCAL_GET, IP).op(I32_WRAP_I64).op(BR_TABLE).uleb(program.len() as u64);
+    for address in 0..program.len() {
+        f.uleb(*depths.get(&address).unwrap_or(&n) as u64);
+    }
+    f.uleb(n as u64);
+
+    for (k, &leader) in leaders.iter().enumerate() {
+        f.op(END); // code of block k follows the end of block k
+        let dispatch = n - k as u32;
+        if analysis.code[leader] == CodeKind::Weak {
+            f.op_index(GLOBAL_GET, WEAK_DIRTY).op(IF).op(VOID).op(UNREACHABLE).op(END);
+        }
+        let mut address = leader;
+        loop {
+            let instruction = &analysis.instructions[&address];
+            let falls_through = compile_instruction(&mut f, instruction, analysis, dispatch)?;
+            address = instruction.next_address();
+            if !falls_through {
+                break;
+            }
+            if analysis.leaders.contains(&address) || !analysis.instructions.contains_key(&address) {
+                f.i64_const(address as i64).op_index(LOCAL_SET, IP).op_index(BR, dispatch);
+                break;
+            }
+        }
+    }
+    f.op(END); // trap
+    f.op(UNREACHABLE);
+    f.op(END); // loop
+    f.op(END);
+    return Ok(f);
+}
+
+// Returns the bytes of a .wasm file
+pub fn compile(program: &[Cell]) -> Result<Vec<u8>, String> {
+    let analysis = Analysis::analyze(program);
+    let cells = cells_offset(program.len());
+    let run = run_function(program, &analysis)?;
+
+    let mut module = Code::new();
+    module.bytes(b"\0asm").bytes(&[1, 0, 0, 0]);
+
+    // Types: 0 input, 1 output, 2 read, 3 write, 4 run
+    let mut types = Code::new();
+    types.uleb(5);
+    types.op(0x60).uleb(0).uleb(1).op(TYPE_I64);
+    types.op(0x60).uleb(1).op(TYPE_I64).uleb(0);
+    types.op(0x60).uleb(1).op(TYPE_I64).uleb(1).op(TYPE_I64);
+    types.op(0x60).uleb(2).op(TYPE_I64).op(TYPE_I64).uleb(0);
+    types.op(0x60).uleb(0).uleb(0);
+    module.section(1, &types);
+
+    let mut imports = Code::new();
+    imports.uleb(2);
+    imports.name("env").name("input").op(0).uleb(0);
+    imports.name("env").name("output").op(0).uleb(1);
+    module.section(2, &imports);
+
+    let mut functions = Code::new();
+    functions.uleb(3).uleb(2).uleb(3).uleb(4);
+    module.section(3, &functions);
+
+    let mut memory = Code::new();
+    let pages = (cells + program.len() as u64 * 8 + PAGE_SIZE - 1) / PAGE_SIZE;
+    memory.uleb(1).op(0).uleb(pages.max(1));
+    module.section(5, &memory);
+
+    let mut globals = Code::new();
+    globals.uleb(1).op(TYPE_I32).op(1).i32_const(0).op(END);
+    module.section(6, &globals);
+
+    let mut exports = Code::new();
+    exports.uleb(2);
+    exports.name("run").op(0).uleb(RUN as u64);
+    exports.name("memory").op(2).uleb(0);
+    module.section(7, &exports);
+
+    let mut code = Code::new();
+    code.uleb(3);
+    code.sized(&read_function(cells));
+    code.sized(&write_function(cells, program.len()));
+    code.sized(&run);
+    module.section(10, &code);
+
+    let mut contents: Vec<u8> = analysis.code.iter().map(|&k| match k {
+        CodeKind::None => 0,
+        CodeKind::Strong => 1,
+        CodeKind::Weak => 2,
+    }).collect();
+    contents.resize(cells as usize, 0);
+    for &value in program {
+        let value = value as i64;
+        for i in 0..8 {
+            contents.push((value >> (i * 8)) as u8);
+        }
+    }
+    let mut data = Code::new();
+    data.uleb(1).uleb(0).i32_const(0).op(END).uleb(contents.len() as u64).bytes(&contents);
+    module.section(11, &data);
+
+    return Ok(module.bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_program;
+
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn byte(&mut self) -> u8 {
+            self.pos += 1;
+            return self.bytes[self.pos - 1];
+        }
+
+        fn uleb(&mut self) -> u64 {
+            let (mut value, mut shift) = (0, 0);
+            loop {
+                let byte = self.byte();
+                value |= ((byte & 0x7f) as u64) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    return value;
+                }
+            }
+        }
+
+        fn sleb(&mut self) -> i64 {
+            let (mut value, mut shift) = (0i64, 0);
+            loop {
+                let byte = self.byte();
+                value |= ((byte & 0x7f) as i64) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    if shift < 64 && byte & 0x40 != 0 {
+                        value |= -1 << shift;
+                    }
+                    return value;
+                }
+            }
+        }
+
+        fn expect(&mut self, byte: u8) {
+            assert_eq!(self.byte(), byte, "at {}", self.pos - 1);
+        }
+    }
+
+    // (id, content) of every section, checking that the sizes add up
+    fn sections(module: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        assert_eq!(&module[..8], b"\0asm\x01\0\0\0");
+        let mut reader = Reader { bytes: module, pos: 8 };
+        let mut sections = Vec::new();
+        while reader.pos < module.len() {
+            let id = reader.byte();
+            let size = reader.uleb() as usize;
+            sections.push((id, module[reader.pos..reader.pos + size].to_vec()));
+            reader.pos += size;
+        }
+        assert_eq!(reader.pos, module.len());
+        return sections;
+    }
+
+    // The targets of the br_table at the start of run and the depth that traps
+    fn dispatch_table(module: &[u8]) -> (Vec<u64>, u64) {
+        let code = sections(module).into_iter().find(|&(id, _)| id == 10).unwrap().1;
+        let mut reader = Reader { bytes: &code, pos: 0 };
+        assert_eq!(reader.uleb(), 3);
+        for _ in 0..2 {
+            let size = reader.uleb() as usize;
+            reader.pos += size;
+        }
+        reader.uleb(); // size of run
+        assert_eq!((reader.uleb(), reader.uleb(), reader.byte()), (1, 2, TYPE_I64));
+        reader.expect(LOOP);
+        reader.expect(VOID);
+        let mut blocks = 0;
+        while code[reader.pos] == BLOCK {
+            reader.expect(BLOCK);
+            reader.expect(VOID);
+            blocks += 1;
+        }
+        reader.expect(LOCAL_GET);
+        assert_eq!(reader.uleb(), IP as u64);
+        reader.expect(I64_CONST);
+        reader.sleb();
+        reader.expect(I64_GE_U);
+        reader.expect(BR_IF);
+        let trap = reader.uleb();
+        assert_eq!(trap, blocks - 1);
+        reader.expect(LOCAL_GET);
+        reader.uleb();
+        reader.expect(I32_WRAP_I64);
+        reader.expect(BR_TABLE);
+        let count = reader.uleb();
+        let table: Vec<u64> = (0..count).map(|_| reader.uleb()).collect();
+        assert_eq!(reader.uleb(), trap);
+        return (table, trap);
+    }
+
+    #[test]
+    fn every_jump_target_is_dispatched() {
+        // jumps back to 2 through [61], which is in the middle of the first block
+        let mut program = parse_program("104,1,104,2,1001,62,1,62,1008,62,2,63,1005,63,19,106,0,61,0,99");
+        program.resize(64, 0);
+        program[61] = 2;
+        let analysis = Analysis::analyze(&program);
+        let (table, trap) = dispatch_table(&compile(&program).unwrap());
+        assert_eq!(table.len(), program.len());
+        assert_eq!(trap, analysis.leaders.len() as u64);
+        for &target in [0, 2, 19].iter() {
+            assert!(table[target] < trap, "{} is not dispatched", target);
+        }
+        for (address, &depth) in table.iter().enumerate() {
+            assert_eq!(depth < trap, analysis.leaders.contains(&address), "at {}", address);
+        }
+    }
+
+    #[test]
+    fn module_layout() {
+        let program = parse_program("3,9,1001,9,-5,9,4,9,99,0");
+        let module = compile(&program).unwrap();
+        let sections = sections(&module);
+        let ids: Vec<u8> = sections.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 5, 6, 7, 10, 11]);
+        let exports = &sections[5].1;
+        assert_eq!(exports, &b"\x02\x03run\x00\x04\x06memory\x02\x00".to_vec());
+        // code map, padded to 16 bytes, then the cells as little endian i64
+        let data = &sections[7].1;
+        let contents = &data[data.len() - (16 + 8 * program.len())..];
+        assert_eq!(&contents[..16], &[1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&contents[16 + 8 * 4..16 + 8 * 5], &(-5i64).to_le_bytes());
+    }
+
+    #[test]
+    fn halt_only() {
+        // checked with node: instantiates, run() returns without calling input or output
+        let sections = sections(&compile(&parse_program("99")).unwrap());
+        let code = &sections[6].1;
+        let run = &code[code.len() - 30..];
+        assert_eq!(run, &[
+            29, // size
+            1, 2, TYPE_I64, // ip, rb
+            LOOP, VOID, BLOCK, VOID, BLOCK, VOID,
+            LOCAL_GET, 0, I64_CONST, 1, I64_GE_U, BR_IF, 1, // past the end: trap
+            LOCAL_GET, 0, I32_WRAP_I64, BR_TABLE, 1, 0, 1,
+            END, RETURN, // the only block: 99
+            END, UNREACHABLE, END, END,
+        ][..]);
+        assert_eq!(&sections[7].1, &[1, 0, I32_CONST, 0, END, 16, 1, 0, 0, 0, 0, 0, 0, 0, 99, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,119 @@
+// Shared code to read the block letters some puzzles draw (day 8, day 11), included via:
+//   #[path = "../../ocr/rust/ocr.rs"]
+//   mod ocr;
+#![allow(dead_code)]
+
+pub const GLYPH_WIDTH: usize = 5; // 4 columns and a gap, only Y uses all 5
+pub const GLYPH_HEIGHT: usize = 6;
+
+// Every letter that has shown up so far
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 19] = [
+    ('A', [".##..", "#..#.", "#..#.", "####.", "#..#.", "#..#."]),
+    ('B', ["###..", "#..#.", "###..", "#..#.", "#..#.", "###.."]),
+    ('C', [".##..", "#..#.", "#....", "#....", "#..#.", ".##.."]),
+    ('E', ["####.", "#....", "###..", "#....", "#....", "####."]),
+    ('F', ["####.", "#....", "###..", "#....", "#....", "#...."]),
+    ('G', [".##..", "#..#.", "#....", "#.##.", "#..#.", ".###."]),
+    ('H', ["#..#.", "#..#.", "####.", "#..#.", "#..#.", "#..#."]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..##.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#..#.", "#.#..", "##...", "#.#..", "#.#..", "#..#."]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "####."]),
+    ('O', [".##..", "#..#.", "#..#.", "#..#.", "#..#.", ".##.."]),
+    ('P', ["###..", "#..#.", "#..#.", "###..", "#....", "#...."]),
+    ('R', ["###..", "#..#.", "#..#.", "###..", "#.#..", "#..#."]),
+    ('S', [".###.", "#....", "#....", ".##..", "...#.", "###.."]),
+    ('U', ["#..#.", "#..#.", "#..#.", "#..#.", "#..#.", ".##.."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####.", "...#.", "..#..", ".#...", "#....", "####."]),
+    (' ', [".....", ".....", ".....", ".....", ".....", "....."]),
+];
+
+fn glyph_at(pixels: &[Vec<bool>], left: usize) -> char {
+    let lit = |x: usize, y: usize| pixels[y].get(x).cloned().unwrap_or(false);
+    for &(letter, ref rows) in FONT.iter() {
+        let matches = rows.iter().enumerate().all(|(y, row)| {
+            return row.bytes().enumerate().all(|(x, c)| lit(left + x, y) == (c == b'#'));
+        });
+        if matches {
+            return letter;
+        }
+    }
+    return '?';
+}
+
+fn read_glyphs(pixels: &[Vec<bool>], width: usize, offset: usize) -> String {
+    let mut text = String::new();
+    let mut left = offset;
+    while left < width {
+        text.push(glyph_at(pixels, left));
+        left += GLYPH_WIDTH;
+    }
+    return text;
+}
+
+// The letters of an image with the lit pixels set, row by row. The letters don't have to
+// start at the edge: every offset within a glyph is tried (some letters like I start with
+// a blank column) and the one that recognizes the most letters wins. Unknown glyphs
+// become '?'.
+pub fn recognize(pixels: &[Vec<bool>]) -> String {
+    assert!(pixels.len() == GLYPH_HEIGHT, "Letters are {} pixels high, the image is {}", GLYPH_HEIGHT, pixels.len());
+    let width = pixels.iter().map(|row| row.len()).max().unwrap_or(0);
+    let letters = |text: &String| text.chars().filter(|&c| c != '?' && c != ' ').count();
+    let mut best = read_glyphs(pixels, width, 0);
+    for offset in 1..GLYPH_WIDTH {
+        let text = read_glyphs(pixels, width, offset);
+        if letters(&text) > letters(&best) {
+            best = text;
+        }
+    }
+    return best.trim().to_string();
+}
+
+// '#' is lit, anything else isn't
+pub fn parse_pixels(text: &str) -> Vec<Vec<bool>> {
+    return text.lines().map(|line| line.chars().map(|c| c == '#').collect()).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters() {
+        let image = parse_pixels("\
+#..#.####.#....###...##..
+#..#.#....#....#..#.#..#.
+####.###..#....#..#.#..#.
+#..#.#....#....###..#..#.
+#..#.#....#....#.#..#..#.
+#..#.####.####.#..#..##..");
+        assert_eq!(recognize(&image), "HELRO");
+        // shifted to the right, with a Y that uses the gap and something that isn't a letter
+        let image = parse_pixels("\
+..#...#####.#....
+..#...#...#.##...
+...#.#...#..#....
+....#...#...#....
+....#..#....#....
+....#..####.####.");
+        assert_eq!(recognize(&image), "YZ?");
+    }
+
+    #[test]
+    fn blank_first_column() {
+        let image = parse_pixels("\
+.###.#..#.
+..#..#..#.
+..#..####.
+..#..#..#.
+..#..#..#.
+.###.#..#.");
+        assert_eq!(recognize(&image), "IH");
+        // and shifted by more than a glyph
+        let shifted: Vec<Vec<bool>> = image.iter().map(|row| {
+            return vec![false; 7].into_iter().chain(row.iter().cloned()).collect();
+        }).collect();
+        assert_eq!(recognize(&shifted), "IH");
+    }
+}
@@ -0,0 +1,35 @@
+// Shared xorshift generator, good enough to make up programs and inputs for fuzzing and
+// benchmarks (same seed, same sequence). Included via:
+//   #[path = "../../rng/rust/rng.rs"]
+//   mod rng;
+#![allow(dead_code)]
+
+#[derive(Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        return Rng(if seed == 0 { 0x2545f4914f6cdd1d } else { seed });
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return self.0;
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        return self.next() % n;
+    }
+
+    // Inclusive
+    pub fn range(&mut self, min: i64, max: i64) -> i64 {
+        return min + self.below((max - min + 1) as u64) as i64;
+    }
+
+    pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.below(max_len as u64) as usize + 1;
+        return (0..len).map(|_| self.next() as u8).collect();
+    }
+}
@@ -0,0 +1,95 @@
+// Shared code for the Space Image Format (day 8): layers of digits, one after another and
+// row by row, 0 is black, 1 white and 2 transparent. Included via:
+//   #[path = "../../sif/rust/sif.rs"]
+//   mod sif;
+#![allow(dead_code)]
+
+pub const BLACK: u8 = 0;
+pub const WHITE: u8 = 1;
+pub const TRANSPARENT: u8 = 2;
+
+// The histograms (how often every digit appears on a layer) are counted while parsing
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+    pub histograms: Vec<[usize; 10]>,
+}
+
+impl Image {
+    // Whitespace (e.g. the trailing newline) is ignored
+    pub fn parse(text: &str, width: usize, height: usize) -> Result<Image, String> {
+        if width == 0 || height == 0 {
+            return Err(format!("Invalid size {}x{}", width, height));
+        }
+        let layer_size = width * height;
+        let mut image = Image { width: width, height: height, pixels: Vec::with_capacity(text.len()), histograms: Vec::new() };
+        for c in text.chars().filter(|c| !c.is_whitespace()) {
+            let digit = c.to_digit(10).ok_or(format!("Invalid pixel '{}'", c))? as u8;
+            if image.pixels.len() % layer_size == 0 {
+                image.histograms.push([0; 10]);
+            }
+            image.histograms.last_mut().unwrap()[digit as usize] += 1;
+            image.pixels.push(digit);
+        }
+        if image.pixels.len() % layer_size != 0 {
+            return Err(format!("{} pixels aren't a whole number of {}x{} layers", image.pixels.len(), width, height));
+        }
+        return Ok(image);
+    }
+
+    pub fn layer_size(&self) -> usize {
+        return self.width * self.height;
+    }
+
+    pub fn layer_count(&self) -> usize {
+        return self.pixels.len() / self.layer_size();
+    }
+
+    pub fn layer(&self, index: usize) -> &[u8] {
+        return &self.pixels[index * self.layer_size()..(index + 1) * self.layer_size()];
+    }
+
+    // Zeros and 1s * 2s of the (first) layer with the fewest zeros
+    pub fn checksum(&self) -> Option<(usize, usize)> {
+        let hist = self.histograms.iter().min_by_key(|hist| hist[0])?;
+        return Some((hist[0], hist[1] * hist[2]));
+    }
+
+    // The first pixel that isn't transparent, looking through the layers from the top
+    pub fn compose(&self) -> Vec<u8> {
+        let mut composed = vec![TRANSPARENT; self.layer_size()];
+        for layer in 0..self.layer_count() {
+            for (pixel, &layer_pixel) in composed.iter_mut().zip(self.layer(layer)) {
+                if *pixel == TRANSPARENT {
+                    *pixel = layer_pixel;
+                }
+            }
+        }
+        return composed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers() {
+        let image = Image::parse("123456789012\n", 3, 2).unwrap();
+        assert_eq!(image.layer_count(), 2);
+        assert_eq!(image.layer(1), &[7, 8, 9, 0, 1, 2]);
+        assert_eq!(image.histograms[0][1..7], [1, 1, 1, 1, 1, 1]);
+        assert_eq!(image.checksum(), Some((0, 1)));
+        assert!(Image::parse("1234567", 3, 2).is_err());
+        assert!(Image::parse("12a456", 3, 2).is_err());
+        assert_eq!(Image::parse("", 3, 2).unwrap().checksum(), None);
+    }
+
+    #[test]
+    fn compose() {
+        let image = Image::parse("0222112222120000", 2, 2).unwrap();
+        assert_eq!(image.compose(), vec![BLACK, WHITE, WHITE, BLACK]);
+        assert_eq!(Image::parse("2222", 2, 1).unwrap().compose(), vec![TRANSPARENT; 2]);
+    }
+}
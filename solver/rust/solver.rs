@@ -0,0 +1,37 @@
+// The interface every day implements, included via:
+//   #[path = "../../solver/rust/solver.rs"]
+//   mod solver;
+// Day 1 is the example to follow.
+#![allow(dead_code)]
+
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Read};
+
+pub trait Solver {
+    type Input;
+    type Output: Display;
+
+    // Panics on invalid input, like everything else here
+    fn parse(text: &str) -> Self::Input;
+    fn part1(input: &Self::Input) -> Self::Output;
+    fn part2(input: &Self::Input) -> Self::Output;
+}
+
+// The first argument that isn't an option, ../input by default and - means stdin
+pub fn read_input() -> String {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let filename = args.iter().find(|arg| !arg.starts_with("--")).map(|s| s.as_str()).unwrap_or("../input");
+    if filename == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).expect("Could not read stdin");
+        return text;
+    }
+    return fs::read_to_string(filename).unwrap_or_else(|err| panic!("Could not read {}: {}", filename, err));
+}
+
+pub fn solve<S: Solver>(text: &str) -> (S::Output, S::Output) {
+    let input = S::parse(text);
+    return (S::part1(&input), S::part2(&input));
+}
@@ -0,0 +1,29 @@
+// Shared code for the benchmarks, included via:
+//   #[path = "../../timing/rust/timing.rs"]
+//   mod timing;
+// Build them with optimizations (`rustc -O bench.rs`), debug builds are many times slower
+// and the ratios between them don't mean much.
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+pub fn warn_if_unoptimized() {
+    if cfg!(debug_assertions) {
+        eprintln!("Warning: built without optimizations, use `rustc -O`");
+    }
+}
+
+// The best time of `runs` runs and the result of the last one
+pub fn time<T>(runs: usize, f: &dyn Fn() -> T) -> (Duration, T) {
+    let mut best = Duration::from_secs(1000000);
+    let mut output = None;
+    for _ in 0..runs {
+        let start = Instant::now();
+        output = Some(f());
+        let elapsed = start.elapsed();
+        if elapsed < best {
+            best = elapsed;
+        }
+    }
+    return (best, output.expect("No runs"));
+}
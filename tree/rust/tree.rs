@@ -0,0 +1,189 @@
+// Shared code for trees stored as the parent of every node (like the orbit map of day 6),
+// included via:
+//   #[path = "../../tree/rust/tree.rs"]
+//   mod tree;
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+pub struct ParentTree<T: Eq + Hash + Clone> {
+    pub parents: HashMap<T, T>,
+}
+
+impl<T: Eq + Hash + Clone> ParentTree<T> {
+    pub fn new() -> ParentTree<T> {
+        return ParentTree { parents: HashMap::new() };
+    }
+
+    // Replaces the parent if the node had one already
+    pub fn add(&mut self, node: T, parent: T) {
+        self.parents.insert(node, parent);
+    }
+
+    pub fn parent(&self, node: &T) -> Option<&T> {
+        return self.parents.get(node);
+    }
+
+    // Nodes that are a parent but have none themselves
+    pub fn roots(&self) -> Vec<&T> {
+        let roots: HashSet<&T> = self.parents.values().filter(|&node| !self.parents.contains_key(node)).collect();
+        return roots.into_iter().collect();
+    }
+
+    // Some nodes that are their own ancestor, in the order parent pointers lead through them.
+    // ancestors(), depths() and everything built on them don't terminate if there is one.
+    pub fn find_cycle(&self) -> Option<Vec<&T>> {
+        let mut done: HashSet<&T> = HashSet::new();
+        for node in self.parents.keys() {
+            let mut path: Vec<&T> = Vec::new();
+            let mut on_path: HashMap<&T, usize> = HashMap::new();
+            let mut current = node;
+            while !done.contains(current) {
+                if let Some(&start) = on_path.get(current) {
+                    return Some(path.split_off(start));
+                }
+                on_path.insert(current, path.len());
+                path.push(current);
+                match self.parents.get(current) {
+                    Some(parent) => current = parent,
+                    None => break,
+                }
+            }
+            done.extend(path);
+        }
+        return None;
+    }
+
+    // Parent first, the root last. Iterative, so deep trees are no problem.
+    pub fn ancestors(&self, node: &T) -> Vec<&T> {
+        let mut chain = Vec::new();
+        let mut current = node;
+        while let Some(parent) = self.parents.get(current) {
+            chain.push(parent);
+            current = parent;
+        }
+        return chain;
+    }
+
+    // Depth of every node that has a parent (the roots are 0 and not included).
+    // Every node is walked up only until a node with a known depth, so this is O(n).
+    pub fn depths(&self) -> HashMap<&T, usize> {
+        let mut depths: HashMap<&T, usize> = HashMap::new();
+        for node in self.parents.keys() {
+            let mut path: Vec<&T> = Vec::new();
+            let mut current = node;
+            let mut depth = loop {
+                if let Some(&depth) = depths.get(current) {
+                    break depth;
+                }
+                match self.parents.get(current) {
+                    Some(parent) => {
+                        path.push(current);
+                        current = parent;
+                    },
+                    None => break 0, // a root
+                }
+            };
+            for node in path.into_iter().rev() {
+                depth += 1;
+                depths.insert(node, depth);
+            }
+        }
+        return depths;
+    }
+
+    // The deepest node that is a or b or an ancestor of both, None if they are in different trees
+    pub fn lowest_common_ancestor<'a>(&'a self, a: &'a T, b: &'a T) -> Option<&'a T> {
+        let mut chain_a: HashSet<&T> = self.ancestors(a).into_iter().collect();
+        chain_a.insert(a);
+        let mut current = b;
+        loop {
+            if chain_a.contains(current) {
+                return Some(current);
+            }
+            current = self.parents.get(current)?;
+        }
+    }
+
+    // From a up to the lowest common ancestor and down to b, both ends included
+    pub fn path<'a>(&'a self, a: &'a T, b: &'a T) -> Option<Vec<&'a T>> {
+        let ancestor = self.lowest_common_ancestor(a, b)?;
+        let mut up = Vec::new();
+        let mut current = a;
+        while current != ancestor {
+            up.push(current);
+            current = &self.parents[current];
+        }
+        up.push(ancestor);
+        let mut down = Vec::new();
+        current = b;
+        while current != ancestor {
+            down.push(current);
+            current = &self.parents[current];
+        }
+        up.extend(down.into_iter().rev());
+        return Some(up);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //     1
+    //    / \
+    //   2   3
+    //  / \   \
+    // 4   5   6
+    fn example() -> ParentTree<u32> {
+        let mut tree = ParentTree::new();
+        for &(node, parent) in [(2, 1), (3, 1), (4, 2), (5, 2), (6, 3)].iter() {
+            tree.add(node, parent);
+        }
+        return tree;
+    }
+
+    #[test]
+    fn ancestors_and_depths() {
+        let tree = example();
+        assert_eq!(tree.ancestors(&4), vec![&2, &1]);
+        assert_eq!(tree.ancestors(&1), Vec::<&u32>::new());
+        let depths = tree.depths();
+        assert_eq!((depths[&2], depths[&6], depths.get(&1)), (1, 2, None));
+        assert_eq!(depths.values().sum::<usize>(), 8);
+    }
+
+    #[test]
+    fn common_ancestors() {
+        let mut tree = example();
+        assert_eq!(tree.lowest_common_ancestor(&4, &5), Some(&2));
+        assert_eq!(tree.lowest_common_ancestor(&4, &6), Some(&1));
+        assert_eq!(tree.lowest_common_ancestor(&2, &5), Some(&2));
+        assert_eq!(tree.path(&4, &6), Some(vec![&4, &2, &1, &3, &6]));
+        assert_eq!(tree.path(&5, &2), Some(vec![&5, &2]));
+        assert_eq!(tree.path(&2, &5), Some(vec![&2, &5]));
+        assert_eq!(tree.path(&4, &4), Some(vec![&4]));
+        tree.add(8, 7);
+        assert_eq!(tree.lowest_common_ancestor(&4, &8), None);
+    }
+
+    #[test]
+    fn roots_and_cycles() {
+        let mut tree = example();
+        assert_eq!(tree.roots(), vec![&1]);
+        assert_eq!(tree.find_cycle(), None);
+        tree.add(8, 7);
+        let mut roots = tree.roots();
+        roots.sort();
+        assert_eq!(roots, vec![&1, &7]);
+        tree.add(7, 9);
+        tree.add(9, 8);
+        let mut cycle = tree.find_cycle().unwrap();
+        cycle.sort();
+        assert_eq!(cycle, vec![&7, &8, &9]);
+        let mut looped = example();
+        looped.add(1, 1);
+        assert_eq!(looped.find_cycle(), Some(vec![&1]));
+    }
+}